@@ -1,34 +1,16 @@
 #[cfg(test)]
 mod tests {
-    use ark_std::{end_timer, start_timer};
     use blake2f_circuit::dev::{Blake2fTestCircuit, INPUTS_OUTPUTS};
-    use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof};
-    use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG, ParamsVerifierKZG};
-    use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
-    use halo2_proofs::poly::kzg::strategy::SingleStrategy;
-    use halo2_proofs::{
-        halo2curves::bn256::{Bn256, Fr, G1Affine},
-        poly::commitment::ParamsProver,
-        transcript::{
-            Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
-        },
-    };
-    use rand::SeedableRng;
-    use rand_xorshift::XorShiftRng;
-    use std::{env::var, marker::PhantomData};
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use std::marker::PhantomData;
 
-    use crate::constants::{PROOFGEN_PREFIX, PROOFVER_PREFIX, SETUP_PREFIX};
+    use crate::harness::bench_circuit;
 
     #[test]
     fn bench_blake2f_circuit() {
         // Unique string used by bench results module for parsing the result.
         const BENCHMARK_ID: &str = "BLAKE2 Compression Function Circuit";
 
-        let degree: u32 = var("DEGREE")
-            .expect("No DEGREE env var was provided")
-            .parse()
-            .expect("Cannot parse DEGREE env var as u32");
-
         // Create BLAKE2F circuit with some test vectors.
         let (inputs, outputs) = INPUTS_OUTPUTS.clone();
         let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit {
@@ -37,67 +19,6 @@ mod tests {
             _marker: PhantomData,
         };
 
-        // Initialize the polynomial commitment parameters.
-        let mut rng = XorShiftRng::from_seed([
-            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
-            0xbc, 0xe5,
-        ]);
-
-        // Bench setup generation.
-        let setup_message = format!("{} {} with degree = {}", BENCHMARK_ID, SETUP_PREFIX, degree);
-        let start1 = start_timer!(|| setup_message);
-        let general_params = ParamsKZG::<Bn256>::setup(degree, &mut rng);
-        let verifier_params: ParamsVerifierKZG<Bn256> = general_params.verifier_params().clone();
-        end_timer!(start1);
-
-        // Initialize the proving/verifying key.
-        let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
-        let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
-        let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
-
-        // Bench proof generation time.
-        let proof_message = format!(
-            "{} {} with degree = {}",
-            BENCHMARK_ID, PROOFGEN_PREFIX, degree
-        );
-        let start2 = start_timer!(|| proof_message);
-        create_proof::<
-            KZGCommitmentScheme<Bn256>,
-            ProverSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            XorShiftRng,
-            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-            Blake2fTestCircuit<Fr>,
-        >(
-            &general_params,
-            &pk,
-            &[circuit],
-            &[&[]],
-            rng,
-            &mut transcript,
-        )
-        .expect("proof generation should not fail");
-        let proof = transcript.finalize();
-        end_timer!(start2);
-
-        // Bench verification time.
-        let start3 = start_timer!(|| format!("{} {}", BENCHMARK_ID, PROOFVER_PREFIX));
-        let mut verifier_transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
-        let strategy = SingleStrategy::new(&general_params);
-        verify_proof::<
-            KZGCommitmentScheme<Bn256>,
-            VerifierSHPLONK<'_, Bn256>,
-            Challenge255<G1Affine>,
-            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
-            SingleStrategy<'_, Bn256>,
-        >(
-            &verifier_params,
-            pk.get_vk(),
-            strategy,
-            &[&[]],
-            &mut verifier_transcript,
-        )
-        .expect("failed to verify bench circuit");
-        end_timer!(start3);
+        bench_circuit(BENCHMARK_ID, "blake2f", circuit);
     }
 }
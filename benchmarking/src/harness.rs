@@ -0,0 +1,133 @@
+//! The benchmark of a circuit, which times the generation of the params, the proof and its
+//! verification in the same way for every circuit.
+
+use ark_std::{end_timer, start_timer};
+use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, Circuit};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG, ParamsVerifierKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverSHPLONK, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    poly::commitment::ParamsProver,
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use prover::keys::{load_or_keygen, load_or_setup_params};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use std::{env::var, fs, path::PathBuf};
+
+use crate::constants::{PROOFGEN_PREFIX, PROOFVER_PREFIX, SETUP_PREFIX};
+use crate::report::{measure, report, Phase};
+
+/// Benchmarks `circuit` with `2^DEGREE` rows. `benchmark_id` is the unique string by which
+/// the results are parsed, and `name` names the files of its keys. If the `PARAMS_DIR` env
+/// var is set, the params and the proving key are read from that directory, or generated
+/// and written to it on the first run, in which case the records of the phases that read
+/// them are flagged as cached. The records of the phases are reported through [`report`].
+pub fn bench_circuit<C: Circuit<Fr>>(benchmark_id: &str, name: &str, circuit: C) {
+    let degree: u32 = var("DEGREE")
+        .expect("No DEGREE env var was provided")
+        .parse()
+        .expect("Cannot parse DEGREE env var as u32");
+    let params_dir = var("PARAMS_DIR").ok().map(PathBuf::from);
+    if let Some(dir) = &params_dir {
+        fs::create_dir_all(dir).expect("cannot create PARAMS_DIR");
+    }
+
+    // Initialize the polynomial commitment parameters.
+    let mut rng = XorShiftRng::from_seed([
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ]);
+
+    // Bench setup generation.
+    let setup_message = format!("{} {} with degree = {}", benchmark_id, SETUP_PREFIX, degree);
+    let start1 = start_timer!(|| setup_message);
+    let params_path = params_dir
+        .as_ref()
+        .map(|dir| dir.join(format!("params_{}.bin", degree)));
+    let params_cached = params_path.as_ref().is_some_and(|path| path.exists());
+    let (general_params, mut setup_record) =
+        measure(name, degree, Phase::Setup, || match &params_path {
+            Some(path) => load_or_setup_params(path, degree, &mut rng)
+                .expect("cannot read or write the params"),
+            None => ParamsKZG::<Bn256>::setup(degree, &mut rng),
+        });
+    setup_record.cached = params_cached;
+    let verifier_params: ParamsVerifierKZG<Bn256> = general_params.verifier_params().clone();
+    end_timer!(start1);
+
+    // Initialize the proving/verifying key. A key written with its fingerprint is flagged as
+    // cached even if the fingerprint no longer matches and the key is generated again, which
+    // only leaves the phase out of the comparison with the baseline.
+    let pk_path = params_dir
+        .as_ref()
+        .map(|dir| dir.join(format!("{}_{}.pk", name, degree)));
+    let pk_cached = pk_path
+        .as_ref()
+        .is_some_and(|path| path.exists() && path.with_extension("fingerprint").exists());
+    let (pk, mut keygen_record) = measure(name, degree, Phase::Keygen, || match &pk_path {
+        Some(path) => load_or_keygen(&general_params, &circuit, path)
+            .expect("cannot read or write the proving key"),
+        None => {
+            let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
+            keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail")
+        }
+    });
+    keygen_record.cached = pk_cached;
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+
+    // Bench proof generation time.
+    let proof_message = format!(
+        "{} {} with degree = {}",
+        benchmark_id, PROOFGEN_PREFIX, degree
+    );
+    let start2 = start_timer!(|| proof_message);
+    let (proof, mut prove_record) = measure(name, degree, Phase::Prove, || {
+        create_proof::<
+            KZGCommitmentScheme<Bn256>,
+            ProverSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            XorShiftRng,
+            Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+            C,
+        >(
+            &general_params,
+            &pk,
+            &[circuit],
+            &[&[]],
+            rng,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    });
+    prove_record.proof_size = Some(proof.len());
+    end_timer!(start2);
+
+    // Bench verification time.
+    let start3 = start_timer!(|| format!("{} {}", benchmark_id, PROOFVER_PREFIX));
+    let mut verifier_transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+    let strategy = SingleStrategy::new(&general_params);
+    let ((), verify_record) = measure(name, degree, Phase::Verify, || {
+        verify_proof::<
+            KZGCommitmentScheme<Bn256>,
+            VerifierSHPLONK<'_, Bn256>,
+            Challenge255<G1Affine>,
+            Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+            SingleStrategy<'_, Bn256>,
+        >(
+            &verifier_params,
+            pk.get_vk(),
+            strategy,
+            &[&[]],
+            &mut verifier_transcript,
+        )
+        .expect("failed to verify bench circuit")
+    });
+    end_timer!(start3);
+
+    report(&[setup_record, keygen_record, prove_record, verify_record]);
+}
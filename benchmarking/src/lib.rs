@@ -1,11 +1,17 @@
-// #[cfg(test)]
-// pub mod blake2f_circuit_bench;
+#[cfg(test)]
+pub mod blake2f_circuit_bench;
 
 #[cfg(test)]
 pub mod ripemd160_circuit_bench;
 
-// #[cfg(test)]
-// pub mod sha2_256_circuit_bench;
+#[cfg(test)]
+pub mod sha2_256_circuit_bench;
 
 #[cfg(test)]
 mod constants;
+
+#[cfg(test)]
+mod harness;
+
+#[cfg(test)]
+mod report;
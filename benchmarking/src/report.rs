@@ -0,0 +1,391 @@
+//! Machine-readable records of the benchmarks, and their comparison with a baseline.
+//!
+//! Each phase of a benchmark gives a [`Record`] of its wall time, the peak memory of the
+//! process and, for the proof, its size. If the `BENCH_OUTPUT` env var is set, the records
+//! are appended to that file, as CSV if its extension is `.csv` and as JSON lines
+//! otherwise. If `BENCH_BASELINE` is set, the records are compared with the ones of the
+//! same circuit, `k` and phase in that file, and the benchmark fails when a phase is slower,
+//! uses more memory or gives a larger proof than the baseline by more than
+//! `BENCH_THRESHOLD` percent, 10 by default, or when a phase has no record in the baseline.
+//!
+//! A phase that reads its params or proving key from `PARAMS_DIR` times the read of a file
+//! rather than their generation, so its record is flagged as cached and is not compared.
+//!
+//! The peak memory is the peak resident set of the process, which is reset before each
+//! phase, so the benchmarks should run with `--test-threads=1`. It is only known on Linux.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    env::var,
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::Instant,
+};
+
+/// The default threshold of the regressions, in percent
+const DEFAULT_THRESHOLD: f64 = 10.0;
+
+const CSV_HEADER: &str = "circuit,k,phase,wall_time_ms,peak_memory_kb,proof_size,cached";
+
+/// Serialises the writes of the benchmarks, which run in parallel by default
+static OUTPUT: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Setup,
+    Keygen,
+    Prove,
+    Verify,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            Phase::Setup => "setup",
+            Phase::Keygen => "keygen",
+            Phase::Prove => "prove",
+            Phase::Verify => "verify",
+        };
+        write!(f, "{}", phase)
+    }
+}
+
+impl FromStr for Phase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "setup" => Ok(Phase::Setup),
+            "keygen" => Ok(Phase::Keygen),
+            "prove" => Ok(Phase::Prove),
+            "verify" => Ok(Phase::Verify),
+            _ => Err(format!("unknown phase {}", s)),
+        }
+    }
+}
+
+/// The measurements of a phase of a benchmark
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub circuit: String,
+    pub k: u32,
+    pub phase: Phase,
+    pub wall_time_ms: f64,
+    pub peak_memory_kb: Option<u64>,
+    /// The size of the proof in bytes, for the proof generation
+    pub proof_size: Option<usize>,
+    /// Whether the phase read its result from `PARAMS_DIR` rather than generate it
+    #[serde(default)]
+    pub cached: bool,
+}
+
+impl Record {
+    fn to_csv(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_default();
+        format!(
+            "{},{},{},{:.3},{},{},{}",
+            self.circuit,
+            self.k,
+            self.phase,
+            self.wall_time_ms,
+            optional(self.peak_memory_kb.map(|kb| kb.to_string())),
+            optional(self.proof_size.map(|size| size.to_string())),
+            self.cached,
+        )
+    }
+
+    fn from_csv(line: &str) -> Result<Self, String> {
+        let fields: Vec<_> = line.split(',').map(str::trim).collect();
+        if fields.len() != 7 {
+            return Err(format!("expected 7 fields in {:?}", line));
+        }
+        let optional = |field: &str| -> Result<Option<u64>, String> {
+            if field.is_empty() {
+                Ok(None)
+            } else {
+                field.parse().map(Some).map_err(|err| format!("{}", err))
+            }
+        };
+        Ok(Record {
+            circuit: fields[0].to_string(),
+            k: fields[1].parse().map_err(|err| format!("{}", err))?,
+            phase: fields[2].parse()?,
+            wall_time_ms: fields[3].parse().map_err(|err| format!("{}", err))?,
+            peak_memory_kb: optional(fields[4])?,
+            proof_size: optional(fields[5])?.map(|size| size as usize),
+            cached: fields[6].parse().map_err(|err| format!("{}", err))?,
+        })
+    }
+}
+
+/// Runs a phase of a benchmark, measuring its wall time and peak memory
+pub fn measure<T>(circuit: &str, k: u32, phase: Phase, f: impl FnOnce() -> T) -> (T, Record) {
+    reset_peak_memory();
+    let start = Instant::now();
+    let result = f();
+    let wall_time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let record = Record {
+        circuit: circuit.to_string(),
+        k,
+        phase,
+        wall_time_ms,
+        peak_memory_kb: peak_memory_kb(),
+        proof_size: None,
+        cached: false,
+    };
+    (result, record)
+}
+
+/// Resets the peak resident set of the process, on Linux
+fn reset_peak_memory() {
+    let _ = fs::write("/proc/self/clear_refs", "5");
+}
+
+/// Returns the peak resident set of the process in kB, on Linux
+fn peak_memory_kb() -> Option<u64> {
+    fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn is_csv(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "csv")
+}
+
+/// Appends the records to `path`, writing the CSV header to a new file
+pub fn write_records(path: &Path, records: &[Record]) -> io::Result<()> {
+    let _lock = OUTPUT.lock().unwrap();
+    let is_new = fs::metadata(path).map_or(true, |metadata| metadata.len() == 0);
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for (i, record) in records.iter().enumerate() {
+        if is_csv(path) {
+            if is_new && i == 0 {
+                writeln!(file, "{}", CSV_HEADER)?;
+            }
+            writeln!(file, "{}", record.to_csv())?;
+        } else {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the records of a CSV or JSON lines file
+pub fn read_records(path: &Path) -> io::Result<Vec<Record>> {
+    let invalid = |err: String| io::Error::new(io::ErrorKind::InvalidData, err);
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter(|line| *line != CSV_HEADER)
+        .map(|line| {
+            if is_csv(path) {
+                Record::from_csv(line).map_err(invalid)
+            } else {
+                serde_json::from_str(line).map_err(io::Error::from)
+            }
+        })
+        .collect()
+}
+
+/// The outcome of the comparison of records with a baseline, naming each record by its
+/// circuit, `k` and phase
+#[derive(Debug, Default)]
+pub struct Comparison {
+    /// The regressions by more than the threshold
+    pub regressions: Vec<String>,
+    /// The records that have no baseline
+    pub missing: Vec<String>,
+    /// The records that were not compared, as they or their baseline are cached
+    pub cached: Vec<String>,
+}
+
+/// Compares the records with the baseline, with a threshold in percent
+pub fn compare(records: &[Record], baseline: &[Record], threshold: f64) -> Comparison {
+    let mut comparison = Comparison::default();
+    for record in records {
+        let name = format!("{} k={} {}", record.circuit, record.k, record.phase);
+        let base = baseline.iter().find(|base| {
+            base.circuit == record.circuit && base.k == record.k && base.phase == record.phase
+        });
+        let base = match base {
+            Some(base) => base,
+            None => {
+                comparison.missing.push(name);
+                continue;
+            }
+        };
+        if record.cached || base.cached {
+            comparison.cached.push(name);
+            continue;
+        }
+
+        let mut check = |metric: &str, value: f64, base: f64| {
+            let limit = base * (1.0 + threshold / 100.0);
+            if value > limit {
+                comparison.regressions.push(format!(
+                    "{}: {} is {:.1}, {:.1}% above the baseline of {:.1}",
+                    name,
+                    metric,
+                    value,
+                    (value / base - 1.0) * 100.0,
+                    base
+                ));
+            }
+        };
+        check("wall_time_ms", record.wall_time_ms, base.wall_time_ms);
+        if let (Some(value), Some(base)) = (record.peak_memory_kb, base.peak_memory_kb) {
+            check("peak_memory_kb", value as f64, base as f64);
+        }
+        if let (Some(value), Some(base)) = (record.proof_size, base.proof_size) {
+            check("proof_size", value as f64, base as f64);
+        }
+    }
+    comparison
+}
+
+/// Writes the records to `BENCH_OUTPUT` and compares them with `BENCH_BASELINE`, if set,
+/// panicking on a regression or on a record that has no baseline
+pub fn report(records: &[Record]) {
+    if let Ok(path) = var("BENCH_OUTPUT") {
+        write_records(Path::new(&path), records).expect("cannot write BENCH_OUTPUT");
+    }
+
+    if let Ok(path) = var("BENCH_BASELINE") {
+        let baseline = read_records(Path::new(&path)).expect("cannot read BENCH_BASELINE");
+        let threshold = var("BENCH_THRESHOLD").map_or(DEFAULT_THRESHOLD, |threshold| {
+            threshold
+                .parse()
+                .expect("Cannot parse BENCH_THRESHOLD env var as f64")
+        });
+        let comparison = compare(records, &baseline, threshold);
+        for name in comparison.cached {
+            println!("not compared, as it is cached: {}", name);
+        }
+        assert!(
+            comparison.missing.is_empty(),
+            "no baseline in {} for:\n{}",
+            path,
+            comparison.missing.join("\n")
+        );
+        assert!(
+            comparison.regressions.is_empty(),
+            "regressions beyond {}%:\n{}",
+            threshold,
+            comparison.regressions.join("\n")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs, path::PathBuf};
+
+    use super::{compare, read_records, write_records, Phase, Record};
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record {
+                circuit: "ripemd160".to_string(),
+                k: 17,
+                phase: Phase::Prove,
+                wall_time_ms: 20_000.0,
+                peak_memory_kb: Some(2_000_000),
+                proof_size: Some(4_000),
+                cached: false,
+            },
+            Record {
+                circuit: "ripemd160".to_string(),
+                k: 17,
+                phase: Phase::Verify,
+                wall_time_ms: 30.0,
+                peak_memory_kb: None,
+                proof_size: None,
+                cached: false,
+            },
+            Record {
+                circuit: "ripemd160".to_string(),
+                k: 17,
+                phase: Phase::Keygen,
+                wall_time_ms: 500.0,
+                peak_memory_kb: Some(1_000_000),
+                proof_size: None,
+                cached: true,
+            },
+        ]
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path = temp_dir().join(format!("{}-{}", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for name in ["records.csv", "records.json"] {
+            let path = temp_file(name);
+            write_records(&path, &records()).unwrap();
+            write_records(&path, &records()[..1]).unwrap();
+            let mut expected = records();
+            expected.push(records()[0].clone());
+            assert_eq!(read_records(&path).unwrap(), expected);
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let baseline = records();
+        let mut records = records();
+        records[0].wall_time_ms *= 1.05;
+        records[1].wall_time_ms *= 1.5;
+        let comparison = compare(&records, &baseline, 10.0);
+        assert_eq!(comparison.regressions.len(), 1);
+        assert!(comparison.regressions[0].starts_with("ripemd160 k=17 verify: wall_time_ms"));
+        assert!(comparison.missing.is_empty());
+
+        // A larger proof is a regression, and a phase without baseline is listed apart
+        records[0].proof_size = Some(5_000);
+        let comparison = compare(&records, &baseline[..1], 50.0);
+        assert!(comparison.regressions.is_empty());
+        assert_eq!(
+            comparison.missing,
+            vec!["ripemd160 k=17 verify", "ripemd160 k=17 keygen"]
+        );
+        let comparison = compare(&records, &baseline, 10.0);
+        assert!(comparison
+            .regressions
+            .iter()
+            .any(|r| r.contains("proof_size")));
+    }
+
+    #[test]
+    fn test_compare_cached() {
+        // A phase that read a cached key is not compared with a baseline that generated it,
+        // nor the other way around
+        let baseline = records();
+        let mut records = records();
+        records[2].wall_time_ms *= 100.0;
+        let comparison = compare(&records, &baseline, 10.0);
+        assert!(comparison.regressions.is_empty());
+        assert_eq!(comparison.cached, vec!["ripemd160 k=17 keygen"]);
+
+        let mut baseline = baseline;
+        baseline[2].cached = false;
+        records[2].cached = false;
+        assert_eq!(compare(&records, &baseline, 10.0).regressions.len(), 1);
+        records[2].cached = true;
+        assert!(compare(&records, &baseline, 10.0).regressions.is_empty());
+    }
+}
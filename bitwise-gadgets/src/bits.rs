@@ -0,0 +1,224 @@
+//! Cells holding little-endian bit-arrays.
+
+use std::convert::TryInto;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Region, Value},
+    plonk::{Advice, Assigned, Column},
+};
+
+use crate::{
+    tamper::{self, Tamperable},
+    util::{i2lebsp, lebs2ip, spread_bits},
+    Error,
+};
+
+/// Little-endian bits (up to 64 bits)
+#[derive(Debug, Clone)]
+pub struct Bits<const LEN: usize>([bool; LEN]);
+
+impl<const LEN: usize> Bits<LEN> {
+    /// Returns the spread form of the bits
+    pub fn spread<const SPREAD: usize>(&self) -> [bool; SPREAD] {
+        spread_bits(self.0)
+    }
+}
+
+impl<const LEN: usize> std::ops::Deref for Bits<LEN> {
+    type Target = [bool; LEN];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const LEN: usize> From<[bool; LEN]> for Bits<LEN> {
+    fn from(bits: [bool; LEN]) -> Self {
+        Self(bits)
+    }
+}
+
+impl<const LEN: usize> From<&Bits<LEN>> for [bool; LEN] {
+    fn from(bits: &Bits<LEN>) -> Self {
+        bits.0
+    }
+}
+
+impl<const LEN: usize, F: FieldExt> From<&Bits<LEN>> for Assigned<F> {
+    fn from(bits: &Bits<LEN>) -> Self {
+        assert!(LEN <= 64);
+        F::from(lebs2ip(&bits.0)).into()
+    }
+}
+
+impl<const LEN: usize, F: FieldExt> From<Bits<LEN>> for Assigned<F> {
+    fn from(bits: Bits<LEN>) -> Self {
+        (&bits).into()
+    }
+}
+
+impl From<&Bits<16>> for u16 {
+    fn from(bits: &Bits<16>) -> Self {
+        lebs2ip(&bits.0) as u16
+    }
+}
+
+impl From<u16> for Bits<16> {
+    fn from(value: u16) -> Self {
+        Bits(i2lebsp::<16>(value.into()))
+    }
+}
+
+impl From<&Bits<32>> for u32 {
+    fn from(bits: &Bits<32>) -> Self {
+        lebs2ip(&bits.0) as u32
+    }
+}
+
+impl From<u32> for Bits<32> {
+    fn from(value: u32) -> Self {
+        Bits(i2lebsp::<32>(value.into()))
+    }
+}
+
+/// A cell holding a `LEN`-bit value
+#[derive(Debug, Clone)]
+pub struct AssignedBits<const LEN: usize, F: FieldExt>(pub(crate) AssignedCell<Bits<LEN>, F>);
+
+impl<const LEN: usize, F: FieldExt> std::ops::Deref for AssignedBits<LEN, F> {
+    type Target = AssignedCell<Bits<LEN>, F>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<const LEN: usize, F: FieldExt> AssignedBits<LEN, F> {
+    /// Assigns a value to an advice cell, returning [`Error::BitLength`] if the value does
+    /// not fit in `LEN` bits
+    pub fn assign_bits<A, AR, T: TryInto<[bool; LEN]> + std::fmt::Debug + Clone>(
+        region: &mut Region<'_, F>,
+        annotation: A,
+        column: Column<Advice>,
+        offset: usize,
+        value: Value<T>,
+    ) -> Result<Self, Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        let mut bit_length = Ok(());
+        let value: Value<[bool; LEN]> = value.map(|v| {
+            v.clone().try_into().unwrap_or_else(|_| {
+                bit_length = Err(Error::BitLength {
+                    bits: LEN,
+                    value: format!("{:?}", v),
+                });
+                [false; LEN]
+            })
+        });
+        bit_length?;
+
+        Self::assign_cell(region, annotation, column, offset, value)
+    }
+
+    /// Assigns a value to an advice cell, which the soundness tests of the hash circuits
+    /// can override through [`tamper::apply`]
+    fn assign_cell<A, AR, T: Tamperable + Into<Bits<LEN>>>(
+        region: &mut Region<'_, F>,
+        annotation: A,
+        column: Column<Advice>,
+        offset: usize,
+        value: Value<T>,
+    ) -> Result<Self, Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        let to = || tamper::apply(&annotation, value).map(Into::into);
+        Ok(AssignedBits(region.assign_advice(
+            &annotation,
+            column,
+            offset,
+            to,
+        )?))
+    }
+
+    /// Copies this cell to the given advice cell, constraining both to be equal
+    pub fn copy_advice<A, AR>(
+        &self,
+        annotation: A,
+        region: &mut Region<'_, F>,
+        column: Column<Advice>,
+        offset: usize,
+    ) -> Result<Self, Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        Ok(AssignedBits(
+            self.0.copy_advice(annotation, region, column, offset)?,
+        ))
+    }
+}
+
+impl<F: FieldExt> AssignedBits<16, F> {
+    pub fn value_u16(&self) -> Value<u16> {
+        self.value().map(|v| v.into())
+    }
+
+    pub fn assign<A, AR>(
+        region: &mut Region<'_, F>,
+        annotation: A,
+        column: Column<Advice>,
+        offset: usize,
+        value: Value<u16>,
+    ) -> Result<Self, Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        Self::assign_cell(region, annotation, column, offset, value)
+    }
+}
+
+impl<F: FieldExt> AssignedBits<32, F> {
+    pub fn value_u32(&self) -> Value<u32> {
+        self.value().map(|v| v.into())
+    }
+
+    pub fn assign<A, AR>(
+        region: &mut Region<'_, F>,
+        annotation: A,
+        column: Column<Advice>,
+        offset: usize,
+        value: Value<u32>,
+    ) -> Result<Self, Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        Self::assign_cell(region, annotation, column, offset, value)
+    }
+
+    /// Assigns a constant to an advice cell, constraining it to equal a fixed cell
+    pub fn assign_constant<A, AR>(
+        region: &mut Region<'_, F>,
+        annotation: A,
+        column: Column<Advice>,
+        offset: usize,
+        value: u32,
+    ) -> Result<Self, Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        Ok(AssignedBits(region.assign_advice_from_constant(
+            annotation,
+            column,
+            offset,
+            Bits::<32>::from(value),
+        )?))
+    }
+}
@@ -0,0 +1,899 @@
+//! Bitwise operations on 32-bit words over the spread table.
+//!
+//! Every word is kept along with its 16-bit halves, which are looked up in the spread table
+//! together with their spread forms. This range checks the word, and lets XOR and AND read
+//! the spread forms of their inputs without looking them up again.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter, Region, Value},
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Fixed, Selector},
+    poly::Rotation,
+};
+
+use crate::{
+    error::assign_region, util::i2lebsp, AssignedBits, Error, SpreadTable, SpreadTableChip,
+    SpreadTableConfig, SpreadVar, SpreadWord,
+};
+
+/// Rows taken by [`BitwiseChip::assign_word`], [`BitwiseChip::assign_constant`],
+/// [`BitwiseChip::decompose`] and [`BitwiseChip::not`]
+pub const WORD_ROWS: usize = 2;
+/// Rows taken by [`BitwiseChip::xor_and`], [`BitwiseChip::xor`] and [`BitwiseChip::and`]
+pub const XOR_AND_ROWS: usize = 4;
+/// Rows taken by a rotation
+pub const ROTATE_ROWS: usize = 6;
+/// Rows taken by a shift by a nonzero number of bits, which masks out the bits rotated in
+pub const SHIFT_ROWS: usize = ROTATE_ROWS + WORD_ROWS + XOR_AND_ROWS;
+/// Rows taken by an addition, with or without a carry in
+pub const ADD_ROWS: usize = 3;
+
+/// A 32-bit word along with its 16-bit halves
+#[derive(Clone, Debug)]
+pub struct Word32<F: FieldExt> {
+    pub word: AssignedBits<32, F>,
+    pub lo: SpreadVar<16, 32, F>,
+    pub hi: SpreadVar<16, 32, F>,
+}
+
+impl<F: FieldExt> Word32<F> {
+    pub fn value(&self) -> Value<u32> {
+        self.word.value_u32()
+    }
+}
+
+/// Configuration of [`BitwiseChip`]
+#[derive(Clone, Debug)]
+pub struct BitwiseConfig<F: FieldExt> {
+    pub(crate) lookup: SpreadTableConfig,
+    pub(crate) advice: [Column<Advice>; 2],
+    // Shift-dependent coefficients of the rotations
+    coeffs: Column<Fixed>,
+
+    s_word: Selector,
+    s_xor_and: Selector,
+    s_not: Selector,
+    s_rotate_left: Selector,
+    s_add: Selector,
+    s_add_carry: Selector,
+
+    _marker: PhantomData<F>,
+}
+
+/// A chip for XOR, AND, OR, NOT, rotations, shifts and modular addition of 32-bit words
+#[derive(Clone, Debug)]
+pub struct BitwiseChip<F: FieldExt> {
+    config: BitwiseConfig<F>,
+}
+
+impl<F: FieldExt> Chip<F> for BitwiseChip<F> {
+    type Config = BitwiseConfig<F>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: FieldExt> BitwiseChip<F> {
+    /// Reconstructs this chip from the given config.
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self { config }
+    }
+
+    /// Configure a circuit to include this chip.
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> <Self as Chip<F>>::Config {
+        Self::configure_with(meta, None)
+    }
+
+    /// Configure a circuit to include this chip, looking up a spread table shared with
+    /// other chips. The circuit loads the table with [`SpreadTable::load`], and
+    /// [`load`](Self::load) does nothing.
+    pub fn configure_with_spread_table(
+        meta: &mut ConstraintSystem<F>,
+        table: &SpreadTable,
+    ) -> <Self as Chip<F>>::Config {
+        Self::configure_with(meta, Some(table))
+    }
+
+    fn configure_with(
+        meta: &mut ConstraintSystem<F>,
+        table: Option<&SpreadTable>,
+    ) -> <Self as Chip<F>>::Config {
+        // Three advice columns to interact with the lookup table
+        let input_tag = meta.advice_column();
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+        let lookup = match table {
+            Some(table) => SpreadTableChip::configure_with_table(
+                meta,
+                table,
+                input_tag,
+                input_dense,
+                input_spread,
+            ),
+            None => SpreadTableChip::configure(meta, input_tag, input_dense, input_spread),
+        };
+
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let coeffs = meta.fixed_column();
+
+        // Fixed column for constants such as the masks of the shifts
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        // Rename these here for ease of matching the gates to their layouts
+        let a_1 = input_dense;
+        let a_2 = input_spread;
+        let [a_3, a_4] = advice;
+
+        for column in [a_1, a_2, a_3, a_4] {
+            meta.enable_equality(column);
+        }
+
+        let s_word = meta.selector();
+        let s_xor_and = meta.selector();
+        let s_not = meta.selector();
+        let s_rotate_left = meta.selector();
+        let s_add = meta.selector();
+        let s_add_carry = meta.selector();
+
+        // s_word decomposes a word into its halves, which the lookup range checks
+        // s_word | a_0    | a_1 | a_2       | a_3  |
+        //   1    | tag_lo | lo  | spread_lo | word |
+        //        | tag_hi | hi  | spread_hi |      |
+        meta.create_gate("s_word", |meta| {
+            let s_word = meta.query_selector(s_word);
+            let lo = meta.query_advice(a_1, Rotation::cur());
+            let hi = meta.query_advice(a_1, Rotation::next());
+            let word = meta.query_advice(a_3, Rotation::cur());
+
+            Constraints::with_selector(s_word, [("word_check", lo + hi * F::from(1 << 16) - word)])
+        });
+
+        // s_xor_and splits the sums of the spread halves of X and Y into their even bits,
+        // which are the bits of X ^ Y, and their odd bits, which are the bits of X & Y
+        // s_xor_and | a_0 | a_1     | a_2            | a_3         | a_4   |
+        //   1       | tag | even_lo | spread_even_lo | spread_x_lo | X ^ Y |
+        //           | tag | odd_lo  | spread_odd_lo  | spread_y_lo | X & Y |
+        //           | tag | even_hi | spread_even_hi | spread_x_hi |       |
+        //           | tag | odd_hi  | spread_odd_hi  | spread_y_hi |       |
+        meta.create_gate("s_xor_and", |meta| {
+            let s_xor_and = meta.query_selector(s_xor_and);
+            let dense: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_1, Rotation(idx)))
+                .collect();
+            let spread: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_2, Rotation(idx)))
+                .collect();
+            let inputs: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_3, Rotation(idx)))
+                .collect();
+            let xor = meta.query_advice(a_4, Rotation::cur());
+            let and = meta.query_advice(a_4, Rotation::next());
+
+            let lo_check = inputs[0].clone() + inputs[1].clone()
+                - spread[0].clone()
+                - spread[1].clone() * F::from(2);
+            let hi_check = inputs[2].clone() + inputs[3].clone()
+                - spread[2].clone()
+                - spread[3].clone() * F::from(2);
+            let xor_check = dense[0].clone() + dense[2].clone() * F::from(1 << 16) - xor;
+            let and_check = dense[1].clone() + dense[3].clone() * F::from(1 << 16) - and;
+
+            Constraints::with_selector(
+                s_xor_and,
+                [
+                    ("lo_check", lo_check),
+                    ("hi_check", hi_check),
+                    ("xor_check", xor_check),
+                    ("and_check", and_check),
+                ],
+            )
+        });
+
+        // s_not is enabled along with s_word, which decomposes !X
+        // s_not | a_0    | a_1 | a_2       | a_3 | a_4 |
+        //   1   | tag_lo | lo  | spread_lo | !X  | X   |
+        //       | tag_hi | hi  | spread_hi |     |     |
+        meta.create_gate("s_not", |meta| {
+            let s_not = meta.query_selector(s_not);
+            let not_x = meta.query_advice(a_3, Rotation::cur());
+            let x = meta.query_advice(a_4, Rotation::cur());
+
+            Constraints::with_selector(s_not, [("not_check", not_x + x - F::from(u32::MAX as u64))])
+        });
+
+        // s_rotate_left rotates X by 16 * swap + s bits. H, the high half of X or its low half
+        // when swapping, is split into (b, a) = (16 - s, s) bit chunks, which are range
+        // checked by looking up a * 2^(16 - s) and b * 2^s. The rotated word is then
+        // a + L * 2^s + b * 2^(16 + s), where L is the other half of X, and it is
+        // decomposed by s_word on the fifth row.
+        // s_rotate_left | a_0 | a_1            | a_2      | a_3  | coeffs     |
+        //   1           | tag | a              | spread_a | x_lo | 2^s        |
+        //               | tag | b              | spread_b | x_hi | 2^(16 - s) |
+        //               | tag | a * 2^(16 - s) |          |      | swap       |
+        //               | tag | b * 2^s        |          |      |            |
+        //               | tag | rol_lo         |          | rol  |            |
+        //               | tag | rol_hi         |          |      |            |
+        meta.create_gate("s_rotate_left", |meta| {
+            let s_rotate_left = meta.query_selector(s_rotate_left);
+            let a = meta.query_advice(a_1, Rotation::cur());
+            let b = meta.query_advice(a_1, Rotation::next());
+            let a_shifted = meta.query_advice(a_1, Rotation(2));
+            let b_shifted = meta.query_advice(a_1, Rotation(3));
+            let x_lo = meta.query_advice(a_3, Rotation::cur());
+            let x_hi = meta.query_advice(a_3, Rotation::next());
+            let rol = meta.query_advice(a_3, Rotation(4));
+            let coef_a = meta.query_fixed(coeffs, Rotation::cur());
+            let coef_b = meta.query_fixed(coeffs, Rotation::next());
+            let swap = meta.query_fixed(coeffs, Rotation(2));
+
+            let l = x_lo.clone() + swap.clone() * (x_hi.clone() - x_lo.clone());
+            let h = x_hi.clone() + swap * (x_lo - x_hi);
+
+            let split_check = a.clone() * coef_b.clone() + b.clone() - h;
+            let a_shifted_check = a.clone() * coef_b - a_shifted;
+            let b_shifted_check = b.clone() * coef_a.clone() - b_shifted;
+            let rol_check = a + coef_a.clone() * l + b * coef_a * F::from(1 << 16) - rol;
+
+            Constraints::with_selector(
+                s_rotate_left,
+                [
+                    ("split_check", split_check),
+                    ("a_shifted_check", a_shifted_check),
+                    ("b_shifted_check", b_shifted_check),
+                    ("rol_check", rol_check),
+                ],
+            )
+        });
+
+        // s_add adds X and Y modulo 2^32, where the sum is decomposed by s_word
+        // s_add | a_0    | a_1    | a_2           | a_3 | a_4   |
+        //   1   |        |        |               | X   | Y     |
+        //       | tag_lo | sum_lo | spread_sum_lo | sum | carry |
+        //       | tag_hi | sum_hi | spread_sum_hi |     |       |
+        meta.create_gate("s_add", |meta| {
+            let s_add = meta.query_selector(s_add);
+            let x = meta.query_advice(a_3, Rotation::cur());
+            let y = meta.query_advice(a_4, Rotation::cur());
+            let sum = meta.query_advice(a_3, Rotation::next());
+            let carry = meta.query_advice(a_4, Rotation::next());
+
+            let sum_check = x + y - sum - carry.clone() * F::from(1 << 32);
+            let carry_check = carry.clone() * (carry - F::one());
+
+            Constraints::with_selector(
+                s_add,
+                [("sum_check", sum_check), ("carry_check", carry_check)],
+            )
+        });
+
+        // s_add_carry also adds the carry of a previous addition, which is boolean already
+        // s_add_carry | a_0    | a_1    | a_2           | a_3 | a_4      |
+        //   1         |        |        |               | X   | Y        |
+        //             | tag_lo | sum_lo | spread_sum_lo | sum | carry    |
+        //             | tag_hi | sum_hi | spread_sum_hi |     | carry_in |
+        meta.create_gate("s_add_carry", |meta| {
+            let s_add_carry = meta.query_selector(s_add_carry);
+            let x = meta.query_advice(a_3, Rotation::cur());
+            let y = meta.query_advice(a_4, Rotation::cur());
+            let sum = meta.query_advice(a_3, Rotation::next());
+            let carry = meta.query_advice(a_4, Rotation::next());
+            let carry_in = meta.query_advice(a_4, Rotation(2));
+
+            let sum_check = x + y + carry_in - sum - carry.clone() * F::from(1 << 32);
+            let carry_check = carry.clone() * (carry - F::one());
+
+            Constraints::with_selector(
+                s_add_carry,
+                [("sum_check", sum_check), ("carry_check", carry_check)],
+            )
+        });
+
+        BitwiseConfig {
+            lookup,
+            advice,
+            coeffs,
+            s_word,
+            s_xor_and,
+            s_not,
+            s_rotate_left,
+            s_add,
+            s_add_carry,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Loads the spread table, unless the chip is configured with a shared table
+    pub fn load(config: BitwiseConfig<F>, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        SpreadTableChip::load(config.lookup, layouter)
+    }
+
+    /// Assigns a word
+    pub fn assign_word(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        word: Value<u32>,
+    ) -> Result<Word32<F>, Error> {
+        assign_region(
+            layouter,
+            || "assign_word",
+            |mut region| {
+                let word = AssignedBits::<32, F>::assign(
+                    &mut region,
+                    || "word",
+                    self.config.advice[0],
+                    0,
+                    word,
+                )?;
+                self.assign_halves(&mut region, 0, word)
+            },
+        )
+    }
+
+    /// Assigns a constant word
+    pub fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        word: u32,
+    ) -> Result<Word32<F>, Error> {
+        assign_region(
+            layouter,
+            || "assign_constant",
+            |mut region| {
+                let word = AssignedBits::<32, F>::assign_constant(
+                    &mut region,
+                    || "constant",
+                    self.config.advice[0],
+                    0,
+                    word,
+                )?;
+                self.assign_halves(&mut region, 0, word)
+            },
+        )
+    }
+
+    /// Decomposes a word assigned elsewhere, such as by another chip, into its halves
+    pub fn decompose(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        word: &AssignedBits<32, F>,
+    ) -> Result<Word32<F>, Error> {
+        assign_region(
+            layouter,
+            || "decompose",
+            |mut region| {
+                let word = word.copy_advice(|| "word", &mut region, self.config.advice[0], 0)?;
+                self.assign_halves(&mut region, 0, word)
+            },
+        )
+    }
+
+    /// Returns X ^ Y and X & Y, which share a region
+    pub fn xor_and(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        y: &Word32<F>,
+    ) -> Result<(Word32<F>, Word32<F>), Error> {
+        let [a_3, a_4] = self.config.advice;
+        assign_region(
+            layouter,
+            || "xor_and",
+            |mut region| {
+                self.config.s_xor_and.enable(&mut region, 0)?;
+
+                // Copy the spread halves of X and Y
+                x.lo.spread
+                    .copy_advice(|| "spread_x_lo", &mut region, a_3, 0)?;
+                y.lo.spread
+                    .copy_advice(|| "spread_y_lo", &mut region, a_3, 1)?;
+                x.hi.spread
+                    .copy_advice(|| "spread_x_hi", &mut region, a_3, 2)?;
+                y.hi.spread
+                    .copy_advice(|| "spread_y_hi", &mut region, a_3, 3)?;
+
+                let xor = x.value().zip(y.value()).map(|(x, y)| x ^ y);
+                let and = x.value().zip(y.value()).map(|(x, y)| x & y);
+
+                // Lookup the even and odd bits of the sums of the spread halves
+                let lookup = &self.config.lookup.input;
+                let xor_lo = SpreadVar::with_lookup(&mut region, lookup, 0, spread_half(xor))?;
+                let and_lo = SpreadVar::with_lookup(&mut region, lookup, 1, spread_half(and))?;
+                let xor_hi =
+                    SpreadVar::with_lookup(&mut region, lookup, 2, spread_half(xor.map(hi)))?;
+                let and_hi =
+                    SpreadVar::with_lookup(&mut region, lookup, 3, spread_half(and.map(hi)))?;
+
+                let xor = AssignedBits::<32, F>::assign(&mut region, || "xor", a_4, 0, xor)?;
+                let and = AssignedBits::<32, F>::assign(&mut region, || "and", a_4, 1, and)?;
+
+                Ok((
+                    Word32 {
+                        word: xor,
+                        lo: xor_lo,
+                        hi: xor_hi,
+                    },
+                    Word32 {
+                        word: and,
+                        lo: and_lo,
+                        hi: and_hi,
+                    },
+                ))
+            },
+        )
+    }
+
+    /// Returns X ^ Y
+    pub fn xor(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        y: &Word32<F>,
+    ) -> Result<Word32<F>, Error> {
+        Ok(self.xor_and(layouter, x, y)?.0)
+    }
+
+    /// Returns X & Y
+    pub fn and(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        y: &Word32<F>,
+    ) -> Result<Word32<F>, Error> {
+        Ok(self.xor_and(layouter, x, y)?.1)
+    }
+
+    /// Returns X | Y as (X ^ Y) + (X & Y), which have no bits in common
+    pub fn or(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        y: &Word32<F>,
+    ) -> Result<Word32<F>, Error> {
+        let (xor, and) = self.xor_and(layouter, x, y)?;
+        self.add(layouter, &xor, &and)
+    }
+
+    /// Returns !X
+    pub fn not(&self, layouter: &mut impl Layouter<F>, x: &Word32<F>) -> Result<Word32<F>, Error> {
+        let [a_3, a_4] = self.config.advice;
+        assign_region(
+            layouter,
+            || "not",
+            |mut region| {
+                self.config.s_not.enable(&mut region, 0)?;
+                x.word.copy_advice(|| "x", &mut region, a_4, 0)?;
+                let not_x = AssignedBits::<32, F>::assign(
+                    &mut region,
+                    || "not_x",
+                    a_3,
+                    0,
+                    x.value().map(|x| !x),
+                )?;
+                self.assign_halves(&mut region, 0, not_x)
+            },
+        )
+    }
+
+    /// Returns X rotated left by `shift` bits, for shifts from 0 to 31
+    pub fn rotate_left(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        shift: u32,
+    ) -> Result<Word32<F>, Error> {
+        assert!(shift < 32);
+        let s = shift % 16;
+        let swap = shift >= 16;
+        let a_3 = self.config.advice[0];
+
+        assign_region(
+            layouter,
+            || "rotate_left",
+            |mut region| {
+                self.config.s_rotate_left.enable(&mut region, 0)?;
+
+                // Assign the shift-dependent coefficients
+                region.assign_fixed(
+                    || "coef_a",
+                    self.config.coeffs,
+                    0,
+                    || Value::known(F::from(1 << s)),
+                )?;
+                region.assign_fixed(
+                    || "coef_b",
+                    self.config.coeffs,
+                    1,
+                    || Value::known(F::from(1 << (16 - s))),
+                )?;
+                region.assign_fixed(
+                    || "swap",
+                    self.config.coeffs,
+                    2,
+                    || Value::known(F::from(swap as u64)),
+                )?;
+
+                // Copy x_lo, x_hi
+                x.lo.dense.copy_advice(|| "x_lo", &mut region, a_3, 0)?;
+                x.hi.dense.copy_advice(|| "x_hi", &mut region, a_3, 1)?;
+
+                // Split H into (b, a) = (16 - s, s) bit chunks
+                let h = x.value().map(|x| if swap { x & 0xffff } else { x >> 16 });
+                let a = h.map(|h| h >> (16 - s));
+                let b = h.map(|h| h & ((1 << (16 - s)) - 1));
+                let a_shifted = a.map(|a| a << (16 - s));
+                let b_shifted = b.map(|b| b << s);
+
+                // Lookup a, b, a * 2^(16 - s) and b * 2^s
+                for (row, chunk) in [a, b, a_shifted, b_shifted].into_iter().enumerate() {
+                    SpreadVar::with_lookup(
+                        &mut region,
+                        &self.config.lookup.input,
+                        row,
+                        spread_half(chunk),
+                    )?;
+                }
+
+                let rol = AssignedBits::<32, F>::assign(
+                    &mut region,
+                    || "rol",
+                    a_3,
+                    4,
+                    x.value().map(|x| x.rotate_left(shift)),
+                )?;
+                self.assign_halves(&mut region, 4, rol)
+            },
+        )
+    }
+
+    /// Returns X rotated right by `shift` bits, for shifts from 0 to 31
+    pub fn rotate_right(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        shift: u32,
+    ) -> Result<Word32<F>, Error> {
+        assert!(shift < 32);
+        self.rotate_left(layouter, x, (32 - shift) % 32)
+    }
+
+    /// Returns X >> `shift`, for shifts from 0 to 31, as the rotation of X with its top
+    /// `shift` bits masked out
+    pub fn shift_right(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        shift: u32,
+    ) -> Result<Word32<F>, Error> {
+        assert!(shift < 32);
+        if shift == 0 {
+            return Ok(x.clone());
+        }
+        let rotated = self.rotate_right(layouter, x, shift)?;
+        let mask = self.assign_constant(layouter, u32::MAX >> shift)?;
+        self.and(layouter, &rotated, &mask)
+    }
+
+    /// Returns X << `shift`, for shifts from 0 to 31, as the rotation of X with its bottom
+    /// `shift` bits masked out
+    pub fn shift_left(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        shift: u32,
+    ) -> Result<Word32<F>, Error> {
+        assert!(shift < 32);
+        if shift == 0 {
+            return Ok(x.clone());
+        }
+        let rotated = self.rotate_left(layouter, x, shift)?;
+        let mask = self.assign_constant(layouter, u32::MAX << shift)?;
+        self.and(layouter, &rotated, &mask)
+    }
+
+    /// Returns X + Y modulo 2^32
+    pub fn add(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        y: &Word32<F>,
+    ) -> Result<Word32<F>, Error> {
+        Ok(self.add_with_carry(layouter, x, y, None)?.0)
+    }
+
+    /// Returns X + Y + `carry_in` modulo 2^32 along with the carry of the sum, so that
+    /// words wider than 32 bits can be added a half at a time. The carry in is the carry
+    /// of a previous addition.
+    pub fn add_with_carry(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        y: &Word32<F>,
+        carry_in: Option<&AssignedBits<1, F>>,
+    ) -> Result<(Word32<F>, AssignedBits<1, F>), Error> {
+        let [a_3, a_4] = self.config.advice;
+        assign_region(
+            layouter,
+            || "add",
+            |mut region| {
+                let carry_in = match carry_in {
+                    Some(carry_in) => {
+                        self.config.s_add_carry.enable(&mut region, 0)?;
+                        let carry_in = carry_in.copy_advice(|| "carry_in", &mut region, a_4, 2)?;
+                        carry_in.value().map(|carry_in| carry_in[0] as u64)
+                    }
+                    None => {
+                        self.config.s_add.enable(&mut region, 0)?;
+                        Value::known(0)
+                    }
+                };
+                x.word.copy_advice(|| "x", &mut region, a_3, 0)?;
+                y.word.copy_advice(|| "y", &mut region, a_4, 0)?;
+
+                let sum = x
+                    .value()
+                    .zip(y.value())
+                    .zip(carry_in)
+                    .map(|((x, y), carry_in)| x as u64 + y as u64 + carry_in);
+                let carry = AssignedBits::<1, F>::assign_bits(
+                    &mut region,
+                    || "carry",
+                    a_4,
+                    1,
+                    sum.map(|sum| [sum >> 32 == 1]),
+                )?;
+                let sum = AssignedBits::<32, F>::assign(
+                    &mut region,
+                    || "sum",
+                    a_3,
+                    1,
+                    sum.map(|sum| sum as u32),
+                )?;
+                Ok((self.assign_halves(&mut region, 1, sum)?, carry))
+            },
+        )
+    }
+
+    /// Returns the sum of `first` and the `rest` of the words modulo 2^32
+    pub fn sum(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        first: &Word32<F>,
+        rest: &[Word32<F>],
+    ) -> Result<Word32<F>, Error> {
+        rest.iter()
+            .try_fold(first.clone(), |sum, word| self.add(layouter, &sum, word))
+    }
+
+    // Looks up the halves of the word assigned to a_3 at the given row
+    fn assign_halves(
+        &self,
+        region: &mut Region<'_, F>,
+        row: usize,
+        word: AssignedBits<32, F>,
+    ) -> Result<Word32<F>, Error> {
+        self.config.s_word.enable(region, row)?;
+
+        let value = word.value_u32();
+        let lookup = &self.config.lookup.input;
+        let lo = SpreadVar::with_lookup(region, lookup, row, spread_half(value))?;
+        let hi = SpreadVar::with_lookup(region, lookup, row + 1, spread_half(value.map(hi)))?;
+
+        Ok(Word32 { word, lo, hi })
+    }
+}
+
+/// The high half of a word
+fn hi(word: u32) -> u32 {
+    word >> 16
+}
+
+/// The low 16 bits of a word, with their tag and spread form for the lookup
+fn spread_half(word: Value<u32>) -> Value<SpreadWord<16, 32>> {
+    word.map(|word| SpreadWord::new(i2lebsp((word & 0xffff).into())))
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+    };
+    use rand::Rng;
+
+    use super::{BitwiseChip, BitwiseConfig};
+    use crate::SpreadTable;
+
+    const SHIFTS: [u32; 5] = [0, 1, 13, 16, 31];
+
+    /// Computes the bitwise operations of two words, exposing the results as public inputs
+    struct BitwiseTestCircuit {
+        x: u32,
+        y: u32,
+        z: u32,
+    }
+
+    impl BitwiseTestCircuit {
+        /// The expected public inputs, in the order they are exposed
+        fn outputs(&self) -> Vec<Fr> {
+            let (x, y, z) = (self.x, self.y, self.z);
+            let mut outputs = vec![
+                x ^ y,
+                x & y,
+                x | y,
+                !x,
+                x.wrapping_add(y),
+                x.wrapping_add(y).wrapping_add(z),
+            ];
+            outputs.extend((0..32).map(|shift| x.rotate_left(shift)));
+            outputs.extend(SHIFTS.map(|shift| x.rotate_right(shift)));
+            outputs.extend(SHIFTS.map(|shift| x >> shift));
+            outputs.extend(SHIFTS.map(|shift| x << shift));
+            // The 64-bit sum of (y, x) and (z, y), a half at a time
+            let lo = x as u64 + y as u64;
+            outputs.push(lo as u32);
+            outputs.push(y.wrapping_add(z).wrapping_add((lo >> 32) as u32));
+            outputs
+                .into_iter()
+                .map(|word| Fr::from(word as u64))
+                .collect()
+        }
+    }
+
+    impl Circuit<Fr> for BitwiseTestCircuit {
+        type Config = (BitwiseConfig<Fr>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            BitwiseTestCircuit {
+                x: self.x,
+                y: self.y,
+                z: self.z,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            (BitwiseChip::configure(meta), instance)
+        }
+
+        fn synthesize(
+            &self,
+            (config, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = BitwiseChip::construct(config.clone());
+            BitwiseChip::load(config, &mut layouter)?;
+
+            let x = chip.assign_word(&mut layouter, Value::known(self.x))?;
+            let y = chip.assign_word(&mut layouter, Value::known(self.y))?;
+            let z = chip.assign_word(&mut layouter, Value::known(self.z))?;
+
+            let mut outputs = vec![
+                chip.xor(&mut layouter, &x, &y)?,
+                chip.and(&mut layouter, &x, &y)?,
+                chip.or(&mut layouter, &x, &y)?,
+                chip.not(&mut layouter, &x)?,
+                chip.add(&mut layouter, &x, &y)?,
+                chip.sum(&mut layouter, &x, &[y, z])?,
+            ];
+            for shift in 0..32 {
+                outputs.push(chip.rotate_left(&mut layouter, &x, shift)?);
+            }
+            for shift in SHIFTS {
+                outputs.push(chip.rotate_right(&mut layouter, &x, shift)?);
+            }
+            for shift in SHIFTS {
+                outputs.push(chip.shift_right(&mut layouter, &x, shift)?);
+            }
+            for shift in SHIFTS {
+                outputs.push(chip.shift_left(&mut layouter, &x, shift)?);
+            }
+            let (lo, carry) = chip.add_with_carry(&mut layouter, &x, &y, None)?;
+            let (hi, _) = chip.add_with_carry(&mut layouter, &y, &z, Some(&carry))?;
+            outputs.extend([lo, hi]);
+
+            for (row, output) in outputs.iter().enumerate() {
+                layouter.constrain_instance(output.word.cell(), instance, row)?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let mut rng = rand::thread_rng();
+        for (x, y, z) in [
+            (rng.gen(), rng.gen(), rng.gen()),
+            (u32::MAX, u32::MAX, 1),
+            (0, 0, 0),
+        ] {
+            let circuit = BitwiseTestCircuit { x, y, z };
+            let outputs = circuit.outputs();
+            let prover = MockProver::run(17, &circuit, vec![outputs]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_wrong_output() {
+        let circuit = BitwiseTestCircuit {
+            x: 0x0123_4567,
+            y: 0x89ab_cdef,
+            z: 0xdead_beef,
+        };
+        for idx in [0, 3, 5, 10, 40, 54] {
+            let mut outputs = circuit.outputs();
+            outputs[idx] += Fr::one();
+            let prover = MockProver::run(17, &circuit, vec![outputs]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    /// XORs two words assigned by two chips that share a spread table
+    struct SharedTableCircuit {
+        x: u32,
+        y: u32,
+    }
+
+    impl Circuit<Fr> for SharedTableCircuit {
+        type Config = ([BitwiseConfig<Fr>; 2], SpreadTable, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            SharedTableCircuit {
+                x: self.x,
+                y: self.y,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            let table = SpreadTable::configure(meta);
+            let configs = [(); 2].map(|_| BitwiseChip::configure_with_spread_table(meta, &table));
+            (configs, table, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (configs, table, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            // The circuit owns the table, and loading the chips sharing it does nothing
+            table.load(&mut layouter)?;
+            for config in configs.iter().chain(configs.iter()) {
+                BitwiseChip::load(config.clone(), &mut layouter)?;
+            }
+            let [first, second] = configs.map(BitwiseChip::construct);
+
+            let x = first.assign_word(&mut layouter, Value::known(self.x))?;
+            let y = second.assign_word(&mut layouter, Value::known(self.y))?;
+            let xor = second.xor(&mut layouter, &x, &y)?;
+            layouter.constrain_instance(xor.word.cell(), instance, 0)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shared_table() {
+        let circuit = SharedTableCircuit {
+            x: 0x0123_4567,
+            y: 0x89ab_cdef,
+        };
+        let xor = Fr::from((circuit.x ^ circuit.y) as u64);
+        let prover = MockProver::run(17, &circuit, vec![vec![xor]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,80 @@
+//! The error type of the chips and gadgets of this crate.
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
+    plonk,
+};
+
+/// An error returned by the chips and gadgets of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// An error of the proving system
+    Plonk(plonk::Error),
+    /// A value does not fit the number of bits of the cell it is assigned to
+    BitLength { bits: usize, value: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Plonk(err) => write!(f, "{}", err),
+            Error::BitLength { bits, value } => {
+                write!(f, "{} does not fit in {} bits", value, bits)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Plonk(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<plonk::Error> for Error {
+    fn from(err: plonk::Error) -> Self {
+        Error::Plonk(err)
+    }
+}
+
+/// Lets a [`halo2_proofs::plonk::Circuit`] use the chips with `?`. The errors of this
+/// crate become [`plonk::Error::Synthesis`].
+impl From<Error> for plonk::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Plonk(err) => err,
+            _ => plonk::Error::Synthesis,
+        }
+    }
+}
+
+/// Assigns a region like [`Layouter::assign_region`], returning the error of the
+/// assignment as is instead of as a [`plonk::Error`]
+pub(crate) fn assign_region<F, L, A, AR, N, NR>(
+    layouter: &mut L,
+    name: N,
+    mut assignment: A,
+) -> Result<AR, Error>
+where
+    F: FieldExt,
+    L: Layouter<F>,
+    A: FnMut(Region<'_, F>) -> Result<AR, Error>,
+    N: Fn() -> NR,
+    NR: Into<String>,
+{
+    let mut error = None;
+    layouter
+        .assign_region(name, |region| {
+            assignment(region).map_err(|err| {
+                error = Some(err);
+                plonk::Error::Synthesis
+            })
+        })
+        .map_err(|err| error.take().unwrap_or(Error::Plonk(err)))
+}
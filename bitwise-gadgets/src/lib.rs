@@ -0,0 +1,28 @@
+//! Gadgets for bitwise operations on 32-bit words, shared by the hash circuits of this
+//! workspace.
+//!
+//! The spread form of a 16-bit word interleaves its bits with zeros, so that the sum of
+//! the spread forms of two words holds their XOR in its even bits and their AND in its odd
+//! bits. [`SpreadTableChip`] looks up 16-bit words along with their spread forms, and
+//! [`BitwiseChip`] builds XOR, AND, OR, NOT, rotations, shifts and modular addition of
+//! 32-bit words on top of it. [`RlcConfig`] combines the bytes of words into one field
+//! element, for the lookup tables of the precompile circuits.
+
+mod bits;
+mod bitwise;
+mod error;
+pub mod rlc;
+pub mod spread_table;
+pub mod tamper;
+pub mod util;
+
+pub use bits::{AssignedBits, Bits};
+pub use bitwise::{
+    BitwiseChip, BitwiseConfig, Word32, ADD_ROWS, ROTATE_ROWS, SHIFT_ROWS, WORD_ROWS, XOR_AND_ROWS,
+};
+pub use error::Error;
+pub use rlc::{bytes_rlc, ByteOrder, RlcConfig};
+pub use spread_table::{
+    SpreadInputs, SpreadTable, SpreadTableChip, SpreadTableConfig, SpreadVar, SpreadWord,
+    SPREAD_TABLE_ROWS,
+};
@@ -0,0 +1,368 @@
+//! Random linear combinations (RLC) of the bytes of words, so that a lookup table of the
+//! EVM circuit can hold the variable-length input or the output of a call as one field
+//! element.
+//!
+//! The RLC of the bytes `b_0, ..., b_{n-1}` with the challenge `r` is
+//! `b_0 * r^{n-1} + b_1 * r^{n-2} + ... + b_{n-1}`, which is how the EVM circuit combines
+//! call data. It is assigned in a second-phase advice column, since `r` is only known
+//! after the first-phase columns have been committed.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{
+        Advice, Challenge, Column, ConstraintSystem, Constraints, Expression, SecondPhase, Selector,
+    },
+    poly::Rotation,
+};
+
+use crate::{
+    error::assign_region, util::i2lebsp, AssignedBits, BitwiseConfig, Error, SpreadInputs,
+    SpreadVar, SpreadWord,
+};
+
+/// Rows taken by the bytes of a word
+pub const WORD_BYTES_ROWS: usize = 4;
+
+/// Returns the RLC of `bytes` with the given challenge, as computed in the circuit.
+pub fn bytes_rlc<F: FieldExt>(bytes: &[u8], challenge: F) -> F {
+    bytes.iter().fold(F::zero(), |rlc, byte| {
+        rlc * challenge + F::from(*byte as u64)
+    })
+}
+
+/// Rows taken by the RLC of the bytes of `words` words
+pub fn rlc_rows(words: usize) -> usize {
+    // The RLC of no bytes is a single constant
+    (words * WORD_BYTES_ROWS).max(1)
+}
+
+/// The order in which the bytes of a word are added to an RLC
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    BigEndian,
+    LittleEndian,
+}
+
+impl ByteOrder {
+    /// Returns the bytes of `word` in this order
+    pub fn bytes(&self, word: u32) -> [u8; 4] {
+        match self {
+            ByteOrder::BigEndian => word.to_be_bytes(),
+            ByteOrder::LittleEndian => word.to_le_bytes(),
+        }
+    }
+}
+
+/// Configuration of the RLCs of the words of a [`crate::BitwiseChip`], which looks up
+/// their bytes in the spread table of the chip
+#[derive(Clone, Debug)]
+pub struct RlcConfig<F: FieldExt> {
+    lookup: SpreadInputs,
+    advice: Column<Advice>,
+    rlc: Column<Advice>,
+    challenge: Challenge,
+    s_word_bytes_be: Selector,
+    s_word_bytes_le: Selector,
+    s_rlc_first: Selector,
+    s_rlc: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> RlcConfig<F> {
+    /// Configures the RLC region next to the columns of `bitwise`, using `challenge` as
+    /// the randomness. The challenge must be usable after the first phase.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        bitwise: &BitwiseConfig<F>,
+        challenge: Challenge,
+    ) -> Self {
+        let lookup = bitwise.lookup.input.clone();
+        let advice = bitwise.advice[0];
+        let rlc = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(rlc);
+
+        let s_word_bytes_be = meta.selector();
+        let s_word_bytes_le = meta.selector();
+        let s_rlc_first = meta.selector();
+        let s_rlc = meta.selector();
+
+        // Rename these here for ease of matching the gates to their layouts
+        let a_0 = lookup.tag;
+        let a_1 = lookup.dense;
+        let a_3 = advice;
+
+        // s_word_bytes_be and s_word_bytes_le decompose a word into its bytes, in big-endian
+        // and little-endian order, and s_rlc_first and s_rlc add each byte to the RLC of
+        // the bytes before it
+        // s_word_bytes | a_0   | a_1 | a_2        | a_3  | rlc   |
+        //   1          | tag_0 | b_0 | spread_b_0 | word | rlc_0 |
+        //              | tag_1 | b_1 | spread_b_1 |      | rlc_1 |
+        //              | tag_2 | b_2 | spread_b_2 |      | rlc_2 |
+        //              | tag_3 | b_3 | spread_b_3 |      | rlc_3 |
+        for (name, selector, order) in [
+            ("s_word_bytes_be", s_word_bytes_be, ByteOrder::BigEndian),
+            ("s_word_bytes_le", s_word_bytes_le, ByteOrder::LittleEndian),
+        ] {
+            meta.create_gate(name, |meta| {
+                let selector = meta.query_selector(selector);
+                let tags: Vec<_> = (0..4)
+                    .map(|idx| meta.query_advice(a_0, Rotation(idx)))
+                    .collect();
+                let bytes: Vec<_> = (0..4)
+                    .map(|idx| meta.query_advice(a_1, Rotation(idx)))
+                    .collect();
+                let word = meta.query_advice(a_3, Rotation::cur());
+
+                let word_check = bytes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, byte)| {
+                        let shift = match order {
+                            ByteOrder::BigEndian => 8 * (3 - idx),
+                            ByteOrder::LittleEndian => 8 * idx,
+                        };
+                        byte * F::from(1 << shift)
+                    })
+                    .fold(Expression::Constant(F::zero()), |sum, byte| sum + byte)
+                    - word;
+
+                Constraints::with_selector(
+                    selector,
+                    // The tag of a byte is zero
+                    tags.into_iter()
+                        .map(|tag| ("byte_tag", tag))
+                        .chain(Some(("word_check", word_check))),
+                )
+            });
+        }
+
+        meta.create_gate("s_rlc_first", |meta| {
+            let s_rlc_first = meta.query_selector(s_rlc_first);
+            let byte = meta.query_advice(a_1, Rotation::cur());
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+
+            Constraints::with_selector(s_rlc_first, [("s_rlc_first", byte - rlc)])
+        });
+
+        meta.create_gate("s_rlc", |meta| {
+            let s_rlc = meta.query_selector(s_rlc);
+            let rlc_prev = meta.query_advice(rlc, Rotation::prev());
+            let challenge = meta.query_challenge(challenge);
+            let byte = meta.query_advice(a_1, Rotation::cur());
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+
+            Constraints::with_selector(s_rlc, [("s_rlc", rlc_prev * challenge + byte - rlc)])
+        });
+
+        RlcConfig {
+            lookup,
+            advice,
+            rlc,
+            challenge,
+            s_word_bytes_be,
+            s_word_bytes_le,
+            s_rlc_first,
+            s_rlc,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assigns the RLC of the first `len` bytes of `words`, taking the bytes of each word
+    /// in the given order. The words are copy constrained into the region, and the bytes
+    /// after the first `len` ones are constrained to `padding`, so that the words cannot
+    /// hold more than the bytes of the RLC.
+    pub fn assign_rlc(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[(AssignedBits<32, F>, ByteOrder)],
+        len: usize,
+        padding: &[u8],
+    ) -> Result<AssignedCell<F, F>, Error> {
+        assert_eq!(len + padding.len(), words.len() * WORD_BYTES_ROWS);
+        let challenge = layouter.get_challenge(self.challenge);
+
+        assign_region(
+            layouter,
+            || "rlc",
+            |mut region| {
+                let mut rlc = Value::known(F::zero());
+                let mut rlc_cell = None;
+                for (word_idx, (word, order)) in words.iter().enumerate() {
+                    let row = word_idx * WORD_BYTES_ROWS;
+                    match order {
+                        ByteOrder::BigEndian => self.s_word_bytes_be.enable(&mut region, row)?,
+                        ByteOrder::LittleEndian => self.s_word_bytes_le.enable(&mut region, row)?,
+                    }
+                    word.copy_advice(
+                        || format!("word_{}", word_idx),
+                        &mut region,
+                        self.advice,
+                        row,
+                    )?;
+
+                    // Lookup the bytes of the word
+                    let bytes = word.value_u32().map(|word| order.bytes(word));
+                    for idx in 0..WORD_BYTES_ROWS {
+                        let byte_idx = row + idx;
+                        let byte = bytes.map(|bytes| bytes[idx]);
+                        let byte_var = SpreadVar::with_lookup(
+                            &mut region,
+                            &self.lookup,
+                            byte_idx,
+                            byte.map(|b| SpreadWord::<8, 16>::new(i2lebsp(b.into()))),
+                        )?;
+
+                        if byte_idx >= len {
+                            region.constrain_constant(
+                                byte_var.dense.cell(),
+                                F::from(padding[byte_idx - len] as u64),
+                            )?;
+                            continue;
+                        }
+                        if byte_idx == 0 {
+                            self.s_rlc_first.enable(&mut region, byte_idx)?;
+                        } else {
+                            self.s_rlc.enable(&mut region, byte_idx)?;
+                        }
+                        rlc = rlc * challenge + byte.map(|b| F::from(b as u64));
+                        rlc_cell = Some(region.assign_advice(
+                            || format!("rlc_{}", byte_idx),
+                            self.rlc,
+                            byte_idx,
+                            || rlc,
+                        )?);
+                    }
+                }
+
+                // The RLC of no bytes is zero
+                match rlc_cell {
+                    Some(rlc_cell) => Ok(rlc_cell),
+                    None => {
+                        Ok(region.assign_advice_from_constant(|| "rlc", self.rlc, 0, F::zero())?)
+                    }
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+        plonk::{Challenge, Circuit, ConstraintSystem, Error, FirstPhase},
+    };
+
+    use super::{bytes_rlc, ByteOrder, RlcConfig};
+    use crate::{BitwiseChip, BitwiseConfig};
+
+    const WORDS: [u32; 3] = [0x6162_6380, 0x0000_0000, 0x0102_0304];
+
+    /// Assigns the RLC of the first `len` bytes of [`WORDS`], which are taken in `order`
+    /// and padded with `padding`
+    struct RlcTestCircuit {
+        order: ByteOrder,
+        len: usize,
+        padding: Vec<u8>,
+    }
+
+    impl Circuit<Fr> for RlcTestCircuit {
+        type Config = (BitwiseConfig<Fr>, RlcConfig<Fr>, Challenge);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            RlcTestCircuit {
+                order: self.order,
+                len: self.len,
+                padding: self.padding.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            let bitwise = BitwiseChip::configure(meta);
+            let rlc = RlcConfig::configure(meta, &bitwise, challenge);
+            (bitwise, rlc, challenge)
+        }
+
+        fn synthesize(
+            &self,
+            (bitwise, rlc, challenge): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = BitwiseChip::construct(bitwise.clone());
+            BitwiseChip::load(bitwise, &mut layouter)?;
+
+            let words = WORDS
+                .into_iter()
+                .map(|word| {
+                    let word = chip.assign_word(&mut layouter, Value::known(word))?;
+                    Ok((word.word, self.order))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let rlc_cell = rlc.assign_rlc(&mut layouter, &words, self.len, &self.padding)?;
+
+            let bytes: Vec<u8> = WORDS
+                .iter()
+                .flat_map(|word| self.order.bytes(*word))
+                .take(self.len)
+                .collect();
+            rlc_cell
+                .value()
+                .zip(layouter.get_challenge(challenge))
+                .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(&bytes, *r));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bytes_rlc() {
+        let r = Fr::from(256);
+        assert_eq!(bytes_rlc(&[], r), Fr::zero());
+        assert_eq!(bytes_rlc(&[0x12, 0x34, 0x56], r), Fr::from(0x12_3456));
+    }
+
+    #[test]
+    fn test_rlc() {
+        for (order, len, padding) in [
+            (ByteOrder::BigEndian, 12, vec![]),
+            (ByteOrder::BigEndian, 3, vec![0x80, 0, 0, 0, 0, 1, 2, 3, 4]),
+            (ByteOrder::LittleEndian, 10, vec![0x02, 0x01]),
+            (
+                ByteOrder::LittleEndian,
+                0,
+                WORDS.map(u32::to_le_bytes).concat(),
+            ),
+        ] {
+            let circuit = RlcTestCircuit {
+                order,
+                len,
+                padding,
+            };
+            let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_wrong_padding() {
+        // The last byte of the words is 4
+        let circuit = RlcTestCircuit {
+            order: ByteOrder::BigEndian,
+            len: 11,
+            padding: vec![5],
+        };
+        let failures = MockProver::run(17, &circuit, vec![])
+            .unwrap()
+            .verify()
+            .unwrap_err();
+        assert!(failures
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+    }
+}
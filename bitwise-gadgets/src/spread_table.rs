@@ -0,0 +1,522 @@
+/*
+Modified version of code from https://github.com/privacy-scaling-explorations/halo2/blob/8c945507ceca5f4ed6e52da3672ea0308bcac812/halo2_gadgets/src/sha256/table16/spread_table.rs
+*/
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    circuit::{Chip, Layouter, Region, Value},
+    halo2curves::FieldExt,
+    plonk::{Advice, Column, ConstraintSystem, TableColumn},
+    poly::Rotation,
+};
+
+use crate::util::{lebs2ip, spread_bits};
+use crate::{AssignedBits, Error};
+
+const BITS_8: usize = 1 << 8;
+const BITS_9: usize = 1 << 9;
+const BITS_10: usize = 1 << 10;
+const BITS_11: usize = 1 << 11;
+const BITS_12: usize = 1 << 12;
+const BITS_13: usize = 1 << 13;
+const BITS_14: usize = 1 << 14;
+const BITS_15: usize = 1 << 15;
+
+/// Number of rows of the spread table
+pub const SPREAD_TABLE_ROWS: usize = 1 << 16;
+
+/// An input word into a lookup, containing (tag, dense, spread)
+#[derive(Copy, Clone, Debug)]
+pub struct SpreadWord<const DENSE: usize, const SPREAD: usize> {
+    pub tag: u8,
+    pub dense: [bool; DENSE],
+    pub spread: [bool; SPREAD],
+}
+
+/// Helper function that returns tag of 16-bit input
+pub fn get_tag(input: u16) -> u8 {
+    let input = input as usize;
+    if input < BITS_8 {
+        0
+    } else if input < BITS_9 {
+        1
+    } else if input < BITS_10 {
+        2
+    } else if input < BITS_11 {
+        3
+    } else if input < BITS_12 {
+        4
+    } else if input < BITS_13 {
+        5
+    } else if input < BITS_14 {
+        6
+    } else if input < BITS_15 {
+        7
+    } else {
+        8
+    }
+}
+
+impl<const DENSE: usize, const SPREAD: usize> SpreadWord<DENSE, SPREAD> {
+    pub fn new(dense: [bool; DENSE]) -> Self {
+        assert!(DENSE <= 16);
+        SpreadWord {
+            tag: get_tag(lebs2ip(&dense) as u16),
+            dense,
+            spread: spread_bits(dense),
+        }
+    }
+
+    pub fn try_new<T: TryInto<[bool; DENSE]> + std::fmt::Debug + Clone>(
+        dense: T,
+    ) -> Result<Self, Error> {
+        assert!(DENSE <= 16);
+        let dense: [bool; DENSE] = dense.clone().try_into().map_err(|_| Error::BitLength {
+            bits: DENSE,
+            value: format!("{:?}", dense),
+        })?;
+        Ok(Self::new(dense))
+    }
+}
+
+/// Variable stored in advice columns corresponding to a row of [`SpreadTableConfig`].
+#[derive(Debug, Clone)]
+pub struct SpreadVar<const DENSE: usize, const SPREAD: usize, F: FieldExt> {
+    pub tag: Value<u8>,
+    pub dense: AssignedBits<DENSE, F>,
+    pub spread: AssignedBits<SPREAD, F>,
+}
+
+impl<const DENSE: usize, const SPREAD: usize, F: FieldExt> SpreadVar<DENSE, SPREAD, F> {
+    pub fn with_lookup(
+        region: &mut Region<'_, F>,
+        cols: &SpreadInputs,
+        row: usize,
+        word: Value<SpreadWord<DENSE, SPREAD>>,
+    ) -> Result<Self, Error> {
+        Self::assign(region, cols, row, word, "tag", "dense", "spread")
+    }
+
+    /// Same as [`with_lookup`](Self::with_lookup), annotating the dense cell with `name`
+    /// and the spread cell with `spread_{name}`, so that tests can pick them out
+    pub fn with_lookup_named(
+        region: &mut Region<'_, F>,
+        name: &str,
+        cols: &SpreadInputs,
+        row: usize,
+        word: Value<SpreadWord<DENSE, SPREAD>>,
+    ) -> Result<Self, Error> {
+        Self::assign(
+            region,
+            cols,
+            row,
+            word,
+            &format!("tag_{}", name),
+            name,
+            &format!("spread_{}", name),
+        )
+    }
+
+    fn assign(
+        region: &mut Region<'_, F>,
+        cols: &SpreadInputs,
+        row: usize,
+        word: Value<SpreadWord<DENSE, SPREAD>>,
+        tag_annotation: &str,
+        dense_annotation: &str,
+        spread_annotation: &str,
+    ) -> Result<Self, Error> {
+        let tag = word.map(|word| word.tag);
+        let dense_val = word.map(|word| word.dense);
+        let spread_val = word.map(|word| word.spread);
+
+        region.assign_advice(
+            || tag_annotation,
+            cols.tag,
+            row,
+            || tag.map(|tag| F::from(tag as u64)),
+        )?;
+
+        let dense = AssignedBits::<DENSE, F>::assign_bits(
+            region,
+            || dense_annotation,
+            cols.dense,
+            row,
+            dense_val,
+        )?;
+
+        let spread = AssignedBits::<SPREAD, F>::assign_bits(
+            region,
+            || spread_annotation,
+            cols.spread,
+            row,
+            spread_val,
+        )?;
+
+        Ok(SpreadVar { tag, dense, spread })
+    }
+
+    pub fn without_lookup(
+        region: &mut Region<'_, F>,
+        dense_col: Column<Advice>,
+        dense_row: usize,
+        spread_col: Column<Advice>,
+        spread_row: usize,
+        word: Value<SpreadWord<DENSE, SPREAD>>,
+    ) -> Result<Self, Error> {
+        let tag = word.map(|word| word.tag);
+        let dense_val = word.map(|word| word.dense);
+        let spread_val = word.map(|word| word.spread);
+
+        let dense = AssignedBits::<DENSE, F>::assign_bits(
+            region,
+            || "dense",
+            dense_col,
+            dense_row,
+            dense_val,
+        )?;
+
+        let spread = AssignedBits::<SPREAD, F>::assign_bits(
+            region,
+            || "spread",
+            spread_col,
+            spread_row,
+            spread_val,
+        )?;
+
+        Ok(SpreadVar { tag, dense, spread })
+    }
+}
+
+/// The advice columns looked up in a [`SpreadTable`]
+#[derive(Clone, Debug)]
+pub struct SpreadInputs {
+    pub tag: Column<Advice>,
+    pub dense: Column<Advice>,
+    pub spread: Column<Advice>,
+}
+
+/// The table of all 16-bit words along with their tags and spread forms
+///
+/// A circuit made of several chips can configure one table and pass it to the `configure`
+/// of each chip, so that the 2^16 rows of the table are only paid for once. The circuit
+/// owns the table then: it loads it once with [`SpreadTable::load`], and the `load` of
+/// the chips configured with it does nothing.
+#[derive(Clone, Debug)]
+pub struct SpreadTable {
+    pub tag: TableColumn,
+    pub dense: TableColumn,
+    pub spread: TableColumn,
+}
+
+impl SpreadTable {
+    /// Configures the columns of a table, which is not looked up until a chip is
+    /// configured with it
+    pub fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+        SpreadTable {
+            tag: meta.lookup_table_column(),
+            dense: meta.lookup_table_column(),
+            spread: meta.lookup_table_column(),
+        }
+    }
+
+    /// Assigns the rows of the table, which must be done once in each synthesis. A chip
+    /// configured with its own table loads it through [`SpreadTableChip::load`].
+    pub fn load<F: FieldExt>(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "spread table",
+            |mut table| {
+                // We generate the row values lazily (we only need them during keygen).
+                let mut rows = Self::generate::<F>();
+
+                for index in 0..(1 << 16) {
+                    let mut row = None;
+                    table.assign_cell(
+                        || "tag",
+                        self.tag,
+                        index,
+                        || {
+                            row = rows.next();
+                            Value::known(row.map(|(tag, _, _)| tag).unwrap())
+                        },
+                    )?;
+                    table.assign_cell(
+                        || "dense",
+                        self.dense,
+                        index,
+                        || Value::known(row.map(|(_, dense, _)| dense).unwrap()),
+                    )?;
+                    table.assign_cell(
+                        || "spread",
+                        self.spread,
+                        index,
+                        || Value::known(row.map(|(_, _, spread)| spread).unwrap()),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+
+    fn generate<F: FieldExt>() -> impl Iterator<Item = (F, F, F)> {
+        (1..=(1 << 16)).scan(
+            (F::zero(), F::zero(), F::zero()),
+            |(tag, dense, spread), i| {
+                // We computed this table row in the previous iteration.
+                let res = (*tag, *dense, *spread);
+
+                // i holds the zero-indexed row number for the next table row.
+                match i {
+                    BITS_8 | BITS_9 | BITS_10 | BITS_11 | BITS_12 | BITS_13 | BITS_14 | BITS_15 => {
+                        *tag += F::one()
+                    }
+                    _ => (),
+                }
+                *dense += F::one();
+                if i & 1 == 0 {
+                    // On even-numbered rows we recompute the spread.
+                    *spread = F::zero();
+                    for b in 0..16 {
+                        if (i >> b) & 1 != 0 {
+                            *spread += F::from(1 << (2 * b));
+                        }
+                    }
+                } else {
+                    // On odd-numbered rows we add one.
+                    *spread += F::one();
+                }
+                Some(res)
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SpreadTableConfig {
+    pub input: SpreadInputs,
+    pub table: SpreadTable,
+    // Whether the table belongs to this config, rather than being shared with other chips
+    loads_table: bool,
+}
+
+/// A chip that loads the spread table and looks up [`SpreadInputs`] in it
+#[derive(Debug, Clone)]
+pub struct SpreadTableChip<F: FieldExt> {
+    config: SpreadTableConfig,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> Chip<F> for SpreadTableChip<F> {
+    type Config = SpreadTableConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: FieldExt> SpreadTableChip<F> {
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        input_tag: Column<Advice>,
+        input_dense: Column<Advice>,
+        input_spread: Column<Advice>,
+    ) -> <Self as Chip<F>>::Config {
+        let table = SpreadTable::configure(meta);
+        let input = Self::configure_inputs(meta, &table, input_tag, input_dense, input_spread);
+
+        SpreadTableConfig {
+            input,
+            table,
+            loads_table: true,
+        }
+    }
+
+    /// Looks up the given input columns in a table shared with other chips. The table is
+    /// loaded by the circuit that configured it, not by this chip.
+    pub fn configure_with_table(
+        meta: &mut ConstraintSystem<F>,
+        table: &SpreadTable,
+        input_tag: Column<Advice>,
+        input_dense: Column<Advice>,
+        input_spread: Column<Advice>,
+    ) -> <Self as Chip<F>>::Config {
+        let input = Self::configure_inputs(meta, table, input_tag, input_dense, input_spread);
+
+        SpreadTableConfig {
+            input,
+            table: table.clone(),
+            loads_table: false,
+        }
+    }
+
+    /// Adds a lookup from another set of input columns into an already configured table.
+    pub fn configure_inputs(
+        meta: &mut ConstraintSystem<F>,
+        table: &SpreadTable,
+        input_tag: Column<Advice>,
+        input_dense: Column<Advice>,
+        input_spread: Column<Advice>,
+    ) -> SpreadInputs {
+        meta.lookup("Bitlength lookup", |meta| {
+            let tag_cur = meta.query_advice(input_tag, Rotation::cur());
+            let dense_cur = meta.query_advice(input_dense, Rotation::cur());
+            let spread_cur = meta.query_advice(input_spread, Rotation::cur());
+
+            vec![
+                (tag_cur, table.tag),
+                (dense_cur, table.dense),
+                (spread_cur, table.spread),
+            ]
+        });
+
+        SpreadInputs {
+            tag: input_tag,
+            dense: input_dense,
+            spread: input_spread,
+        }
+    }
+
+    /// Loads the table if it belongs to this config, and does nothing for a config that
+    /// shares the table of its circuit, however many times it is called
+    pub fn load(
+        config: SpreadTableConfig,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<<Self as Chip<F>>::Loaded, Error> {
+        if config.loads_table {
+            config.table.load(layouter)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    use super::{SpreadTable, SpreadTableChip, SpreadTableConfig};
+
+    /// Assigns the given (tag, dense, spread) rows to the lookup inputs
+    struct SpreadTestCircuit {
+        rows: Vec<(u64, u64, u64)>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for SpreadTestCircuit {
+        type Config = SpreadTableConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            SpreadTestCircuit { rows: vec![] }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let input_tag = meta.advice_column();
+            let input_dense = meta.advice_column();
+            let input_spread = meta.advice_column();
+
+            SpreadTableChip::configure(meta, input_tag, input_dense, input_spread)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            SpreadTableChip::load(config.clone(), &mut layouter)?;
+
+            layouter.assign_region(
+                || "spread_test",
+                |mut region| {
+                    for (row, (tag, dense, spread)) in self.rows.iter().enumerate() {
+                        for (column, value) in [
+                            (config.input.tag, tag),
+                            (config.input.dense, dense),
+                            (config.input.spread, spread),
+                        ] {
+                            region.assign_advice(
+                                || "input",
+                                column,
+                                row,
+                                || Value::known(F::from(*value)),
+                            )?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn lookup_table() {
+        // The first few small values and the tag boundaries
+        let mut rows = vec![
+            (0, 0b000, 0b000000),
+            (0, 0b001, 0b000001),
+            (0, 0b010, 0b000100),
+            (0, 0b011, 0b000101),
+            (0, 0b100, 0b010000),
+            (0, 0b101, 0b010001),
+            (0, 0b1111_1111, 0b0101_0101_0101_0101),
+        ];
+        for tag in 1..=8 {
+            let bits = tag + 7;
+            rows.push((tag, 1 << bits, 1 << (2 * bits)));
+        }
+        rows.push((8, 0xffff, 0x5555_5555));
+
+        let prover = MockProver::<Fr>::run(17, &SpreadTestCircuit { rows }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong tag, spread or a dense value of more than 16 bits are not in the table
+        for row in [
+            (1, 0b1111_1111, 0b0101_0101_0101_0101),
+            (0, 0b11, 0b11),
+            (8, 1 << 16, 1 << 32),
+        ] {
+            let circuit = SpreadTestCircuit { rows: vec![row] };
+            let prover = MockProver::<Fr>::run(17, &circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn shared_table() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let table = SpreadTable::configure(&mut meta);
+        let configs: Vec<_> = (0..3)
+            .map(|_| {
+                let input_tag = meta.advice_column();
+                let input_dense = meta.advice_column();
+                let input_spread = meta.advice_column();
+                SpreadTableChip::configure_with_table(
+                    &mut meta,
+                    &table,
+                    input_tag,
+                    input_dense,
+                    input_spread,
+                )
+            })
+            .collect();
+
+        // None of the configs sharing the table loads it, whatever the order they were
+        // configured in, while a config with its own table does
+        assert!(configs.iter().all(|config| !config.loads_table));
+        let input_tag = meta.advice_column();
+        let input_dense = meta.advice_column();
+        let input_spread = meta.advice_column();
+        let config = SpreadTableChip::configure(&mut meta, input_tag, input_dense, input_spread);
+        assert!(config.loads_table);
+    }
+}
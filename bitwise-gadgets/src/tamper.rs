@@ -0,0 +1,227 @@
+//! Overrides of assigned cell values, for the soundness tests of the hash circuits to
+//! check that their gates reject the witness of a malicious prover. Without the `tamper`
+//! feature, [`apply`] returns the honest value.
+//!
+//! A cell is chosen by its annotation and by how many cells with the same annotation
+//! were assigned before it. The override is applied in the value closure passed to the
+//! region, which the floor planner only calls when the cell is assigned for real.
+
+use halo2_proofs::circuit::Value;
+
+use crate::util::{i2lebsp, lebs2ip};
+
+/// A value that a test can write over a cell
+pub trait Tamperable: Sized {
+    fn to_u64(&self) -> u64;
+    fn from_u64(value: u64) -> Self;
+}
+
+macro_rules! impl_tamperable {
+    ($($ty:ty),*) => {
+        $(impl Tamperable for $ty {
+            fn to_u64(&self) -> u64 {
+                u64::from(*self)
+            }
+
+            fn from_u64(value: u64) -> Self {
+                value.try_into().expect("tampered value does not fit the cell")
+            }
+        })*
+    };
+}
+
+impl_tamperable!(u16, u32);
+
+impl Tamperable for u64 {
+    fn to_u64(&self) -> u64 {
+        *self
+    }
+
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+}
+
+impl<const LEN: usize> Tamperable for [bool; LEN] {
+    fn to_u64(&self) -> u64 {
+        lebs2ip(self)
+    }
+
+    fn from_u64(value: u64) -> Self {
+        assert!(
+            LEN == 64 || value >> LEN == 0,
+            "tampered value does not fit the cell"
+        );
+        i2lebsp(value)
+    }
+}
+
+/// Returns the value to assign to the cell with the given annotation
+#[cfg(not(feature = "tamper"))]
+#[inline]
+pub fn apply<T, A, AR>(_annotation: &A, value: Value<T>) -> Value<T>
+where
+    T: Tamperable,
+    A: Fn() -> AR,
+    AR: Into<String>,
+{
+    value
+}
+
+#[cfg(feature = "tamper")]
+pub use harness::apply;
+
+/// The overrides themselves, which the crates of the hash circuits enable in their
+/// dev-dependencies
+#[cfg(feature = "tamper")]
+pub mod harness {
+    use std::cell::RefCell;
+
+    use halo2_proofs::{
+        circuit::Value,
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+        plonk::Circuit,
+    };
+
+    use super::Tamperable;
+
+    /// Replaces the value of the `occurrence`-th cell annotated with `annotation`
+    pub struct Tamper {
+        annotation: &'static str,
+        occurrence: usize,
+        op: Box<dyn Fn(u64) -> u64>,
+        seen: usize,
+        applied: bool,
+    }
+
+    impl Tamper {
+        /// Sets the cell to the given value
+        pub fn set(annotation: &'static str, occurrence: usize, value: u64) -> Self {
+            Self::new(annotation, occurrence, Box::new(move |_| value))
+        }
+
+        /// Flips the given bits of the cell, which always changes its value
+        pub fn xor(annotation: &'static str, occurrence: usize, mask: u64) -> Self {
+            assert!(mask != 0);
+            Self::new(annotation, occurrence, Box::new(move |value| value ^ mask))
+        }
+
+        fn new(annotation: &'static str, occurrence: usize, op: Box<dyn Fn(u64) -> u64>) -> Self {
+            Tamper {
+                annotation,
+                occurrence,
+                op,
+                seen: 0,
+                applied: false,
+            }
+        }
+    }
+
+    thread_local! {
+        static TAMPERS: RefCell<Vec<Tamper>> = RefCell::new(vec![]);
+    }
+
+    pub fn apply<T, A, AR>(annotation: &A, value: Value<T>) -> Value<T>
+    where
+        T: Tamperable,
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        TAMPERS.with(|tampers| {
+            let mut tampers = tampers.borrow_mut();
+            if tampers.is_empty() {
+                return value;
+            }
+
+            let annotation: String = annotation().into();
+            let mut value = value;
+            for tamper in tampers.iter_mut().filter(|t| t.annotation == annotation) {
+                if tamper.seen == tamper.occurrence {
+                    value = value.map(|v| T::from_u64((tamper.op)(v.to_u64())));
+                    tamper.applied = true;
+                }
+                tamper.seen += 1;
+            }
+            value
+        })
+    }
+
+    /// Runs the mock prover with the given cells overwritten and returns the failures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a chosen cell is never assigned, which would make a test vacuous.
+    pub fn verify_tampered<C: Circuit<Fr>>(
+        k: u32,
+        circuit: &C,
+        tampers: Vec<Tamper>,
+    ) -> Vec<VerifyFailure> {
+        verify_tampered_with_instances(k, circuit, vec![], tampers)
+    }
+
+    /// Runs the mock prover with the given public inputs and cells overwritten, and returns
+    /// the failures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a chosen cell is never assigned.
+    pub fn verify_tampered_with_instances<C: Circuit<Fr>>(
+        k: u32,
+        circuit: &C,
+        instances: Vec<Vec<Fr>>,
+        tampers: Vec<Tamper>,
+    ) -> Vec<VerifyFailure> {
+        TAMPERS.with(|cell| *cell.borrow_mut() = tampers);
+        let prover = MockProver::run(k, circuit, instances);
+        let tampers = TAMPERS.with(|cell| cell.take());
+
+        for tamper in tampers.iter() {
+            assert!(
+                tamper.applied,
+                "cell {} #{} was never assigned ({} seen)",
+                tamper.annotation, tamper.occurrence, tamper.seen
+            );
+        }
+
+        prover.unwrap().verify().err().unwrap_or_default()
+    }
+
+    /// Whether the named constraint of the named gate is among the failures
+    pub fn failed_constraint(failures: &[VerifyFailure], gate: &str, constraint: &str) -> bool {
+        let gate = format!("('{}')", gate);
+        let constraint = format!("('{}')", constraint);
+        failures.iter().any(|failure| match failure {
+            VerifyFailure::ConstraintNotSatisfied { constraint: c, .. } => {
+                let c = c.to_string();
+                c.contains(&gate) && c.contains(&constraint)
+            }
+            _ => false,
+        })
+    }
+
+    /// Whether a constraint of the named gate is among the failures
+    pub fn failed_gate(failures: &[VerifyFailure], gate: &str) -> bool {
+        let gate = format!("('{}')", gate);
+        failures.iter().any(|failure| match failure {
+            VerifyFailure::ConstraintNotSatisfied { constraint, .. } => {
+                constraint.to_string().ends_with(&gate)
+            }
+            _ => false,
+        })
+    }
+
+    /// Whether a lookup is among the failures
+    pub fn failed_lookup(failures: &[VerifyFailure]) -> bool {
+        failures
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::Lookup { .. }))
+    }
+
+    /// Whether a copy constraint is among the failures
+    pub fn failed_permutation(failures: &[VerifyFailure]) -> bool {
+        failures
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::Permutation { .. }))
+    }
+}
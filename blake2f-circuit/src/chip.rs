@@ -0,0 +1,233 @@
+//! The BLAKE2b compression function F on 64-bit words, each held as its 32-bit halves in
+//! [`BitwiseChip`].
+//!
+//! The additions modulo 2^64 add the high halves with the carry of the low halves, and the
+//! rotations rotate both halves before swapping their top bits. The number of rounds and
+//! the final block flag are part of the layout, like the lengths of the inputs of the
+//! other precompiles.
+
+use bitwise_gadgets::{BitwiseChip, Error, Word32, ADD_ROWS, ROTATE_ROWS, WORD_ROWS, XOR_AND_ROWS};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Value},
+};
+
+use crate::native::{IV, SIGMA};
+
+/// Rows taken by an addition of 64-bit words
+const ADD64_ROWS: usize = 2 * ADD_ROWS;
+/// Rows taken by a XOR of 64-bit words
+const XOR64_ROWS: usize = 2 * XOR_AND_ROWS;
+/// Rows taken by a rotation of a 64-bit word by a shift that is not a multiple of 32
+const ROTATE64_ROWS: usize = 2 * ROTATE_ROWS + WORD_ROWS + 4 * XOR_AND_ROWS;
+/// Rows taken by the mixing function G, whose rotation by 32 bits takes none
+const G_ROWS: usize = 6 * ADD64_ROWS + 4 * XOR64_ROWS + 3 * ROTATE64_ROWS;
+/// Rows taken by a round, which mixes the columns and the diagonals of the working vector
+pub const ROUND_ROWS: usize = 8 * G_ROWS;
+
+/// The words of the working vector mixed by each G of a round: the columns, then the
+/// diagonals
+const MIXES: [(usize, usize, usize, usize); 8] = [
+    (0, 4, 8, 12),
+    (1, 5, 9, 13),
+    (2, 6, 10, 14),
+    (3, 7, 11, 15),
+    (0, 5, 10, 15),
+    (1, 6, 11, 12),
+    (2, 7, 8, 13),
+    (3, 4, 9, 14),
+];
+
+/// Returns the rows taken by the compression function with the given number of rounds
+pub fn compression_rows(rounds: u32) -> usize {
+    // The initialization vector, the counter and the final XORs with the state
+    let init = IV.len() * 2 * WORD_ROWS + 2 * XOR64_ROWS;
+    let finalize = 2 * IV.len() * XOR64_ROWS;
+    init + rounds as usize * ROUND_ROWS + finalize
+}
+
+/// A 64-bit word as its 32-bit halves
+#[derive(Clone, Debug)]
+pub struct Word64<F: FieldExt> {
+    pub lo: Word32<F>,
+    pub hi: Word32<F>,
+}
+
+impl<F: FieldExt> Word64<F> {
+    pub fn value(&self) -> Value<u64> {
+        self.lo
+            .value()
+            .zip(self.hi.value())
+            .map(|(lo, hi)| lo as u64 | (hi as u64) << 32)
+    }
+}
+
+/// A chip computing the BLAKE2b compression function with a [`BitwiseChip`]
+#[derive(Clone, Debug)]
+pub struct Blake2fChip<F: FieldExt> {
+    bitwise: BitwiseChip<F>,
+}
+
+impl<F: FieldExt> Blake2fChip<F> {
+    pub fn construct(bitwise: BitwiseChip<F>) -> Self {
+        Self { bitwise }
+    }
+
+    /// Assigns a word
+    pub fn assign_word(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        word: Value<u64>,
+    ) -> Result<Word64<F>, Error> {
+        Ok(Word64 {
+            lo: self
+                .bitwise
+                .assign_word(layouter, word.map(|word| word as u32))?,
+            hi: self
+                .bitwise
+                .assign_word(layouter, word.map(|word| (word >> 32) as u32))?,
+        })
+    }
+
+    /// Assigns a constant word
+    pub fn assign_constant(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        word: u64,
+    ) -> Result<Word64<F>, Error> {
+        Ok(Word64 {
+            lo: self.bitwise.assign_constant(layouter, word as u32)?,
+            hi: self
+                .bitwise
+                .assign_constant(layouter, (word >> 32) as u32)?,
+        })
+    }
+
+    /// Returns the state vector after compressing the message block `m` with the counter
+    /// `t`, for 8 words of `h`, 16 words of `m` and 2 words of `t`
+    pub fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        rounds: u32,
+        h: &[Word64<F>],
+        m: &[Word64<F>],
+        t: &[Word64<F>],
+        f: bool,
+    ) -> Result<[Word64<F>; 8], Error> {
+        assert_eq!((h.len(), m.len(), t.len()), (8, 16, 2));
+
+        let mut v = h.to_vec();
+        for (idx, word) in IV.iter().enumerate() {
+            // The final block flag inverts the word 14
+            let word = if idx == 6 && f { !word } else { *word };
+            v.push(self.assign_constant(layouter, word)?);
+        }
+        v[12] = self.xor(layouter, &v[12], &t[0])?;
+        v[13] = self.xor(layouter, &v[13], &t[1])?;
+
+        for round in 0..rounds as usize {
+            let s = &SIGMA[round % 10];
+            for (idx, mix) in MIXES.into_iter().enumerate() {
+                self.g(layouter, &mut v, mix, &m[s[2 * idx]], &m[s[2 * idx + 1]])?;
+            }
+        }
+
+        let mut state = vec![];
+        for (idx, word) in h.iter().enumerate() {
+            let word = self.xor(layouter, word, &v[idx])?;
+            state.push(self.xor(layouter, &word, &v[idx + 8])?);
+        }
+        Ok(state.try_into().unwrap())
+    }
+
+    /// The mixing function G, mixing the message words `x` and `y` into four words of the
+    /// working vector
+    fn g(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        v: &mut [Word64<F>],
+        (a, b, c, d): (usize, usize, usize, usize),
+        x: &Word64<F>,
+        y: &Word64<F>,
+    ) -> Result<(), Error> {
+        v[a] = self.add(layouter, &v[a], &v[b])?;
+        v[a] = self.add(layouter, &v[a], x)?;
+        v[d] = self.xor(layouter, &v[d], &v[a])?;
+        v[d] = self.rotate_right(layouter, &v[d], 32)?;
+        v[c] = self.add(layouter, &v[c], &v[d])?;
+        v[b] = self.xor(layouter, &v[b], &v[c])?;
+        v[b] = self.rotate_right(layouter, &v[b], 24)?;
+        v[a] = self.add(layouter, &v[a], &v[b])?;
+        v[a] = self.add(layouter, &v[a], y)?;
+        v[d] = self.xor(layouter, &v[d], &v[a])?;
+        v[d] = self.rotate_right(layouter, &v[d], 16)?;
+        v[c] = self.add(layouter, &v[c], &v[d])?;
+        v[b] = self.xor(layouter, &v[b], &v[c])?;
+        v[b] = self.rotate_right(layouter, &v[b], 63)?;
+        Ok(())
+    }
+
+    /// Returns X + Y modulo 2^64
+    fn add(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word64<F>,
+        y: &Word64<F>,
+    ) -> Result<Word64<F>, Error> {
+        let (lo, carry) = self.bitwise.add_with_carry(layouter, &x.lo, &y.lo, None)?;
+        let (hi, _) = self
+            .bitwise
+            .add_with_carry(layouter, &x.hi, &y.hi, Some(&carry))?;
+        Ok(Word64 { lo, hi })
+    }
+
+    /// Returns X ^ Y
+    fn xor(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word64<F>,
+        y: &Word64<F>,
+    ) -> Result<Word64<F>, Error> {
+        Ok(Word64 {
+            lo: self.bitwise.xor(layouter, &x.lo, &y.lo)?,
+            hi: self.bitwise.xor(layouter, &x.hi, &y.hi)?,
+        })
+    }
+
+    /// Returns X rotated right by `shift` bits, for shifts from 0 to 63
+    fn rotate_right(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word64<F>,
+        shift: u32,
+    ) -> Result<Word64<F>, Error> {
+        assert!(shift < 64);
+        let bitwise = &self.bitwise;
+
+        // Rotating by 32 bits swaps the halves
+        let (lo, hi) = if shift >= 32 {
+            (&x.hi, &x.lo)
+        } else {
+            (&x.lo, &x.hi)
+        };
+        let shift = shift % 32;
+        if shift == 0 {
+            return Ok(Word64 {
+                lo: lo.clone(),
+                hi: hi.clone(),
+            });
+        }
+
+        // Rotating each half leaves the top `shift` bits of each half in the other one, so
+        // they are swapped by XORing both halves with the difference of their top bits
+        let lo = bitwise.rotate_right(layouter, lo, shift)?;
+        let hi = bitwise.rotate_right(layouter, hi, shift)?;
+        let mask = bitwise.assign_constant(layouter, !(u32::MAX >> shift))?;
+        let diff = bitwise.xor(layouter, &lo, &hi)?;
+        let diff = bitwise.and(layouter, &diff, &mask)?;
+        Ok(Word64 {
+            lo: bitwise.xor(layouter, &lo, &diff)?,
+            hi: bitwise.xor(layouter, &hi, &diff)?,
+        })
+    }
+}
@@ -1,51 +1,215 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
-#![allow(unreachable_code)]
-
-use std::marker::PhantomData;
+//! The circuit of the [`BLAKE2F`] precompile, through which the EVM circuit looks up the
+//! output of the compression function F of BLAKE2b for the input of each call.
+//!
+//! [`Blake2fChip`] compresses the message block of each call with the gadgets of
+//! [`bitwise_gadgets`], over a spread table that may be shared with the other circuits of
+//! the super-circuit. Each row of [`Blake2fTable`] holds a call as the RLCs of its 213-byte
+//! input and of its 64-byte output. The number of rounds and the final block flag of each
+//! call are part of its layout, so they are set when the keys are generated, as are the
+//! rows of the table.
+//!
+//! The public inputs of a call are the words of `h`, `m` and `t` followed by the words of
+//! the output, each 64-bit word as its low and its high 32-bit halves.
+//!
+//! [`BLAKE2F`]: https://eips.ethereum.org/EIPS/eip-152
 
+use bitwise_gadgets::{
+    rlc::rlc_rows, AssignedBits, BitwiseChip, BitwiseConfig, ByteOrder, Error, RlcConfig,
+    SpreadTable, SPREAD_TABLE_ROWS, WORD_ROWS,
+};
 use halo2_proofs::{
     arithmetic::FieldExt,
-    circuit::Layouter,
-    plonk::{Advice, Any, Column, ConstraintSystem, Error},
+    circuit::{AssignedCell, Cell, Layouter, Value},
+    plonk::{self, Advice, Any, Challenge, Column, ConstraintSystem, Fixed, SecondPhase},
 };
+use precompile_circuit::PrecompileCircuit;
+
+mod chip;
+mod native;
 
+pub use chip::{compression_rows, Blake2fChip, Word64, ROUND_ROWS};
+pub use native::{compress, INPUT_LEN};
+
+/// Number of bytes of the output
+pub const OUTPUT_LEN: usize = 64;
+
+/// The columns through which other circuits look up the calls to the BLAKE2F
+/// precompile, along with the spread table and the challenge used by the chip
 #[derive(Clone, Debug)]
 pub struct Blake2fTable {
-    id: Column<Advice>,
+    /// Whether the row holds a call
+    pub q_enable: Column<Fixed>,
+    /// The RLC of the 213 bytes of the input
+    pub input_rlc: Column<Advice>,
+    /// The RLC of the 64 bytes of the output
+    pub output_rlc: Column<Advice>,
+    /// The spread table looked up by the chip, which may be shared with other chips. The
+    /// circuit that configures it loads it with [`SpreadTable::load`].
+    pub spread_table: SpreadTable,
+    /// The challenge of the RLCs, which should be the one of the EVM circuit
+    pub challenge: Challenge,
 }
 
 impl Blake2fTable {
-    pub fn construct<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+    /// Configures the columns of the table. The challenge must be usable after the first
+    /// phase.
+    pub fn construct<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        spread_table: SpreadTable,
+        challenge: Challenge,
+    ) -> Self {
+        let input_rlc = meta.advice_column_in(SecondPhase);
+        let output_rlc = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(input_rlc);
+        meta.enable_equality(output_rlc);
+
         Self {
-            id: meta.advice_column(),
+            q_enable: meta.fixed_column(),
+            input_rlc,
+            output_rlc,
+            spread_table,
+            challenge,
         }
     }
 
     pub fn columns(&self) -> Vec<Column<Any>> {
-        vec![self.id.into()]
+        vec![
+            self.q_enable.into(),
+            self.input_rlc.into(),
+            self.output_rlc.into(),
+        ]
     }
 
     pub fn annotations(&self) -> Vec<String> {
-        vec![String::from("id")]
+        ["q_enable", "input_rlc", "output_rlc"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    /// Assigns a row for each call
+    fn assign<F: FieldExt>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        calls: &[Blake2fCall<F>],
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "blake2f table",
+            |mut region| {
+                for (row, call) in calls.iter().enumerate() {
+                    region.assign_fixed(
+                        || "q_enable",
+                        self.q_enable,
+                        row,
+                        || Value::known(F::one()),
+                    )?;
+                    call.input_rlc
+                        .copy_advice(|| "input_rlc", &mut region, self.input_rlc, row)?;
+                    call.output_rlc.copy_advice(
+                        || "output_rlc",
+                        &mut region,
+                        self.output_rlc,
+                        row,
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
     }
 }
 
+/// The words of a call, along with the RLCs of its input and of its output
 #[derive(Clone, Debug)]
-pub struct Blake2fConfig<F> {
-    table: Blake2fTable,
-    _marker: PhantomData<F>,
+pub struct Blake2fCall<F: FieldExt> {
+    /// The words of `h`, `m` and `t`
+    pub words: Vec<Word64<F>>,
+    /// The words of the output
+    pub output: [Word64<F>; 8],
+    /// The RLC of the bytes of the input
+    pub input_rlc: AssignedCell<F, F>,
+    /// The RLC of the bytes of the output
+    pub output_rlc: AssignedCell<F, F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2fConfig<F: FieldExt> {
+    pub table: Blake2fTable,
+    bitwise: BitwiseConfig<F>,
+    rlc: RlcConfig<F>,
 }
 
 impl<F: FieldExt> Blake2fConfig<F> {
     pub fn configure(meta: &mut ConstraintSystem<F>, table: Blake2fTable) -> Self {
+        let bitwise = BitwiseChip::configure_with_spread_table(meta, &table.spread_table);
+        let rlc = RlcConfig::configure(meta, &bitwise, table.challenge);
         Self {
             table,
-            _marker: PhantomData,
+            bitwise,
+            rlc,
+        }
+    }
+
+    /// Compresses the block of each call, assigning a row of the table for each of them.
+    /// The spread table is loaded by the circuit that configures it.
+    pub fn assign_calls(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        witnesses: &[Blake2fWitness],
+    ) -> Result<Vec<Blake2fCall<F>>, Error> {
+        let bitwise = BitwiseChip::construct(self.bitwise.clone());
+        let chip = Blake2fChip::construct(bitwise.clone());
+
+        let mut calls = vec![];
+        for witness in witnesses {
+            let words = witness
+                .h
+                .iter()
+                .chain(&witness.m)
+                .chain(&witness.t)
+                .map(|word| chip.assign_word(layouter, Value::known(*word)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (h, rest) = words.split_at(8);
+            let (m, t) = rest.split_at(16);
+            let output = chip.compress(layouter, witness.rounds, h, m, t, witness.f)?;
+
+            // The input is the big-endian number of rounds, the little-endian words and the
+            // final block flag, which is followed by three bytes of padding
+            let rounds = bitwise.assign_constant(layouter, witness.rounds)?;
+            let f = bitwise.assign_constant(layouter, witness.f as u32)?;
+            let input_words: Vec<_> = Some((rounds.word, ByteOrder::BigEndian))
+                .into_iter()
+                .chain(little_endian(&words))
+                .chain(Some((f.word, ByteOrder::LittleEndian)))
+                .collect();
+            let input_rlc = self
+                .rlc
+                .assign_rlc(layouter, &input_words, INPUT_LEN, &[0; 3])?;
+            let output_rlc =
+                self.rlc
+                    .assign_rlc(layouter, &little_endian(&output), OUTPUT_LEN, &[])?;
+
+            calls.push(Blake2fCall {
+                words,
+                output,
+                input_rlc,
+                output_rlc,
+            });
         }
+
+        self.table.assign(layouter, &calls)?;
+        Ok(calls)
     }
 }
 
+/// The halves of the words along with the order of their bytes in the RLCs
+fn little_endian<F: FieldExt>(words: &[Word64<F>]) -> Vec<(AssignedBits<32, F>, ByteOrder)> {
+    words
+        .iter()
+        .flat_map(|word| [&word.lo, &word.hi])
+        .map(|half| (half.word.clone(), ByteOrder::LittleEndian))
+        .collect()
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct Blake2fWitness {
     pub rounds: u32,
@@ -55,19 +219,67 @@ pub struct Blake2fWitness {
     pub f: bool,
 }
 
-#[derive(Clone, Debug)]
-pub struct Blake2fChip<F> {
-    config: Blake2fConfig<F>,
-    data: Vec<Blake2fWitness>,
-}
+impl<F: FieldExt> PrecompileCircuit<F> for Blake2fConfig<F> {
+    type Table = Blake2fTable;
+    type Witness = Blake2fWitness;
+    /// The little-endian bytes of the state vector
+    type Output = [u8; OUTPUT_LEN];
 
-impl<F: FieldExt> Blake2fChip<F> {
-    pub fn construct(config: Blake2fConfig<F>, data: Vec<Blake2fWitness>) -> Self {
-        Self { config, data }
+    fn configure(meta: &mut ConstraintSystem<F>, table: Self::Table) -> Self {
+        Blake2fConfig::configure(meta, table)
     }
 
-    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        Ok(())
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        witnesses: &[Self::Witness],
+    ) -> Result<Vec<Cell>, plonk::Error> {
+        let calls = self.assign_calls(layouter, witnesses)?;
+        Ok(calls
+            .iter()
+            .flat_map(|call| call.words.iter().chain(&call.output))
+            .flat_map(|word| [word.lo.word.cell(), word.hi.word.cell()])
+            .collect())
+    }
+
+    fn public_inputs(witness: &Self::Witness) -> Vec<F> {
+        let Blake2fWitness { h, m, t, .. } = witness;
+        h.iter()
+            .chain(m)
+            .chain(t)
+            .chain(&compress(witness))
+            .flat_map(|word| [*word as u32, (*word >> 32) as u32])
+            .map(|half| F::from(half as u64))
+            .collect()
+    }
+
+    fn min_rows(witnesses: &[Self::Witness]) -> usize {
+        // The calls take the columns of the bitwise chip one after the other, while the
+        // table has columns of its own
+        let calls: usize = witnesses
+            .iter()
+            .map(|witness| {
+                // The words of h, m and t, the number of rounds and the final block flag
+                let words = 2 * (8 + 16 + 2);
+                (words + 2) * WORD_ROWS
+                    + compression_rows(witness.rounds)
+                    + rlc_rows(words + 2)
+                    + rlc_rows(2 * 8)
+            })
+            .sum();
+
+        [calls, SPREAD_TABLE_ROWS, witnesses.len()]
+            .into_iter()
+            .max()
+            .unwrap()
+    }
+
+    fn compute(witness: &Self::Witness) -> Self::Output {
+        let mut output = [0; OUTPUT_LEN];
+        for (bytes, word) in output.chunks_mut(8).zip(compress(witness)) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        output
     }
 }
 
@@ -75,8 +287,12 @@ impl<F: FieldExt> Blake2fChip<F> {
 pub mod dev {
     use super::*;
 
+    use bitwise_gadgets::bytes_rlc;
     use ethers_core::{types::H512, utils::hex::FromHex};
-    use halo2_proofs::{arithmetic::FieldExt, circuit::SimpleFloorPlanner, plonk::Circuit};
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        plonk::{Circuit, FirstPhase},
+    };
     use std::{marker::PhantomData, str::FromStr};
 
     lazy_static::lazy_static! {
@@ -136,6 +352,8 @@ pub mod dev {
         };
     }
 
+    /// Compresses the blocks of the inputs, checking the RLCs of the states against the
+    /// outputs
     #[derive(Default)]
     pub struct Blake2fTestCircuit<F> {
         pub inputs: Vec<Blake2fWitness>,
@@ -147,12 +365,20 @@ pub mod dev {
         type Config = Blake2fConfig<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
+        /// The layout depends on the number of rounds and the flag of each input, so the
+        /// inputs are kept
         fn without_witnesses(&self) -> Self {
-            Self::default()
+            Self {
+                inputs: self.inputs.clone(),
+                outputs: self.outputs.clone(),
+                _marker: PhantomData,
+            }
         }
 
-        fn configure(meta: &mut halo2_proofs::plonk::ConstraintSystem<F>) -> Self::Config {
-            let blake2f_table = Blake2fTable::construct(meta);
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            let spread_table = SpreadTable::configure(meta);
+            let blake2f_table = Blake2fTable::construct(meta, spread_table, challenge);
             Blake2fConfig::configure(meta, blake2f_table)
         }
 
@@ -160,19 +386,83 @@ pub mod dev {
             &self,
             config: Self::Config,
             mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
-            let chip = Blake2fChip::construct(config, self.inputs.clone());
-            chip.load(&mut layouter)
+        ) -> Result<(), plonk::Error> {
+            config.table.spread_table.load(&mut layouter)?;
+            let calls = config.assign_calls(&mut layouter, &self.inputs)?;
+
+            // The RLCs are the ones of the call data and of the output of each call
+            let challenge = layouter.get_challenge(config.table.challenge);
+            for ((call, input), output) in calls.iter().zip(&self.inputs).zip(&self.outputs) {
+                call.input_rlc
+                    .value()
+                    .zip(challenge)
+                    .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(&input.to_input(), *r));
+                call.output_rlc
+                    .value()
+                    .zip(challenge)
+                    .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(&output.0, *r));
+            }
+            Ok(())
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use bitwise_gadgets::{SpreadTable, SPREAD_TABLE_ROWS};
+    use ethers_core::types::H512;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, Column, ConstraintSystem, Error, FirstPhase, Instance},
+    };
+    use precompile_circuit::PrecompileCircuit;
     use std::marker::PhantomData;
 
-    use crate::dev::{Blake2fTestCircuit, INPUTS_OUTPUTS};
+    use crate::{
+        dev::{Blake2fTestCircuit, INPUTS_OUTPUTS},
+        Blake2fConfig, Blake2fTable, Blake2fWitness, INPUT_LEN,
+    };
+
+    /// Compresses the blocks of the inputs through [`PrecompileCircuit`], exposing their
+    /// public inputs
+    struct PrecompileTestCircuit {
+        inputs: Vec<Blake2fWitness>,
+    }
+
+    impl Circuit<Fr> for PrecompileTestCircuit {
+        type Config = (Blake2fConfig<Fr>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            PrecompileTestCircuit {
+                inputs: self.inputs.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            let spread_table = SpreadTable::configure(meta);
+            let table = Blake2fTable::construct(meta, spread_table, challenge);
+            (PrecompileCircuit::configure(meta, table), instance)
+        }
+
+        fn synthesize(
+            &self,
+            (config, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.table.spread_table.load(&mut layouter)?;
+            let cells = config.assign(&mut layouter, &self.inputs)?;
+            for (row, cell) in cells.into_iter().enumerate() {
+                layouter.constrain_instance(cell, instance, row)?;
+            }
+            Ok(())
+        }
+    }
 
     #[test]
     fn test_blake2f_circuit() {
@@ -184,8 +474,109 @@ mod tests {
             _marker: PhantomData,
         };
 
-        let k = 8;
+        let k = 17;
         let prover = MockProver::run(k, &circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_rounds_and_flag() {
+        // The test vector with no rounds, one round, and without the final block flag
+        let witness = INPUTS_OUTPUTS.0[0].clone();
+        let inputs: Vec<_> = [(0, true), (1, true), (1, false)]
+            .into_iter()
+            .map(|(rounds, f)| Blake2fWitness {
+                rounds,
+                f,
+                ..witness.clone()
+            })
+            .collect();
+        let outputs = inputs
+            .iter()
+            .map(|input| H512(Blake2fConfig::<Fr>::compute(input)))
+            .collect();
+
+        let circuit: Blake2fTestCircuit<Fr> = Blake2fTestCircuit {
+            inputs,
+            outputs,
+            _marker: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_input() {
+        let (inputs, _) = INPUTS_OUTPUTS.clone();
+        let input = inputs[0].to_input();
+        assert_eq!(input.len(), INPUT_LEN);
+        assert_eq!(
+            Blake2fWitness::from_input(&input).unwrap().to_input(),
+            input
+        );
+
+        // The inputs of wrong lengths or flags are rejected
+        assert!(Blake2fWitness::from_input(&input[1..]).is_none());
+        let mut flag = input;
+        flag[INPUT_LEN - 1] = 2;
+        assert!(Blake2fWitness::from_input(&flag).is_none());
+    }
+
+    #[test]
+    fn test_compute() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        for (input, output) in inputs.iter().zip(outputs) {
+            assert_eq!(Blake2fConfig::<Fr>::compute(input), output.0);
+        }
+    }
+
+    #[test]
+    fn test_public_inputs() {
+        // The halves of the 26 words of h, m and t, then of the 8 words of the output
+        let witness = &INPUTS_OUTPUTS.0[0];
+        let public_inputs = Blake2fConfig::<Fr>::public_inputs(witness);
+        assert_eq!(public_inputs.len(), 2 * (26 + 8));
+        assert_eq!(public_inputs[0], Fr::from(witness.h[0] as u32 as u64));
+        assert_eq!(public_inputs[1], Fr::from(witness.h[0] >> 32));
+
+        let output = Blake2fConfig::<Fr>::compute(witness);
+        let half = u32::from_le_bytes(output[..4].try_into().unwrap());
+        assert_eq!(public_inputs[2 * 26], Fr::from(half as u64));
+    }
+
+    #[test]
+    fn test_min_rows() {
+        // The 12 rounds of the test vector fit next to the spread table
+        let (inputs, _) = INPUTS_OUTPUTS.clone();
+        assert_eq!(Blake2fConfig::<Fr>::min_rows(&inputs), SPREAD_TABLE_ROWS);
+
+        let inputs = vec![inputs[0].clone(); 5];
+        assert!(Blake2fConfig::<Fr>::min_rows(&inputs) > SPREAD_TABLE_ROWS);
+    }
+
+    #[test]
+    fn test_wrong_public_inputs() {
+        let circuit = PrecompileTestCircuit {
+            inputs: vec![Blake2fWitness {
+                rounds: 1,
+                ..INPUTS_OUTPUTS.0[0].clone()
+            }],
+        };
+        let public_inputs = Blake2fConfig::<Fr>::public_inputs(&circuit.inputs[0]);
+        let prover = MockProver::run(17, &circuit, vec![public_inputs.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong half of the output, of h or of t
+        for idx in [public_inputs.len() - 1, 0, 2 * 24] {
+            let mut public_inputs = public_inputs.clone();
+            public_inputs[idx] += Fr::one();
+            let failures = MockProver::run(17, &circuit, vec![public_inputs])
+                .unwrap()
+                .verify()
+                .unwrap_err();
+            assert!(failures
+                .iter()
+                .any(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+        }
+    }
 }
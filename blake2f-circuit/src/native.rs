@@ -0,0 +1,119 @@
+//! The BLAKE2b compression function F, as specified by [EIP-152].
+//!
+//! [EIP-152]: https://eips.ethereum.org/EIPS/eip-152
+
+use crate::Blake2fWitness;
+
+/// Length of the input of the precompile
+pub const INPUT_LEN: usize = 213;
+
+/// The initialization vector of BLAKE2b
+pub const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The permutations of the message words, the round `i` taking `SIGMA[i % 10]`
+pub const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The mixing function G, mixing the message words `x` and `y` into four words of the
+/// working vector
+fn g(v: &mut [u64; 16], (a, b, c, d): (usize, usize, usize, usize), x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Returns the state vector after compressing the message block of the witness
+pub fn compress(witness: &Blake2fWitness) -> [u64; 8] {
+    let Blake2fWitness { rounds, h, m, t, f } = witness;
+
+    let mut v = [0; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if *f {
+        v[14] = !v[14];
+    }
+
+    for round in 0..*rounds as usize {
+        let s = &SIGMA[round % 10];
+        // Mix the columns, then the diagonals
+        g(&mut v, (0, 4, 8, 12), m[s[0]], m[s[1]]);
+        g(&mut v, (1, 5, 9, 13), m[s[2]], m[s[3]]);
+        g(&mut v, (2, 6, 10, 14), m[s[4]], m[s[5]]);
+        g(&mut v, (3, 7, 11, 15), m[s[6]], m[s[7]]);
+        g(&mut v, (0, 5, 10, 15), m[s[8]], m[s[9]]);
+        g(&mut v, (1, 6, 11, 12), m[s[10]], m[s[11]]);
+        g(&mut v, (2, 7, 8, 13), m[s[12]], m[s[13]]);
+        g(&mut v, (3, 4, 9, 14), m[s[14]], m[s[15]]);
+    }
+
+    let mut state = *h;
+    for (idx, word) in state.iter_mut().enumerate() {
+        *word ^= v[idx] ^ v[idx + 8];
+    }
+    state
+}
+
+impl Blake2fWitness {
+    /// Decodes the input of a call to the precompile, which is made of the big-endian number
+    /// of rounds, the little-endian words of `h`, `m` and `t`, and a final byte for `f`.
+    /// Returns `None` for inputs of the wrong length or with a final byte other than 0 or 1.
+    pub fn from_input(input: &[u8]) -> Option<Self> {
+        if input.len() != INPUT_LEN || input[INPUT_LEN - 1] > 1 {
+            return None;
+        }
+
+        let mut words = input[4..INPUT_LEN - 1]
+            .chunks(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        let mut witness = Blake2fWitness {
+            rounds: u32::from_be_bytes(input[..4].try_into().unwrap()),
+            f: input[INPUT_LEN - 1] == 1,
+            ..Default::default()
+        };
+        for word in witness
+            .h
+            .iter_mut()
+            .chain(witness.m.iter_mut())
+            .chain(witness.t.iter_mut())
+        {
+            *word = words.next().unwrap();
+        }
+        Some(witness)
+    }
+
+    /// Encodes the witness as the input of a call to the precompile
+    pub fn to_input(&self) -> Vec<u8> {
+        let mut input = self.rounds.to_be_bytes().to_vec();
+        for word in self.h.iter().chain(&self.m).chain(&self.t) {
+            input.extend(word.to_le_bytes());
+        }
+        input.push(self.f as u8);
+        input
+    }
+}
@@ -0,0 +1,411 @@
+//! A mock of the EVM circuit, which records the calls to the precompiles and looks each of
+//! them up in the table of its precompile.
+//!
+//! Each row holds a call as the address of the precompile, the length of the input, and the
+//! RLCs of the input and of the output the EVM claims. The address must be one of the
+//! precompiles, and a call must be a row of the table of its precompile, [`Sha2Table`],
+//! [`Ripemd160Table`] or [`Blake2fTable`], so a wrong output fails the lookup. The inputs
+//! of BLAKE2F have a fixed length, so their table has no column of lengths and the length
+//! is looked up as a constant.
+
+use blake2f_circuit::{Blake2fConfig, Blake2fTable, Blake2fWitness, INPUT_LEN};
+use halo2_proofs::{
+    arithmetic::{Field, FieldExt},
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    plonk::{
+        Advice, Challenge, Circuit, Column, ConstraintSystem, Constraints, Error, Expression,
+        FirstPhase, Fixed, SecondPhase, Selector, VirtualCells,
+    },
+    poly::Rotation,
+};
+use precompile_circuit::PrecompileCircuit;
+use ripemd160_circuit::{bytes_rlc, Ripemd160Table, SpreadTable, Table16Config};
+use sha2_256_circuit::{Sha2Config, Sha2Table};
+
+/// The address of the SHA2-256 precompile
+pub const SHA256: u64 = 0x02;
+/// The address of the RIPEMD-160 precompile
+pub const RIPEMD160: u64 = 0x03;
+/// The address of the BLAKE2F precompile
+pub const BLAKE2F: u64 = 0x09;
+
+/// The addresses of the precompiles that the EVM circuit looks up
+const PRECOMPILES: [u64; 3] = [SHA256, RIPEMD160, BLAKE2F];
+
+/// Returns an expression that equals 1 if `address` is the one of `precompile`, and 0 if it
+/// is the one of another precompile
+fn is_precompile<F: FieldExt>(precompile: u64, address: Expression<F>) -> Expression<F> {
+    PRECOMPILES
+        .into_iter()
+        .filter(|other| *other != precompile)
+        .fold(Expression::Constant(F::one()), |acc, other| {
+            let inv = (F::from(precompile) - F::from(other)).invert().unwrap();
+            acc * (address.clone() - Expression::Constant(F::from(other)))
+                * Expression::Constant(inv)
+        })
+}
+
+/// A call to a precompile, with the output claimed by the EVM
+#[derive(Clone, Debug)]
+pub struct EvmCall {
+    pub address: u64,
+    pub input: Vec<u8>,
+    pub output: Vec<u8>,
+}
+
+/// The columns in which the EVM circuit records the calls to the precompiles
+#[derive(Clone, Debug)]
+pub struct EvmCallConfig {
+    q_enable: Selector,
+    address: Column<Advice>,
+    input_len: Column<Advice>,
+    input_rlc: Column<Advice>,
+    output_rlc: Column<Advice>,
+    challenge: Challenge,
+}
+
+impl EvmCallConfig {
+    /// Configures the columns of the calls, looking up the calls to each precompile in its
+    /// table. The tables share the challenge of the RLCs.
+    pub fn configure<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        sha256: &Sha2Table,
+        ripemd160: &Ripemd160Table,
+        blake2f: &Blake2fTable,
+    ) -> Self {
+        let q_enable = meta.complex_selector();
+        let address = meta.advice_column();
+        let input_len = meta.advice_column();
+        let input_rlc = meta.advice_column_in(SecondPhase);
+        let output_rlc = meta.advice_column_in(SecondPhase);
+
+        meta.create_gate("precompile address", |meta| {
+            let q_enable = meta.query_selector(q_enable);
+            let address = meta.query_advice(address, Rotation::cur());
+            Constraints::with_selector(
+                q_enable,
+                [(
+                    "address_check",
+                    PRECOMPILES
+                        .into_iter()
+                        .map(|precompile| {
+                            address.clone() - Expression::Constant(F::from(precompile))
+                        })
+                        .reduce(|acc, is_address| acc * is_address)
+                        .unwrap(),
+                )],
+            )
+        });
+
+        let config = Self {
+            q_enable,
+            address,
+            input_len,
+            input_rlc,
+            output_rlc,
+            challenge: ripemd160.challenge,
+        };
+
+        config.lookup_calls(meta, "sha256 call", SHA256, sha256.q_enable, |meta| {
+            vec![
+                (
+                    input_len,
+                    meta.query_fixed(sha256.input_len, Rotation::cur()),
+                ),
+                (
+                    input_rlc,
+                    meta.query_advice(sha256.input_rlc, Rotation::cur()),
+                ),
+                (
+                    output_rlc,
+                    meta.query_advice(sha256.output_rlc, Rotation::cur()),
+                ),
+            ]
+        });
+        config.lookup_calls(
+            meta,
+            "ripemd160 call",
+            RIPEMD160,
+            ripemd160.q_enable,
+            |meta| {
+                vec![
+                    (
+                        input_len,
+                        meta.query_fixed(ripemd160.input_len, Rotation::cur()),
+                    ),
+                    (
+                        input_rlc,
+                        meta.query_advice(ripemd160.input_rlc, Rotation::cur()),
+                    ),
+                    (
+                        output_rlc,
+                        meta.query_advice(ripemd160.output_rlc, Rotation::cur()),
+                    ),
+                ]
+            },
+        );
+        config.lookup_calls(meta, "blake2f call", BLAKE2F, blake2f.q_enable, |meta| {
+            let q_enable = meta.query_fixed(blake2f.q_enable, Rotation::cur());
+            vec![
+                (
+                    input_len,
+                    q_enable * Expression::Constant(F::from(INPUT_LEN as u64)),
+                ),
+                (
+                    input_rlc,
+                    meta.query_advice(blake2f.input_rlc, Rotation::cur()),
+                ),
+                (
+                    output_rlc,
+                    meta.query_advice(blake2f.output_rlc, Rotation::cur()),
+                ),
+            ]
+        });
+
+        config
+    }
+
+    /// Looks the calls to `precompile` up in its table, whose `q_enable` is `q_table` and
+    /// whose other columns `table` returns along with the columns of the calls they hold
+    fn lookup_calls<F: FieldExt>(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        name: &'static str,
+        precompile: u64,
+        q_table: Column<Fixed>,
+        table: impl FnOnce(&mut VirtualCells<'_, F>) -> Vec<(Column<Advice>, Expression<F>)>,
+    ) {
+        meta.lookup_any(name, |meta| {
+            let q_enable = meta.query_selector(self.q_enable);
+            let address = meta.query_advice(self.address, Rotation::cur());
+            let condition = q_enable * is_precompile(precompile, address);
+            let mut lookup = vec![(
+                condition.clone(),
+                meta.query_fixed(q_table, Rotation::cur()),
+            )];
+            for (column, table) in table(meta) {
+                lookup.push((
+                    condition.clone() * meta.query_advice(column, Rotation::cur()),
+                    table,
+                ));
+            }
+            lookup
+        });
+    }
+
+    /// Assigns a row for each call
+    pub fn assign<F: FieldExt>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        calls: &[EvmCall],
+    ) -> Result<(), Error> {
+        let challenge = layouter.get_challenge(self.challenge);
+
+        layouter.assign_region(
+            || "evm calls",
+            |mut region| {
+                for (row, call) in calls.iter().enumerate() {
+                    self.q_enable.enable(&mut region, row)?;
+                    region.assign_advice(
+                        || "address",
+                        self.address,
+                        row,
+                        || Value::known(F::from(call.address)),
+                    )?;
+                    region.assign_advice(
+                        || "input_len",
+                        self.input_len,
+                        row,
+                        || Value::known(F::from(call.input.len() as u64)),
+                    )?;
+                    region.assign_advice(
+                        || "input_rlc",
+                        self.input_rlc,
+                        row,
+                        || challenge.map(|r| bytes_rlc(&call.input, r)),
+                    )?;
+                    region.assign_advice(
+                        || "output_rlc",
+                        self.output_rlc,
+                        row,
+                        || challenge.map(|r| bytes_rlc(&call.output, r)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Records the calls to the precompiles and verifies them with the precompile circuits
+#[derive(Clone, Debug, Default)]
+pub struct MockEvmCircuit {
+    pub calls: Vec<EvmCall>,
+}
+
+impl MockEvmCircuit {
+    /// Returns the inputs of the calls to `precompile`
+    fn inputs(&self, precompile: u64) -> impl Iterator<Item = &Vec<u8>> {
+        self.calls
+            .iter()
+            .filter(move |call| call.address == precompile)
+            .map(|call| &call.input)
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for MockEvmCircuit {
+    type Config = (
+        EvmCallConfig,
+        Sha2Config<F>,
+        Table16Config<F>,
+        Blake2fConfig<F>,
+        SpreadTable,
+    );
+    type FloorPlanner = SimpleFloorPlanner;
+
+    /// The layouts of the precompile circuits depend on the inputs, so the calls are kept
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        let spread_table = SpreadTable::configure(meta);
+        let sha256_table = Sha2Table::construct(meta, spread_table.clone(), challenge);
+        let ripemd160_table = Ripemd160Table::construct(meta, spread_table.clone(), challenge);
+        let blake2f_table = Blake2fTable::construct(meta, spread_table.clone(), challenge);
+        let evm = EvmCallConfig::configure(meta, &sha256_table, &ripemd160_table, &blake2f_table);
+        let sha256 = PrecompileCircuit::configure(meta, sha256_table);
+        let ripemd160 = PrecompileCircuit::configure(meta, ripemd160_table);
+        let blake2f = PrecompileCircuit::configure(meta, blake2f_table);
+        (evm, sha256, ripemd160, blake2f, spread_table)
+    }
+
+    fn synthesize(
+        &self,
+        (evm, sha256, ripemd160, blake2f, spread_table): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        spread_table.load(&mut layouter)?;
+        let inputs: Vec<_> = self.inputs(SHA256).cloned().collect();
+        sha256.assign(&mut layouter.namespace(|| "sha256"), &inputs)?;
+        let inputs: Vec<_> = self.inputs(RIPEMD160).cloned().collect();
+        ripemd160.assign(&mut layouter.namespace(|| "ripemd160"), &inputs)?;
+        // An input that BLAKE2F rejects has no row in its table, so its call fails the lookup
+        let witnesses: Vec<_> = self
+            .inputs(BLAKE2F)
+            .filter_map(|input| Blake2fWitness::from_input(input))
+            .collect();
+        blake2f.assign(&mut layouter.namespace(|| "blake2f"), &witnesses)?;
+        evm.assign(&mut layouter.namespace(|| "evm"), &self.calls)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blake2f_circuit::dev::INPUTS_OUTPUTS as BLAKE2F_INPUTS_OUTPUTS;
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+    };
+    use ripemd160_circuit::dev::INPUTS_OUTPUTS as RIPEMD160_INPUTS_OUTPUTS;
+    use sha2_256_circuit::dev::INPUTS_OUTPUTS as SHA256_INPUTS_OUTPUTS;
+
+    use super::{EvmCall, MockEvmCircuit, BLAKE2F, RIPEMD160, SHA256};
+
+    /// Calls RIPEMD-160 with its test vectors, between calls to SHA2-256 and to BLAKE2F
+    fn calls() -> Vec<EvmCall> {
+        let (inputs, outputs) = SHA256_INPUTS_OUTPUTS.clone();
+        let mut calls = vec![EvmCall {
+            address: SHA256,
+            input: inputs[1].clone(),
+            output: outputs[1].0.to_vec(),
+        }];
+        let (inputs, outputs) = RIPEMD160_INPUTS_OUTPUTS.clone();
+        for (input, output) in inputs.into_iter().zip(outputs) {
+            calls.push(EvmCall {
+                address: RIPEMD160,
+                input,
+                output: output.0.to_vec(),
+            });
+        }
+        let (inputs, outputs) = BLAKE2F_INPUTS_OUTPUTS.clone();
+        calls.push(EvmCall {
+            address: BLAKE2F,
+            input: inputs[0].to_input(),
+            output: outputs[0].0.to_vec(),
+        });
+        calls
+    }
+
+    fn verify(calls: Vec<EvmCall>) -> Result<(), Vec<VerifyFailure>> {
+        let prover = MockProver::<Fr>::run(17, &MockEvmCircuit { calls }, vec![]).unwrap();
+        prover.verify()
+    }
+
+    /// Returns whether a lookup named `name` failed
+    fn failed_lookup(failures: &[VerifyFailure], name: &str) -> bool {
+        failures.iter().any(|failure| match failure {
+            VerifyFailure::Lookup { name: lookup, .. } => *lookup == name,
+            _ => false,
+        })
+    }
+
+    #[test]
+    fn test_mock_evm() {
+        assert_eq!(verify(calls()), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_output() {
+        let mut calls = calls();
+        calls[2].output[0] ^= 1;
+        assert!(failed_lookup(&verify(calls).unwrap_err(), "ripemd160 call"));
+    }
+
+    #[test]
+    fn test_output_of_other_call() {
+        let mut calls = calls();
+        calls[2].output = calls[3].output.clone();
+        assert!(failed_lookup(&verify(calls).unwrap_err(), "ripemd160 call"));
+    }
+
+    #[test]
+    fn test_wrong_sha256_output() {
+        let mut calls = calls();
+        calls[0].output[31] ^= 1;
+        assert!(failed_lookup(&verify(calls).unwrap_err(), "sha256 call"));
+    }
+
+    #[test]
+    fn test_wrong_blake2f_output() {
+        let mut calls = calls();
+        let last = calls.len() - 1;
+        calls[last].output[0] ^= 1;
+        assert!(failed_lookup(&verify(calls).unwrap_err(), "blake2f call"));
+    }
+
+    #[test]
+    fn test_invalid_blake2f_input() {
+        // A final block flag other than 0 or 1 has no row in the table of BLAKE2F
+        let mut calls = calls();
+        let last = calls.len() - 1;
+        calls[last].input[212] = 2;
+        assert!(failed_lookup(&verify(calls).unwrap_err(), "blake2f call"));
+    }
+
+    #[test]
+    fn test_unknown_address() {
+        // A call that claims another address would skip the lookup
+        let mut calls = calls();
+        calls[2].address = 0x04;
+        calls[2].output[0] ^= 1;
+        let failures = verify(calls).unwrap_err();
+        assert!(failures.iter().any(|failure| match failure {
+            VerifyFailure::ConstraintNotSatisfied { constraint, .. } => {
+                constraint.to_string().contains("('precompile address')")
+            }
+            _ => false,
+        }));
+    }
+}
@@ -1,3 +1,5 @@
+mod evm_circuit;
 mod residue_pattern;
 
+pub use evm_circuit::{EvmCall, EvmCallConfig, MockEvmCircuit, BLAKE2F, RIPEMD160, SHA256};
 pub use residue_pattern::{residue_pattern, ResiduePatternChip, ResiduePatternConfig};
@@ -0,0 +1,54 @@
+//! The interface shared by the circuits of the precompiled contracts, so that a
+//! super-circuit can configure and assign each of them in the same way.
+//!
+//! A precompile circuit exposes the calls it verifies through a table, which the EVM
+//! circuit looks up to check the output of each call to the precompile. The table is
+//! configured by the super-circuit and passed to [`PrecompileCircuit::configure`], so that
+//! other circuits can look it up.
+//!
+//! The input and the output of each call are also public inputs of the proof, which the
+//! circuit returns as cells from [`PrecompileCircuit::assign`] and the verifier computes
+//! with [`PrecompileCircuit::public_inputs`].
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Layouter},
+    plonk::{ConstraintSystem, Error},
+};
+
+/// A circuit verifying the calls to a precompiled contract
+pub trait PrecompileCircuit<F: FieldExt>: Clone + fmt::Debug {
+    /// The columns through which other circuits look up the calls
+    type Table: Clone + fmt::Debug;
+    /// The input of a call
+    type Witness: Clone + fmt::Debug;
+    /// The output of a call
+    type Output: Clone + fmt::Debug + PartialEq;
+
+    /// Configures the circuit, exposing its calls in `table`
+    fn configure(meta: &mut ConstraintSystem<F>, table: Self::Table) -> Self;
+
+    /// Assigns the given calls, along with any fixed table the circuit needs. A circuit is
+    /// assigned once, with all of its calls.
+    ///
+    /// Returns the cells holding the public inputs of the calls, one call after the other,
+    /// which the caller constrains to an instance column.
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        witnesses: &[Self::Witness],
+    ) -> Result<Vec<Cell>, Error>;
+
+    /// Returns the public inputs of a call, in the order of the cells returned by
+    /// [`PrecompileCircuit::assign`]
+    fn public_inputs(witness: &Self::Witness) -> Vec<F>;
+
+    /// Returns the number of rows needed to assign the given calls, not counting the
+    /// blinding rows
+    fn min_rows(witnesses: &[Self::Witness]) -> usize;
+
+    /// Computes the output of a call natively
+    fn compute(witness: &Self::Witness) -> Self::Output;
+}
@@ -0,0 +1,67 @@
+//! A circuit with a few rows, for the tests of the keys that do not need a hash circuit.
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    halo2curves::bn256::Fr,
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector},
+    poly::Rotation,
+};
+
+/// Proves that the public input is the square of `x`
+#[derive(Clone, Default)]
+pub struct SquareCircuit {
+    pub x: Value<Fr>,
+}
+
+#[derive(Clone)]
+pub struct SquareConfig {
+    advice: Column<Advice>,
+    instance: Column<Instance>,
+    s_square: Selector,
+}
+
+impl Circuit<Fr> for SquareCircuit {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let advice = meta.advice_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(advice);
+        meta.enable_equality(instance);
+
+        let s_square = meta.selector();
+        meta.create_gate("square", |meta| {
+            let s_square = meta.query_selector(s_square);
+            let x = meta.query_advice(advice, Rotation::cur());
+            let square = meta.query_advice(advice, Rotation::next());
+            vec![s_square * (x.clone() * x - square)]
+        });
+
+        SquareConfig {
+            advice,
+            instance,
+            s_square,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let square = layouter.assign_region(
+            || "square",
+            |mut region| {
+                config.s_square.enable(&mut region, 0)?;
+                region.assign_advice(|| "x", config.advice, 0, || self.x)?;
+                region.assign_advice(|| "square", config.advice, 1, || self.x * self.x)
+            },
+        )?;
+        layouter.constrain_instance(square.cell(), config.instance, 0)
+    }
+}
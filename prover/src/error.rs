@@ -0,0 +1,74 @@
+//! The error type of the prover.
+
+use std::{fmt, io, path::PathBuf};
+
+use halo2_proofs::plonk;
+
+/// An error returned by the commands of the prover
+#[derive(Debug)]
+pub enum Error {
+    /// An error of the proving system
+    Plonk(plonk::Error),
+    /// A file could not be read or written
+    Io { path: PathBuf, err: io::Error },
+    /// The input is not valid hex
+    Hex(hex::FromHexError),
+    /// The calls are not valid calls to the precompiles
+    Call(zkp_mooc_halo2::Error),
+    /// The calls do not fit in a circuit of `2^k` rows
+    Degree { k: u32, min_k: u32 },
+    /// The proof was rejected by the verifier
+    InvalidProof(plonk::Error),
+    /// The public inputs written with a proof are not field elements, or are not the ones
+    /// of the calls being verified
+    PublicInputs { path: PathBuf },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Plonk(err) => write!(f, "{}", err),
+            Error::Io { path, err } => write!(f, "{}: {}", path.display(), err),
+            Error::Hex(err) => write!(f, "invalid hex input: {}", err),
+            Error::Call(err) => write!(f, "{}", err),
+            Error::Degree { k, min_k } => {
+                write!(f, "the input needs k >= {}, got k = {}", min_k, k)
+            }
+            Error::InvalidProof(err) => write!(f, "the proof is invalid: {}", err),
+            Error::PublicInputs { path } => write!(
+                f,
+                "{}: the public inputs are not the input and output of the call",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { err, .. } => Some(err),
+            Error::Hex(err) => Some(err),
+            Error::Call(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<plonk::Error> for Error {
+    fn from(err: plonk::Error) -> Self {
+        Error::Plonk(err)
+    }
+}
+
+impl From<hex::FromHexError> for Error {
+    fn from(err: hex::FromHexError) -> Self {
+        Error::Hex(err)
+    }
+}
+
+impl From<zkp_mooc_halo2::Error> for Error {
+    fn from(err: zkp_mooc_halo2::Error) -> Self {
+        Error::Call(err)
+    }
+}
@@ -0,0 +1,181 @@
+//! Fingerprints of the verifying keys of the circuits, so that a change of their
+//! constraints or layout does not go unnoticed.
+//!
+//! The fingerprint of a circuit is the hash of its verifying key, which covers the
+//! constraint system and the commitments to the fixed and permutation columns. The params
+//! are generated from a fixed seed, so the fingerprint only changes with the circuit. The
+//! tests compare the fingerprints to the ones pinned in `fingerprints.txt`: a change of a
+//! circuit fails them until the file is updated with
+//!
+//! ```text
+//! UPDATE_FINGERPRINTS=1 cargo test -p prover fingerprint
+//! ```
+//!
+//! [`crate::keys::load_or_keygen`] also stores the fingerprint next to a cached proving
+//! key, to tell whether the key is still the one of the circuit.
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{keygen_vk, Circuit, VerifyingKey},
+    poly::kzg::commitment::ParamsKZG,
+};
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use crate::Error;
+
+/// The seed of the params, which must not change
+const SEED: [u8; 16] = [
+    0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc, 0xe5,
+];
+
+/// Returns the fingerprint of the verifying key of `circuit` with `2^k` rows, in hex
+pub fn vk_fingerprint<C: Circuit<Fr>>(circuit: &C, k: u32) -> Result<String, Error> {
+    let params = ParamsKZG::<Bn256>::setup(k, XorShiftRng::from_seed(SEED));
+    let vk = keygen_vk(&params, circuit)?;
+    Ok(fingerprint(&vk))
+}
+
+/// Returns the fingerprint of `vk`, as the hex of the little-endian bytes of its
+/// transcript representation
+pub fn fingerprint(vk: &VerifyingKey<G1Affine>) -> String {
+    hex::encode(vk.transcript_repr().to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, marker::PhantomData, path::PathBuf, sync::Mutex};
+
+    use halo2_proofs::{halo2curves::bn256::Fr, plonk::Circuit};
+    use zkp_mooc_halo2::{
+        blake2f_circuit::dev::{Blake2fTestCircuit, INPUTS_OUTPUTS as BLAKE2F_INPUTS_OUTPUTS},
+        ripemd160_circuit::dev::{
+            Ripemd160TestCircuit, INPUTS_OUTPUTS as RIPEMD160_INPUTS_OUTPUTS,
+        },
+        sha2_256_circuit::dev::{Sha2TestCircuit, INPUTS_OUTPUTS as SHA256_INPUTS_OUTPUTS},
+        Precompile, PrecompileCall, SuperCircuit,
+    };
+
+    use super::vk_fingerprint;
+    use crate::dev::SquareCircuit;
+
+    /// Serialises the updates of the file by the tests, which run in parallel
+    static UPDATE: Mutex<()> = Mutex::new(());
+
+    fn fingerprints_file() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("fingerprints.txt")
+    }
+
+    /// Reads the pinned fingerprints, as lines of a name, `k` and a fingerprint
+    fn read_fingerprints() -> Vec<(String, u32, String)> {
+        fs::read_to_string(fingerprints_file())
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                assert_eq!(
+                    fields.len(),
+                    3,
+                    "invalid line in fingerprints.txt: {}",
+                    line
+                );
+                (
+                    fields[0].to_string(),
+                    fields[1].parse().unwrap(),
+                    fields[2].to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Replaces the pinned fingerprint of `name`
+    fn update_fingerprint(name: &str, k: u32, fingerprint: &str) {
+        let _lock = UPDATE.lock().unwrap();
+        let path = fingerprints_file();
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        let mut lines: Vec<_> = contents
+            .lines()
+            .filter(|line| line.split_whitespace().next() != Some(name))
+            .map(String::from)
+            .collect();
+        lines.push(format!("{} {} {}", name, k, fingerprint));
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+    }
+
+    /// Checks the fingerprint of `circuit` against the pinned one, or pins it if the
+    /// `UPDATE_FINGERPRINTS` env var is set
+    fn check_fingerprint<C: Circuit<Fr>>(name: &str, circuit: &C, k: u32) {
+        let fingerprint = vk_fingerprint(circuit, k).unwrap();
+        if env::var("UPDATE_FINGERPRINTS").is_ok() {
+            update_fingerprint(name, k, &fingerprint);
+            return;
+        }
+
+        let pinned = read_fingerprints()
+            .into_iter()
+            .find(|(pinned, _, _)| pinned == name);
+        let hint = "if the change is intended, run `UPDATE_FINGERPRINTS=1 cargo test -p prover \
+                    fingerprint` and commit fingerprints.txt";
+        match pinned {
+            None => panic!("no fingerprint is pinned for {}; {}", name, hint),
+            Some((_, pinned_k, pinned)) => {
+                assert_eq!(pinned_k, k, "{} is pinned with another k; {}", name, hint);
+                assert_eq!(
+                    pinned, fingerprint,
+                    "the verifying key of {} changed; {}",
+                    name, hint
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        let circuit = SquareCircuit::default();
+        assert_eq!(
+            vk_fingerprint(&circuit, 4).unwrap(),
+            vk_fingerprint(&circuit, 4).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ripemd160() {
+        let (inputs, outputs) = RIPEMD160_INPUTS_OUTPUTS.clone();
+        let circuit = Ripemd160TestCircuit::<Fr> {
+            inputs,
+            outputs,
+            _marker: PhantomData,
+        };
+        check_fingerprint("ripemd160", &circuit, 17);
+    }
+
+    #[test]
+    fn test_fingerprint_sha256() {
+        let (inputs, outputs) = SHA256_INPUTS_OUTPUTS.clone();
+        let circuit = Sha2TestCircuit::<Fr> {
+            inputs,
+            outputs,
+            _marker: PhantomData,
+        };
+        check_fingerprint("sha256", &circuit, 17);
+    }
+
+    #[test]
+    fn test_fingerprint_blake2f() {
+        let (inputs, outputs) = BLAKE2F_INPUTS_OUTPUTS.clone();
+        let circuit = Blake2fTestCircuit::<Fr> {
+            inputs,
+            outputs,
+            _marker: PhantomData,
+        };
+        check_fingerprint("blake2f", &circuit, 17);
+    }
+
+    #[test]
+    fn test_fingerprint_super_circuit() {
+        let calls = vec![PrecompileCall::new(Precompile::Ripemd160, b"abc".to_vec())];
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+        check_fingerprint("super_circuit", &circuit, circuit.min_k());
+    }
+}
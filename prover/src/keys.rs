@@ -0,0 +1,268 @@
+//! Writes the KZG params and the keys of a circuit to files in halo2's serialisation
+//! format, and reads them back.
+//!
+//! A key is read with the config of the circuit it was generated for, so the files of the
+//! keys are stale whenever the layout of the circuit changes. [`load_or_setup_params`] and
+//! [`load_or_keygen`] cache the params and the proving key in files, so that a benchmark
+//! only generates them on its first run. The proving key is cached along with the
+//! fingerprint of its verifying key, and generated again when the circuit no longer has
+//! that fingerprint.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
+    poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    SerdeFormat,
+};
+use rand::RngCore;
+
+use crate::{fingerprint::fingerprint, Error};
+
+/// The format of the keys, which stores the points uncompressed and unchecked, so that the
+/// keys are read fast
+pub const FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+pub fn write_params(params: &ParamsKZG<Bn256>, path: &Path) -> Result<(), Error> {
+    write(path, |writer| params.write(writer))
+}
+
+pub fn read_params(path: &Path) -> Result<ParamsKZG<Bn256>, Error> {
+    read(path, |reader| ParamsKZG::read(reader))
+}
+
+pub fn write_pk(pk: &ProvingKey<G1Affine>, path: &Path) -> Result<(), Error> {
+    write(path, |writer| pk.write(writer, FORMAT))
+}
+
+/// Reads the proving key of the circuit `C`
+pub fn read_pk<C: Circuit<Fr>>(path: &Path) -> Result<ProvingKey<G1Affine>, Error> {
+    read(path, |reader| ProvingKey::read::<_, C>(reader, FORMAT))
+}
+
+pub fn write_vk(vk: &VerifyingKey<G1Affine>, path: &Path) -> Result<(), Error> {
+    write(path, |writer| vk.write(writer, FORMAT))
+}
+
+/// Reads the verifying key of the circuit `C`
+pub fn read_vk<C: Circuit<Fr>>(path: &Path) -> Result<VerifyingKey<G1Affine>, Error> {
+    read(path, |reader| VerifyingKey::read::<_, C>(reader, FORMAT))
+}
+
+/// Reads the params in `path` if they have `2^k` rows, or generates them and writes them
+/// to `path`
+pub fn load_or_setup_params(
+    path: &Path,
+    k: u32,
+    rng: impl RngCore,
+) -> Result<ParamsKZG<Bn256>, Error> {
+    if path.exists() {
+        let params = read_params(path)?;
+        if params.k() == k {
+            return Ok(params);
+        }
+    }
+    let params = ParamsKZG::setup(k, rng);
+    write_params(&params, path)?;
+    Ok(params)
+}
+
+/// Reads the proving key of `circuit` in `path`, or generates it and writes it to `path`.
+///
+/// The fingerprint of the verifying key is written next to the key, with the `fingerprint`
+/// extension. The cached key is only read if it has the fingerprint of the verifying key of
+/// `circuit` with `params`, which is generated to compare them, so a key of another layout
+/// or of other params is generated again.
+pub fn load_or_keygen<C: Circuit<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    circuit: &C,
+    path: &Path,
+) -> Result<ProvingKey<G1Affine>, Error> {
+    let vk = keygen_vk(params, circuit)?;
+    let vk_fingerprint = fingerprint(&vk);
+    let fingerprint_path = path.with_extension("fingerprint");
+    let cached = fs::read_to_string(&fingerprint_path).ok();
+    if path.exists() && cached.as_deref() == Some(vk_fingerprint.as_str()) {
+        return read_pk::<C>(path);
+    }
+    let pk = keygen_pk(params, vk, circuit)?;
+    write_pk(&pk, path)?;
+    // Written last, so that a key that is not fully written is not read back
+    write(&fingerprint_path, |writer| {
+        writer.write_all(vk_fingerprint.as_bytes())
+    })?;
+    Ok(pk)
+}
+
+pub(crate) fn write(
+    path: &Path,
+    f: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> Result<(), Error> {
+    File::create(path)
+        .map(BufWriter::new)
+        .and_then(|mut writer| {
+            f(&mut writer)?;
+            writer.flush()
+        })
+        .map_err(|err| Error::Io {
+            path: path.to_path_buf(),
+            err,
+        })
+}
+
+fn read<T>(path: &Path, f: impl FnOnce(&mut BufReader<File>) -> io::Result<T>) -> Result<T, Error> {
+    File::open(path)
+        .map(BufReader::new)
+        .and_then(|mut reader| f(&mut reader))
+        .map_err(|err| Error::Io {
+            path: path.to_path_buf(),
+            err,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, marker::PhantomData, path::PathBuf};
+
+    use halo2_proofs::{
+        circuit::Value,
+        halo2curves::bn256::{Bn256, Fr},
+        plonk::{keygen_pk, keygen_vk, Circuit},
+        poly::{commitment::Params, kzg::commitment::ParamsKZG},
+    };
+    use rand::rngs::OsRng;
+    use zkp_mooc_halo2::{
+        ripemd160_circuit::dev::{Ripemd160TestCircuit, INPUTS_OUTPUTS},
+        Precompile,
+    };
+
+    use super::{
+        load_or_keygen, load_or_setup_params, read_params, read_pk, read_vk, write_params,
+        write_pk, write_vk, FORMAT,
+    };
+    use crate::{
+        circuit, create_proof, dev::SquareCircuit, fingerprint::fingerprint, verify_proof,
+    };
+
+    fn params_bytes(params: &ParamsKZG<Bn256>) -> Vec<u8> {
+        let mut bytes = vec![];
+        params.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("prover-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Writes the params and the keys of `circuit`, and proves and verifies it with the
+    /// params and the keys read back
+    fn round_trip<C: Circuit<Fr>>(name: &str, circuit: C, k: u32, instances: &[&[Fr]]) {
+        let dir = temp_dir(name);
+        let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let pk = keygen_pk(&params, vk.clone(), &circuit).unwrap();
+        write_params(&params, &dir.join("params.bin")).unwrap();
+        write_pk(&pk, &dir.join("pk.bin")).unwrap();
+        write_vk(&vk, &dir.join("vk.bin")).unwrap();
+
+        let params = read_params(&dir.join("params.bin")).unwrap();
+        let pk = read_pk::<C>(&dir.join("pk.bin")).unwrap();
+        let vk = read_vk::<C>(&dir.join("vk.bin")).unwrap();
+        assert_eq!(vk.to_bytes(FORMAT), pk.get_vk().to_bytes(FORMAT));
+
+        let proof = create_proof(&params, &pk, circuit, instances).unwrap();
+        verify_proof(&params, &vk, instances, &proof).unwrap();
+
+        // The cached params are read back rather than generated again
+        let cached = load_or_setup_params(&dir.join("params.bin"), k, OsRng).unwrap();
+        assert_eq!(params_bytes(&cached), params_bytes(&params));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let circuit = SquareCircuit {
+            x: Value::known(Fr::from(3)),
+        };
+        round_trip("round-trip", circuit, 8, &[&[Fr::from(9)]]);
+    }
+
+    #[test]
+    fn test_load_or_keygen() {
+        let dir = temp_dir("load-or-keygen");
+        let circuit = SquareCircuit::default();
+        let params = load_or_setup_params(&dir.join("params.bin"), 8, OsRng).unwrap();
+        let pk = load_or_keygen(&params, &circuit, &dir.join("pk.bin")).unwrap();
+        let cached = load_or_keygen(&params, &circuit, &dir.join("pk.bin")).unwrap();
+        assert_eq!(
+            cached.get_vk().to_bytes(FORMAT),
+            pk.get_vk().to_bytes(FORMAT)
+        );
+
+        // A valid key whose fingerprint file is corrupted is generated again, which restores
+        // its fingerprint, as reading the cached key leaves the file untouched
+        let mut corrupted = fs::read(dir.join("pk.fingerprint")).unwrap();
+        corrupted[0] ^= 1;
+        fs::write(dir.join("pk.fingerprint"), &corrupted).unwrap();
+        let regenerated = load_or_keygen(&params, &circuit, &dir.join("pk.bin")).unwrap();
+        assert_eq!(
+            regenerated.get_vk().to_bytes(FORMAT),
+            pk.get_vk().to_bytes(FORMAT)
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("pk.fingerprint")).unwrap(),
+            fingerprint(pk.get_vk())
+        );
+
+        // As is a key whose fingerprint is not the one of the circuit
+        fs::write(dir.join("pk.bin"), b"not a key").unwrap();
+        fs::write(dir.join("pk.fingerprint"), b"0x00").unwrap();
+        let regenerated = load_or_keygen(&params, &circuit, &dir.join("pk.bin")).unwrap();
+        assert_eq!(
+            regenerated.get_vk().to_bytes(FORMAT),
+            pk.get_vk().to_bytes(FORMAT)
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("pk.fingerprint")).unwrap(),
+            fingerprint(pk.get_vk())
+        );
+
+        // As is a key without a fingerprint
+        fs::remove_file(dir.join("pk.fingerprint")).unwrap();
+        fs::write(dir.join("pk.bin"), b"not a key").unwrap();
+        assert!(load_or_keygen(&params, &circuit, &dir.join("pk.bin")).is_ok());
+
+        // Params of another size are generated again
+        let params = load_or_setup_params(&dir.join("params.bin"), 9, OsRng).unwrap();
+        assert_eq!(params.k(), 9);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// The smallest circuit of RIPEMD-160, a single block, which needs 2^17 rows for the
+    /// spread table
+    #[test]
+    fn test_round_trip_ripemd160() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let circuit = Ripemd160TestCircuit::<Fr> {
+            inputs: inputs[..1].to_vec(),
+            outputs: outputs[..1].to_vec(),
+            _marker: PhantomData,
+        };
+        round_trip("round-trip-ripemd160", circuit, 17, &[]);
+    }
+
+    #[test]
+    fn test_round_trip_super_circuit() {
+        let circuit = circuit(Precompile::Ripemd160, b"abc".to_vec()).unwrap();
+        let k = circuit.min_k();
+        let public_inputs = circuit.public_inputs();
+        round_trip("round-trip-super-circuit", circuit, k, &[&public_inputs]);
+    }
+}
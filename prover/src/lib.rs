@@ -0,0 +1,279 @@
+//! Generates and verifies proofs of calls to the hash precompiles, writing the KZG params,
+//! the keys and the proofs to files.
+//!
+//! A proof is made of the [`SuperCircuit`] with a single call. The layout of the circuit
+//! depends on the length of the input, so the keys of [`setup`] only prove inputs of the
+//! same length.
+//!
+//! The public inputs of a proof are the input and the output of the call, which [`prove`]
+//! writes next to the proof. [`verify`] takes the call, checks that the public inputs in
+//! the directory are the ones of the call and verifies the proof against them, so a proof
+//! of another input or of a wrong output is rejected.
+
+use std::{fs, io::Write, path::Path};
+
+use halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{self, keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::{ProverSHPLONK, VerifierSHPLONK},
+            strategy::SingleStrategy,
+        },
+    },
+    transcript::{
+        Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer,
+    },
+};
+use rand::rngs::OsRng;
+use zkp_mooc_halo2::{Precompile, PrecompileCall, SuperCircuit};
+
+#[cfg(test)]
+mod dev;
+mod error;
+pub mod fingerprint;
+pub mod keys;
+
+pub use error::Error;
+
+/// The file of the KZG params, in the output directory
+pub const PARAMS_FILE: &str = "params.bin";
+/// The file of the proving key
+pub const PK_FILE: &str = "pk.bin";
+/// The file of the verifying key
+pub const VK_FILE: &str = "vk.bin";
+/// The file of the proof
+pub const PROOF_FILE: &str = "proof.bin";
+/// The file of the public inputs of the proof, each as the 32 little-endian bytes of a
+/// field element
+pub const PUBLIC_INPUTS_FILE: &str = "public_inputs.bin";
+
+/// Returns the circuit proving a call to `precompile` with `input`
+pub fn circuit(precompile: Precompile, input: Vec<u8>) -> Result<SuperCircuit<Fr>, Error> {
+    Ok(SuperCircuit::new(vec![PrecompileCall::new(
+        precompile, input,
+    )])?)
+}
+
+/// Decodes an input given in hex, with or without a `0x` prefix
+pub fn parse_hex(input: &str) -> Result<Vec<u8>, Error> {
+    let input = input.trim();
+    Ok(hex::decode(input.strip_prefix("0x").unwrap_or(input))?)
+}
+
+/// Generates KZG params of `2^k` rows, or of the smallest circuit that fits the calls if `k`
+/// is `None`, and the keys of the circuit, and writes them to `dir`. Returns `k`.
+pub fn setup(circuit: &SuperCircuit<Fr>, k: Option<u32>, dir: &Path) -> Result<u32, Error> {
+    let min_k = circuit.min_k();
+    let k = k.unwrap_or(min_k);
+    if k < min_k {
+        return Err(Error::Degree { k, min_k });
+    }
+
+    let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+    let vk = keygen_vk(&params, circuit)?;
+    let pk = keygen_pk(&params, vk.clone(), circuit)?;
+
+    fs::create_dir_all(dir).map_err(|err| Error::Io {
+        path: dir.to_path_buf(),
+        err,
+    })?;
+    keys::write_params(&params, &dir.join(PARAMS_FILE))?;
+    keys::write_pk(&pk, &dir.join(PK_FILE))?;
+    keys::write_vk(&vk, &dir.join(VK_FILE))?;
+    Ok(k)
+}
+
+/// Proves the calls of the circuit with the params and the proving key in `dir`, and
+/// writes the proof and its public inputs to `dir`
+pub fn prove(circuit: SuperCircuit<Fr>, dir: &Path) -> Result<Vec<u8>, Error> {
+    let params = keys::read_params(&dir.join(PARAMS_FILE))?;
+    let pk = keys::read_pk::<SuperCircuit<Fr>>(&dir.join(PK_FILE))?;
+    let public_inputs = circuit.public_inputs();
+    let proof = create_proof(&params, &pk, circuit, &[&public_inputs])?;
+    keys::write(&dir.join(PROOF_FILE), |writer| writer.write_all(&proof))?;
+    write_public_inputs(&public_inputs, &dir.join(PUBLIC_INPUTS_FILE))?;
+    Ok(proof)
+}
+
+/// Verifies the proof in `dir` of the calls of the circuit, with the params and the
+/// verifying key in `dir`. Fails if the public inputs written with the proof are not the
+/// input and the output of the calls.
+pub fn verify(circuit: &SuperCircuit<Fr>, dir: &Path) -> Result<(), Error> {
+    let params = keys::read_params(&dir.join(PARAMS_FILE))?;
+    let vk = keys::read_vk::<SuperCircuit<Fr>>(&dir.join(VK_FILE))?;
+    let path = dir.join(PUBLIC_INPUTS_FILE);
+    let public_inputs = read_public_inputs(&path)?;
+    if public_inputs != circuit.public_inputs() {
+        return Err(Error::PublicInputs { path });
+    }
+    let path = dir.join(PROOF_FILE);
+    let proof = fs::read(&path).map_err(|err| Error::Io { path, err })?;
+    verify_proof(&params, &vk, &[&public_inputs], &proof)
+}
+
+pub fn write_public_inputs(public_inputs: &[Fr], path: &Path) -> Result<(), Error> {
+    keys::write(path, |writer| {
+        public_inputs
+            .iter()
+            .try_for_each(|input| writer.write_all(&input.to_bytes()))
+    })
+}
+
+/// Reads the public inputs in `path`, failing if it does not hold field elements
+pub fn read_public_inputs(path: &Path) -> Result<Vec<Fr>, Error> {
+    let bytes = fs::read(path).map_err(|err| Error::Io {
+        path: path.to_path_buf(),
+        err,
+    })?;
+    if bytes.len() % 32 != 0 {
+        return Err(Error::PublicInputs {
+            path: path.to_path_buf(),
+        });
+    }
+    bytes
+        .chunks_exact(32)
+        .map(|chunk| {
+            Option::from(Fr::from_bytes(chunk.try_into().unwrap())).ok_or_else(|| {
+                Error::PublicInputs {
+                    path: path.to_path_buf(),
+                }
+            })
+        })
+        .collect()
+}
+
+/// Proves `circuit` with the given public inputs, one slice per instance column
+pub fn create_proof<C: Circuit<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instances: &[&[Fr]],
+) -> Result<Vec<u8>, Error> {
+    let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+    plonk::create_proof::<
+        KZGCommitmentScheme<Bn256>,
+        ProverSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        OsRng,
+        Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
+        C,
+    >(params, pk, &[circuit], &[instances], OsRng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+/// Verifies a proof with the given public inputs, one slice per instance column
+pub fn verify_proof(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    instances: &[&[Fr]],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof);
+    plonk::verify_proof::<
+        KZGCommitmentScheme<Bn256>,
+        VerifierSHPLONK<'_, Bn256>,
+        Challenge255<G1Affine>,
+        Blake2bRead<&[u8], G1Affine, Challenge255<G1Affine>>,
+        SingleStrategy<'_, Bn256>,
+    >(
+        params.verifier_params(),
+        vk,
+        SingleStrategy::new(params),
+        &[instances],
+        &mut transcript,
+    )
+    .map_err(Error::InvalidProof)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use zkp_mooc_halo2::{blake2f_circuit::INPUT_LEN, Error as CallError, Precompile};
+
+    use super::{
+        circuit, parse_hex, prove, read_public_inputs, setup, verify, write_public_inputs, Error,
+        PUBLIC_INPUTS_FILE,
+    };
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("0x616263").unwrap(), b"abc");
+        assert_eq!(parse_hex("616263\n").unwrap(), b"abc");
+        assert_eq!(parse_hex("").unwrap(), b"");
+        assert!(matches!(parse_hex("0x6162g3"), Err(Error::Hex(_))));
+    }
+
+    #[test]
+    fn test_circuit() {
+        assert!(circuit(Precompile::Sha256, b"abc".to_vec()).is_ok());
+        assert!(circuit(Precompile::Ripemd160, b"abc".to_vec()).is_ok());
+        assert!(circuit(Precompile::Blake2f, vec![0; INPUT_LEN]).is_ok());
+        assert!(matches!(
+            circuit(Precompile::Blake2f, vec![0; INPUT_LEN - 1]),
+            Err(Error::Call(CallError::InvalidInput { address: 0x09 }))
+        ));
+    }
+
+    #[test]
+    fn test_setup_degree() {
+        // The spread table alone takes 2^16 rows
+        let circuit = circuit(Precompile::Ripemd160, b"abc".to_vec()).unwrap();
+        let dir = std::env::temp_dir().join("prover-test-setup-degree");
+        assert!(matches!(
+            setup(&circuit, Some(16), &dir),
+            Err(Error::Degree { k: 16, .. })
+        ));
+    }
+
+    #[test]
+    fn test_public_inputs_file() {
+        let dir = std::env::temp_dir().join(format!("prover-public-inputs-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("public_inputs.bin");
+
+        let public_inputs = vec![Fr::from(0x80636261), -Fr::from(1)];
+        write_public_inputs(&public_inputs, &path).unwrap();
+        assert_eq!(read_public_inputs(&path).unwrap(), public_inputs);
+
+        // A truncated file and a value that is not a field element are rejected
+        fs::write(&path, [0; 31]).unwrap();
+        assert!(matches!(
+            read_public_inputs(&path),
+            Err(Error::PublicInputs { .. })
+        ));
+        fs::write(&path, [0xff; 32]).unwrap();
+        assert!(matches!(
+            read_public_inputs(&path),
+            Err(Error::PublicInputs { .. })
+        ));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Runs at the smallest `k` of a real circuit, 17, as the spread table alone takes 2^16
+    /// rows
+    #[test]
+    fn test_verify_call() {
+        let dir = std::env::temp_dir().join(format!("prover-verify-call-{}", std::process::id()));
+        let abc = circuit(Precompile::Ripemd160, b"abc".to_vec()).unwrap();
+        assert_eq!(setup(&abc, None, &dir).unwrap(), 17);
+        prove(abc.clone(), &dir).unwrap();
+        verify(&abc, &dir).unwrap();
+
+        // The proof of "abc" is not a proof of another input of the same length
+        let abd = circuit(Precompile::Ripemd160, b"abd".to_vec()).unwrap();
+        assert!(matches!(
+            verify(&abd, &dir),
+            Err(Error::PublicInputs { .. })
+        ));
+
+        // Nor are the public inputs of "abd" accepted with the proof of "abc"
+        write_public_inputs(&abd.public_inputs(), &dir.join(PUBLIC_INPUTS_FILE)).unwrap();
+        assert!(matches!(verify(&abd, &dir), Err(Error::InvalidProof(_))));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,140 @@
+//! Sets up, proves and verifies calls to the hash precompiles from the command line:
+//!
+//! ```text
+//! prover setup --algorithm ripemd160 --hex 616263 --dir out
+//! prover prove --algorithm ripemd160 --hex 616263 --dir out
+//! prover verify --algorithm ripemd160 --hex 616263 --dir out
+//! ```
+//!
+//! The input of a call to BLAKE2F is the 213 bytes that the EVM passes to the precompile.
+
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use prover::{circuit, parse_hex, prove, setup, verify, Error};
+use zkp_mooc_halo2::Precompile;
+
+#[derive(Parser)]
+#[command(about = "Proves calls to the RIPEMD-160, SHA2-256 and BLAKE2F precompiles")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generates the KZG params and the keys for an input, and writes them to the directory
+    Setup {
+        #[command(flatten)]
+        call: Call,
+        /// The circuit has 2^k rows, by default the fewest that fit the input
+        #[arg(short)]
+        k: Option<u32>,
+    },
+    /// Proves a call with the params and the proving key in the directory, and writes the
+    /// proof to it
+    Prove {
+        #[command(flatten)]
+        call: Call,
+    },
+    /// Verifies the proof of a call in the directory, whose public inputs are the input and
+    /// the output of the call
+    Verify {
+        #[command(flatten)]
+        call: Call,
+    },
+}
+
+#[derive(Args)]
+struct Call {
+    #[arg(long, value_enum)]
+    algorithm: Algorithm,
+    #[command(flatten)]
+    input: Input,
+    /// The directory of the params, the keys and the proof
+    #[arg(long, default_value = ".")]
+    dir: PathBuf,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct Input {
+    /// The input in hex. The input of BLAKE2F is the 213 bytes of EIP-152.
+    #[arg(long)]
+    hex: Option<String>,
+    /// A file holding the input as raw bytes
+    #[arg(long)]
+    file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Algorithm {
+    Ripemd160,
+    Sha256,
+    Blake2f,
+}
+
+impl From<Algorithm> for Precompile {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Ripemd160 => Precompile::Ripemd160,
+            Algorithm::Sha256 => Precompile::Sha256,
+            Algorithm::Blake2f => Precompile::Blake2f,
+        }
+    }
+}
+
+impl Input {
+    fn read(&self) -> Result<Vec<u8>, Error> {
+        match (&self.hex, &self.file) {
+            (Some(hex), _) => parse_hex(hex),
+            (_, Some(path)) => fs::read(path).map_err(|err| Error::Io {
+                path: path.clone(),
+                err,
+            }),
+            (None, None) => unreachable!("clap requires one of the inputs"),
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Error> {
+    match command {
+        Command::Setup { call, k } => {
+            let circuit = circuit(call.algorithm.into(), call.input.read()?)?;
+            let k = setup(&circuit, k, &call.dir)?;
+            println!(
+                "wrote the params and keys with k = {} to {}",
+                k,
+                call.dir.display()
+            );
+        }
+        Command::Prove { call } => {
+            let circuit = circuit(call.algorithm.into(), call.input.read()?)?;
+            let output = circuit.outputs().remove(0);
+            let proof = prove(circuit, &call.dir)?;
+            println!("output: {}", hex::encode(output));
+            println!(
+                "wrote a {}-byte proof to {}",
+                proof.len(),
+                call.dir.display()
+            );
+        }
+        Command::Verify { call } => {
+            let circuit = circuit(call.algorithm.into(), call.input.read()?)?;
+            verify(&circuit, &call.dir)?;
+            println!("output: {}", hex::encode(circuit.outputs().remove(0)));
+            println!("the proof is valid");
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -4,6 +4,7 @@ pub const BLOCK_SIZE: usize = 16;
 pub const BLOCK_SIZE_BYTES: usize = BLOCK_SIZE * 4;
 pub const ROUNDS: usize = 80;
 pub const ROUND_PHASE_SIZE: usize = 16;
+pub const SHA256_DIGEST_SIZE: usize = 8;
 
 pub const INITIAL_VALUES: [u32; DIGEST_SIZE] = [
     0x6745_2301,
@@ -13,6 +14,15 @@ pub const INITIAL_VALUES: [u32; DIGEST_SIZE] = [
     0xC3D2_E1F0,
 ];
 
+// Initial values of the right line of RIPEMD-256 (the first four words) and RIPEMD-320
+pub const INITIAL_VALUES_RIGHT: [u32; DIGEST_SIZE] = [
+    0x7654_3210,
+    0xFEDC_BA98,
+    0x89AB_CDEF,
+    0x0123_4567,
+    0x3C2D_1E0F,
+];
+
 pub const ROUND_CONSTANTS_LEFT: [u32; ROUNDS / ROUND_PHASE_SIZE] = [
     0x0000_0000,
     0x5A82_7999,
@@ -28,6 +38,10 @@ pub const ROUND_CONSTANTS_RIGHT: [u32; ROUNDS / ROUND_PHASE_SIZE] = [
     0x0000_0000,
 ];
 
+// Right line round constants of RIPEMD-128 and RIPEMD-256, which have four phases
+pub const ROUND_CONSTANTS_RIGHT_4_PHASES: [u32; 4] =
+    [0x50A2_8BE6, 0x5C4D_D124, 0x6D70_3EF3, 0x0000_0000];
+
 pub const MSG_SEL_IDX_LEFT: [usize; ROUNDS] = [
     0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5,
     2, 14, 11, 8, 3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, 1, 9, 11, 10, 0, 8, 12, 4,
@@ -0,0 +1,63 @@
+//! Property tests checking that the circuit, the native module and the `ripemd` crate
+//! agree on the RIPEMD-160 digest of random messages.
+
+use std::marker::PhantomData;
+
+use ethers_core::types::H160;
+use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use proptest::{collection::vec, prelude::*};
+use ripemd::{Digest, Ripemd160 as ReferenceRipemd160};
+
+use crate::{constants::BLOCK_SIZE_BYTES, dev::Ripemd160TestCircuit, native};
+
+/// Messages span up to this many blocks, before padding
+const MAX_BLOCKS: usize = 4;
+
+/// Lengths where the padding changes shape: the last byte of a block that still fits the
+/// length, the first byte that does not, and full blocks.
+const BOUNDARY_LENGTHS: [usize; 8] = [55, 56, 63, 64, 119, 120, 127, 128];
+
+fn message() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        proptest::sample::select(BOUNDARY_LENGTHS.to_vec()),
+        0..=MAX_BLOCKS * BLOCK_SIZE_BYTES,
+    ]
+    .prop_flat_map(|len| vec(any::<u8>(), len))
+}
+
+fn reference_hash(msg: &[u8]) -> [u8; 20] {
+    ReferenceRipemd160::digest(msg).into()
+}
+
+proptest! {
+    #[test]
+    fn native_matches_reference(msg in message()) {
+        prop_assert_eq!(native::hash(msg.clone()), reference_hash(&msg));
+    }
+
+    #[test]
+    fn streaming_matches_reference(msg in message(), chunk in 1..=2 * BLOCK_SIZE_BYTES) {
+        let mut hasher = native::Ripemd160::new();
+        for part in msg.chunks(chunk) {
+            hasher.update(part);
+        }
+        prop_assert_eq!(hasher.finalize(), reference_hash(&msg));
+    }
+}
+
+proptest! {
+    // Each case runs the mock prover over the whole 2^17 rows
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn circuit_matches_reference(msg in message()) {
+        let circuit: Ripemd160TestCircuit<Fr> = Ripemd160TestCircuit {
+            outputs: vec![H160(reference_hash(&msg))],
+            inputs: vec![msg],
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        prop_assert_eq!(prover.verify(), Ok(()));
+    }
+}
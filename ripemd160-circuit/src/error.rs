@@ -0,0 +1,158 @@
+//! The error type of the chips and gadgets of this crate.
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, Region},
+    plonk,
+};
+
+use crate::constants::BLOCK_SIZE;
+
+/// An error returned by the chips and gadgets of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// An error of the proving system
+    Plonk(plonk::Error),
+    /// A value does not fit the number of bits of the cell or word it is assigned to
+    BitLength { bits: usize, value: String },
+    /// A byte slice does not have the length of the words it is converted to
+    ByteLength { expected: usize, actual: usize },
+    /// The length of a message does not match the number of its padded blocks
+    MessageLength { len: usize, blocks: usize },
+    /// An HMAC key given as words does not fit in a block
+    KeyLength { words: usize },
+    /// RLCs were requested from a chip configured without a challenge
+    RlcNotConfigured,
+    /// Calls were assigned to a lookup table by a chip configured without one
+    TableNotConfigured,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Plonk(err) => write!(f, "{}", err),
+            Error::BitLength { bits, value } => {
+                write!(f, "{} does not fit in {} bits", value, bits)
+            }
+            Error::ByteLength { expected, actual } => {
+                write!(f, "expected {} bytes, got {}", expected, actual)
+            }
+            Error::MessageLength { len, blocks } => {
+                write!(
+                    f,
+                    "a {}-byte message does not pad to {} blocks",
+                    len, blocks
+                )
+            }
+            Error::KeyLength { words } => {
+                write!(
+                    f,
+                    "an HMAC key of {} words does not fit in a block of {} words",
+                    words, BLOCK_SIZE
+                )
+            }
+            Error::RlcNotConfigured => write!(f, "the chip is configured without RLCs"),
+            Error::TableNotConfigured => {
+                write!(f, "the chip is configured without a lookup table")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Plonk(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<plonk::Error> for Error {
+    fn from(err: plonk::Error) -> Self {
+        Error::Plonk(err)
+    }
+}
+
+impl From<bitwise_gadgets::Error> for Error {
+    fn from(err: bitwise_gadgets::Error) -> Self {
+        match err {
+            bitwise_gadgets::Error::Plonk(err) => Error::Plonk(err),
+            bitwise_gadgets::Error::BitLength { bits, value } => Error::BitLength { bits, value },
+        }
+    }
+}
+
+/// Lets a [`halo2_proofs::plonk::Circuit`] use the chips with `?`. The errors of this
+/// crate become [`plonk::Error::Synthesis`].
+impl From<Error> for plonk::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Plonk(err) => err,
+            _ => plonk::Error::Synthesis,
+        }
+    }
+}
+
+/// Assigns a region like [`Layouter::assign_region`], returning the error of the
+/// assignment as is instead of as a [`plonk::Error`]
+pub(crate) fn assign_region<F, L, A, AR, N, NR>(
+    layouter: &mut L,
+    name: N,
+    mut assignment: A,
+) -> Result<AR, Error>
+where
+    F: FieldExt,
+    L: Layouter<F>,
+    A: FnMut(Region<'_, F>) -> Result<AR, Error>,
+    N: Fn() -> NR,
+    NR: Into<String>,
+{
+    let mut error = None;
+    layouter
+        .assign_region(name, |region| {
+            assignment(region).map_err(|err| {
+                error = Some(err);
+                plonk::Error::Synthesis
+            })
+        })
+        .map_err(|err| error.take().unwrap_or(Error::Plonk(err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::plonk;
+
+    use super::Error;
+    use crate::table16::util::convert_byte_slice_to_u32_slice;
+
+    #[test]
+    fn test_byte_length() {
+        assert_eq!(
+            convert_byte_slice_to_u32_slice::<8, 2>([1, 0, 0, 0, 2, 0, 0, 0]).unwrap(),
+            [1, 2]
+        );
+        assert!(matches!(
+            convert_byte_slice_to_u32_slice::<7, 2>([0; 7]),
+            Err(Error::ByteLength {
+                expected: 8,
+                actual: 7
+            })
+        ));
+    }
+
+    #[test]
+    fn test_plonk_error_conversion() {
+        let err = Error::from(plonk::Error::Synthesis);
+        assert!(matches!(plonk::Error::from(err), plonk::Error::Synthesis));
+        assert!(matches!(
+            plonk::Error::from(Error::BitLength {
+                bits: 16,
+                value: "65536".to_string()
+            }),
+            plonk::Error::Synthesis
+        ));
+    }
+}
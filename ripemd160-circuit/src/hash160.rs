@@ -0,0 +1,231 @@
+//! Hash160, i.e. RIPEMD-160 over the SHA-256 digest of a message, as used for Bitcoin
+//! P2PKH and P2WPKH addresses.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::Layouter};
+
+use crate::{
+    constants::{DIGEST_SIZE, SHA256_DIGEST_SIZE},
+    Error, RIPEMD160Instructions,
+};
+
+/// The set of circuit instructions required to use the [`Hash160`] gadget.
+pub trait Hash160Instructions<F: FieldExt>: RIPEMD160Instructions<F> {
+    /// Starting from the given initialized state, processes the padded block of a 32-byte
+    /// message given as big-endian words, such as the output of a SHA-256 chip, and returns
+    /// the final state. The words are copy constrained into the message block.
+    fn compress_be_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::Word; SHA256_DIGEST_SIZE],
+    ) -> Result<Self::State, Error>;
+}
+
+/// A gadget that constrains `RIPEMD160(SHA256(x))` given the assigned words of `SHA256(x)`.
+#[derive(Debug)]
+pub struct Hash160<F: FieldExt, CS: Hash160Instructions<F>> {
+    chip: CS,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt, Hash160Chip: Hash160Instructions<F>> Hash160<F, Hash160Chip> {
+    /// Create a new hasher instance
+    pub fn new(chip: Hash160Chip) -> Self {
+        Hash160 {
+            chip,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Computes the RIPEMD-160 digest of a SHA-256 digest.
+    ///
+    /// `sha256_digest` holds the eight big-endian words output by a SHA-256 chip. The
+    /// returned words are the little-endian words of the 20-byte Hash160.
+    pub fn digest(
+        &self,
+        mut layouter: impl Layouter<F>,
+        sha256_digest: [Hash160Chip::Word; SHA256_DIGEST_SIZE],
+    ) -> Result<[Hash160Chip::Word; DIGEST_SIZE], Error> {
+        let state = self.chip.init_vector(&mut layouter.namespace(|| "init"))?;
+        let state = self.chip.compress_be_words(
+            &mut layouter.namespace(|| "compress"),
+            &state,
+            sha256_digest,
+        )?;
+        self.chip
+            .digest_words(&mut layouter.namespace(|| "finalize"), &state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitwise_gadgets::tamper::harness::{failed_constraint, verify_tampered, Tamper};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+    };
+    use sha2::{Digest, Sha256};
+
+    use super::Hash160;
+    use crate::constants::SHA256_DIGEST_SIZE;
+    use crate::table16::{AssignedBits, Table16Chip, Table16Config};
+
+    // (public key, P2PKH address)
+    const PUBKEYS_ADDRESSES: [(&str, &str); 3] = [
+        (
+            "0250863ad64a87ae8a2fe83c1af1a8403cb53f53e486d8511dad8a04887e5b2352",
+            "1PMycacnJaSqwwJqjawXBErnLsZ7RkXUAs",
+        ),
+        (
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+        ),
+        (
+            "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+             483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+            "1EHNa6Q4Jz2uvNExL497mE43ikXhwF6kZm",
+        ),
+    ];
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Returns the Hash160 in a P2PKH address
+    fn address_to_hash160(address: &str) -> [u8; 20] {
+        const ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+        // Version byte, Hash160 and 4-byte checksum, as a big-endian number
+        let mut bytes = [0u8; 25];
+        for c in address.chars() {
+            let mut carry = ALPHABET.find(c).unwrap() as u32;
+            for byte in bytes.iter_mut().rev() {
+                carry += *byte as u32 * 58;
+                *byte = carry as u8;
+                carry >>= 8;
+            }
+            assert_eq!(carry, 0);
+        }
+        assert_eq!(bytes[0], 0, "not a P2PKH address");
+
+        bytes[1..21].try_into().unwrap()
+    }
+
+    struct Hash160TestCircuit {
+        pubkey: Vec<u8>,
+        /// The expected Hash160, unchecked if tampered cells may change it
+        hash160: Option<[u8; 20]>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for Hash160TestCircuit {
+        type Config = (Table16Config<F>, Column<Advice>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Hash160TestCircuit {
+                pubkey: vec![],
+                hash160: None,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            // Stands in for the digest column of a SHA-256 chip
+            let sha256_digest = meta.advice_column();
+            meta.enable_equality(sha256_digest);
+
+            (Table16Chip::configure(meta), sha256_digest)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let (config, sha256_digest_column) = config;
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            // Witness SHA256(pubkey) as a SHA-256 chip would output it
+            let sha256_bytes: [u8; 32] = Sha256::digest(&self.pubkey).into();
+            let sha256_digest: [AssignedBits<32, F>; SHA256_DIGEST_SIZE] = layouter.assign_region(
+                || "sha256 digest",
+                |mut region| {
+                    let mut words = Vec::with_capacity(SHA256_DIGEST_SIZE);
+                    for (idx, bytes) in sha256_bytes.chunks(4).enumerate() {
+                        words.push(AssignedBits::<32, F>::assign(
+                            &mut region,
+                            || format!("sha256 digest word {}", idx),
+                            sha256_digest_column,
+                            idx,
+                            Value::known(u32::from_be_bytes(bytes.try_into().unwrap())),
+                        )?);
+                    }
+                    Ok(words.try_into().unwrap())
+                },
+            )?;
+
+            let digest =
+                Hash160::new(chip).digest(layouter.namespace(|| "hash160"), sha256_digest)?;
+
+            if let Some(hash160) = self.hash160 {
+                for (idx, word) in digest.iter().enumerate() {
+                    let expected =
+                        u32::from_le_bytes(hash160[4 * idx..4 * idx + 4].try_into().unwrap());
+                    word.value_u32().assert_if_known(|v| *v == expected);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hash160() {
+        for (pubkey, address) in PUBKEYS_ADDRESSES {
+            let circuit = Hash160TestCircuit {
+                pubkey: decode_hex(pubkey),
+                hash160: Some(address_to_hash160(address)),
+            };
+
+            let prover = MockProver::<Fr>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    // The first SHA-256 digest word passed on to RIPEMD-160 in its big-endian byte order,
+    // as if the byte swap had been skipped
+    #[test]
+    fn test_unswapped_digest_word() {
+        let pubkey = decode_hex(PUBKEYS_ADDRESSES[0].0);
+        let sha256_bytes: [u8; 32] = Sha256::digest(&pubkey).into();
+        let word = u32::from_be_bytes(sha256_bytes[..4].try_into().unwrap());
+        assert_ne!(word, word.swap_bytes());
+
+        let circuit = Hash160TestCircuit {
+            pubkey,
+            hash160: None,
+        };
+        let failures = verify_tampered(
+            17,
+            &circuit,
+            vec![
+                Tamper::set("swapped_lo", 0, (word & 0xffff).into()),
+                Tamper::set("swapped_hi", 0, (word >> 16).into()),
+            ],
+        );
+        for check in ["swapped_lo_check", "swapped_hi_check"] {
+            assert!(
+                failed_constraint(&failures, "s_swap_bytes", check),
+                "{}",
+                check
+            );
+        }
+    }
+}
@@ -0,0 +1,337 @@
+//! HMAC-RIPEMD160, as specified in [RFC 2104] and [RFC 2286].
+//!
+//! [RFC 2104]: https://www.rfc-editor.org/rfc/rfc2104
+//! [RFC 2286]: https://www.rfc-editor.org/rfc/rfc2286
+
+use std::fmt;
+
+use halo2_proofs::{arithmetic::FieldExt, circuit::Layouter};
+
+use crate::{
+    constants::{BLOCK_SIZE, DIGEST_SIZE},
+    Error, RIPEMD160Instructions,
+};
+
+/// The set of circuit instructions required to use the [`Hmac`] gadget.
+pub trait HmacInstructions<F: FieldExt>: RIPEMD160Instructions<F> {
+    /// Variable representing an assigned input block to the compression function
+    type Block: Clone + fmt::Debug;
+
+    /// Returns the blocks `K XOR ipad` and `K XOR opad` for a key `K` of at most
+    /// `BLOCK_SIZE` little-endian words, which is padded with zero words.
+    fn key_pads(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[Self::Word],
+    ) -> Result<(Self::Block, Self::Block), Error>;
+
+    /// Starting from the given initialized state, processes an assigned block and returns
+    /// the final state
+    fn compress_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &Self::Block,
+    ) -> Result<Self::State, Error>;
+
+    /// Starting from the given initialized state, processes the last block of the outer
+    /// message `(K XOR opad) || digest` and returns the final state. The digest words are
+    /// copy constrained into the message block.
+    fn compress_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        digest: [Self::Word; DIGEST_SIZE],
+    ) -> Result<Self::State, Error>;
+}
+
+/// An HMAC key, given as little-endian words.
+#[derive(Clone, Debug)]
+pub enum HmacKey<BlockWord> {
+    /// A key of at most 64 bytes, padded with zero bytes to a multiple of 4 bytes
+    Short(Vec<BlockWord>),
+    /// The padded blocks of a key longer than 64 bytes, which is hashed first
+    Long(Vec<[BlockWord; BLOCK_SIZE]>),
+}
+
+/// A gadget that constrains an HMAC-RIPEMD160 with a private key.
+#[derive(Debug)]
+pub struct Hmac<F: FieldExt, CS: HmacInstructions<F>> {
+    chip: CS,
+    state: CS::State,
+    opad: CS::Block,
+}
+
+impl<F: FieldExt, HmacChip: HmacInstructions<F>> Hmac<F, HmacChip> {
+    /// Create a new HMAC instance, processing the inner key block
+    pub fn new(
+        chip: HmacChip,
+        mut layouter: impl Layouter<F>,
+        key: &HmacKey<HmacChip::BlockWord>,
+    ) -> Result<Self, Error> {
+        let key = match key {
+            HmacKey::Short(words) => {
+                if words.len() > BLOCK_SIZE {
+                    return Err(Error::KeyLength { words: words.len() });
+                }
+                chip.witness_words(&mut layouter.namespace(|| "key"), words)?
+            }
+            HmacKey::Long(blocks) => {
+                let mut state = chip.init_vector(&mut layouter.namespace(|| "key init"))?;
+                for block in blocks {
+                    state = chip.compress(&mut layouter.namespace(|| "key"), &state, *block)?;
+                }
+                chip.digest_words(&mut layouter.namespace(|| "key digest"), &state)?
+                    .to_vec()
+            }
+        };
+
+        let (ipad, opad) = chip.key_pads(&mut layouter.namespace(|| "key pads"), &key)?;
+
+        let state = chip.init_vector(&mut layouter.namespace(|| "inner init"))?;
+        let state = chip.compress_block(&mut layouter.namespace(|| "inner key"), &state, &ipad)?;
+
+        Ok(Hmac { chip, state, opad })
+    }
+
+    /// Update the inner state by consuming all message blocks
+    /// The input is assumed to be already padded as a message that follows the 64-byte
+    /// key block, i.e. with a length of 64 bytes more than its own
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[HmacChip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<(), Error> {
+        for block in data {
+            self.state = self.chip.compress(&mut layouter, &self.state, *block)?;
+        }
+
+        Ok(())
+    }
+
+    /// Computes the outer hash over the inner digest and returns the words of the HMAC
+    pub fn finalize(
+        self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<[HmacChip::Word; DIGEST_SIZE], Error> {
+        let inner_digest = self
+            .chip
+            .digest_words(&mut layouter.namespace(|| "inner digest"), &self.state)?;
+
+        let state = self
+            .chip
+            .init_vector(&mut layouter.namespace(|| "outer init"))?;
+        let state = self.chip.compress_block(
+            &mut layouter.namespace(|| "outer key"),
+            &state,
+            &self.opad,
+        )?;
+        let state = self.chip.compress_digest(
+            &mut layouter.namespace(|| "outer digest"),
+            &state,
+            inner_digest,
+        )?;
+
+        self.chip
+            .digest_words(&mut layouter.namespace(|| "finalize"), &state)
+    }
+
+    /// Util function to compute the HMAC of the data
+    pub fn mac(
+        chip: HmacChip,
+        mut layouter: impl Layouter<F>,
+        key: &HmacKey<HmacChip::BlockWord>,
+        data: &Vec<[HmacChip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<[HmacChip::Word; DIGEST_SIZE], Error> {
+        let mut hmac = Self::new(chip, layouter.namespace(|| "init"), key)?;
+        hmac.update(layouter.namespace(|| "update"), data)?;
+        hmac.finalize(layouter.namespace(|| "finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    use super::{Hmac, HmacKey};
+    use crate::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
+    use crate::native::{pad_message_bytes, pad_message_bytes_with_prefix};
+    use crate::table16::{
+        util::convert_byte_slice_to_blockword_slice, BlockWord, Table16Chip, Table16Config,
+    };
+
+    // (key, data, HMAC) test cases 1 to 7 of RFC 2286
+    fn rfc2286_test_cases() -> Vec<(Vec<u8>, Vec<u8>, &'static str)> {
+        vec![
+            (
+                vec![0x0b; 20],
+                b"Hi There".to_vec(),
+                "24cb4bd67d20fc1a5d2ed7732dcc39377f0a5668",
+            ),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?".to_vec(),
+                "dda6c0213a485a9e24f4742064a7f033b43c4069",
+            ),
+            (
+                vec![0xaa; 20],
+                vec![0xdd; 50],
+                "b0b105360de759960ab4f35298e116e295d8e7c1",
+            ),
+            (
+                (0x01..=0x19).collect(),
+                vec![0xcd; 50],
+                "d5ca862f4d21d5e610e18b4cf1beb97a4365ecf4",
+            ),
+            (
+                vec![0x0c; 20],
+                b"Test With Truncation".to_vec(),
+                "7619693978f91d90539ae786500ff3d8e0518e39",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                "6466ca07ac5eac29e1bd523e5ada7605b791fd8b",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data"
+                    .to_vec(),
+                "69ea60798d71616cce5fd0871e23754cd75d5a0a",
+            ),
+        ]
+    }
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn to_blocks(padded: Vec<[u8; BLOCK_SIZE_BYTES]>) -> Vec<[BlockWord; BLOCK_SIZE]> {
+        padded
+            .into_iter()
+            .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    struct HmacTestCircuit {
+        key: Vec<u8>,
+        data: Vec<u8>,
+        mac: Vec<u8>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for HmacTestCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            HmacTestCircuit {
+                key: vec![],
+                data: vec![],
+                mac: vec![],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let key = if self.key.len() > BLOCK_SIZE_BYTES {
+                HmacKey::Long(to_blocks(pad_message_bytes(self.key.clone())))
+            } else {
+                let mut key = self.key.clone();
+                key.resize((key.len() + 3) / 4 * 4, 0);
+                HmacKey::Short(
+                    key.chunks(4)
+                        .map(|bytes| {
+                            BlockWord(Value::known(u32::from_le_bytes(bytes.try_into().unwrap())))
+                        })
+                        .collect(),
+                )
+            };
+            let data = to_blocks(pad_message_bytes_with_prefix(
+                self.data.clone(),
+                BLOCK_SIZE_BYTES,
+            ));
+
+            let mac = Hmac::mac(chip, layouter.namespace(|| "hmac"), &key, &data)?;
+
+            for (idx, word) in mac.iter().enumerate() {
+                let expected =
+                    u32::from_le_bytes(self.mac[4 * idx..4 * idx + 4].try_into().unwrap());
+                word.value_u32().assert_if_known(|v| *v == expected);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Creates an HMAC with a short key of more words than a block holds
+    struct KeyLengthTestCircuit;
+
+    impl<F: FieldExt> Circuit<F> for KeyLengthTestCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            KeyLengthTestCircuit
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let key = HmacKey::Short(vec![BlockWord(Value::known(0)); BLOCK_SIZE + 1]);
+            let result = Hmac::new(chip, layouter.namespace(|| "hmac"), &key);
+            assert!(matches!(
+                result,
+                Err(crate::Error::KeyLength { words }) if words == BLOCK_SIZE + 1
+            ));
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hmac_key_length() {
+        MockProver::<Fr>::run(17, &KeyLengthTestCircuit, vec![]).unwrap();
+    }
+
+    #[test]
+    fn test_hmac_rfc2286() {
+        for (key, data, mac) in rfc2286_test_cases() {
+            let circuit = HmacTestCircuit {
+                key,
+                data,
+                mac: decode_hex(mac),
+            };
+
+            let prover = MockProver::<Fr>::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Rendering of circuit layouts, enabled by the `layout` feature.
+//!
+//! The functions take any [`Circuit`], so they can render the circuits of the other crates
+//! of the workspace as well.
+
+use std::{error::Error, path::Path};
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    dev::{circuit_dot_graph, CircuitLayout},
+    plonk::Circuit,
+};
+use plotters::{coord::Shift, prelude::*};
+
+/// Renders the layout of `circuit` with `2^k` rows into an image of the given size in
+/// pixels. The image is an SVG if `path` ends with `.svg`, and a bitmap in the format
+/// given by the extension of `path` otherwise.
+pub fn render_layout<F: FieldExt, C: Circuit<F>>(
+    k: u32,
+    circuit: &C,
+    title: &str,
+    path: impl AsRef<Path>,
+    size: (u32, u32),
+) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    if path.extension().map_or(false, |ext| ext == "svg") {
+        draw(
+            k,
+            circuit,
+            title,
+            SVGBackend::new(path, size).into_drawing_area(),
+        )
+    } else {
+        draw(
+            k,
+            circuit,
+            title,
+            BitMapBackend::new(path, size).into_drawing_area(),
+        )
+    }
+}
+
+/// Returns the constraint system of `circuit` as a graph in the DOT language.
+pub fn dot_graph<F: FieldExt, C: Circuit<F>>(circuit: &C) -> String {
+    circuit_dot_graph(circuit)
+}
+
+fn draw<F: FieldExt, C: Circuit<F>, DB: DrawingBackend>(
+    k: u32,
+    circuit: &C,
+    title: &str,
+    root: DrawingArea<DB, Shift>,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let root = root.titled(title, ("sans-serif", 30))?;
+    CircuitLayout::default().render(k, circuit, &root)?;
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{dot_graph, render_layout};
+    use crate::dev::{Ripemd160TestCircuit, INPUTS_OUTPUTS};
+
+    #[test]
+    fn test_render_layout() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let circuit: Ripemd160TestCircuit<Fr> = Ripemd160TestCircuit {
+            inputs,
+            outputs,
+            _marker: PhantomData,
+        };
+
+        let dir = std::env::temp_dir();
+        for file in ["ripemd160_layout.png", "ripemd160_layout.svg"] {
+            let path = dir.join(file);
+            render_layout(17, &circuit, "Ripemd160 hash Layout", &path, (1024, 512)).unwrap();
+            assert!(std::fs::metadata(&path).unwrap().len() > 0);
+            std::fs::remove_file(&path).unwrap();
+        }
+
+        assert!(dot_graph(&circuit).contains("digraph"));
+    }
+}
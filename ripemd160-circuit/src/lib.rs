@@ -7,13 +7,30 @@ use std::{fmt, marker::PhantomData};
 use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Chip, Layouter},
-    plonk::{ConstraintSystem, Error},
+    plonk::ConstraintSystem,
 };
 
 mod constants;
+#[cfg(test)]
+mod differential_tests;
+mod error;
+mod hash160;
+mod hmac;
+#[cfg(feature = "layout")]
+pub mod layout;
 mod native;
+mod ripemd;
 mod table16;
 
+pub use error::Error;
+pub use hash160::{Hash160, Hash160Instructions};
+pub use hmac::{Hmac, HmacInstructions, HmacKey};
+pub use ripemd::{Ripemd, RipemdDigest, RipemdInstructions, RipemdVariant};
+pub use table16::{
+    bytes_rlc, AssignedBits, BlockWord, Columns, Cost, CostReport, RegionCost, Ripemd160Table,
+    RlcDigest, SpreadTable, Table16Chip, Table16Config, SPREAD_TABLE_ROWS,
+};
+
 use constants::{BLOCK_SIZE, DIGEST_SIZE};
 
 /// The set of circuit instructions required to use the [`RIPEMD160`] gadget.
@@ -22,10 +39,28 @@ pub trait RIPEMD160Instructions<F: FieldExt>: Chip<F> {
     type State: Clone + fmt::Debug;
     /// Variable representing a 32-bit word of the input block to the RIPEMD-160 compression function
     type BlockWord: Copy + fmt::Debug + Default;
+    /// Variable representing an assigned 32-bit word
+    type Word: Clone + fmt::Debug;
 
     /// Places the RIPEMD-160 IV in the circuit, returning the initial state variable
     fn init_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
 
+    /// Places the given chaining state in the circuit, returning the initial state variable.
+    /// The words are copy constrained into the state, so they can be private witnesses or
+    /// the output of [`digest_words`](Self::digest_words) after an earlier block.
+    fn init_with_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[Self::Word; DIGEST_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    /// Assigns the given words, such as a private key or chaining state
+    fn witness_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[Self::BlockWord],
+    ) -> Result<Vec<Self::Word>, Error>;
+
     /// Starting from the given initialized state, processes a block of input and returns the final state
     fn compress(
         &self,
@@ -40,6 +75,34 @@ pub trait RIPEMD160Instructions<F: FieldExt>: Chip<F> {
         layouter: &mut impl Layouter<F>,
         state: &Self::State,
     ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error>;
+
+    /// Converts the given state into the assigned words of the message digest, which are
+    /// also the chaining state after the blocks processed so far
+    fn digest_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::Word; DIGEST_SIZE], Error>;
+
+    /// Hashes each of the given padded messages, returning the assigned words of their
+    /// digests. By default the messages are hashed one after another, while chips may lay
+    /// them out together and share one IV between them.
+    fn digest_batch(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        messages: &[Vec<[Self::BlockWord; BLOCK_SIZE]>],
+    ) -> Result<Vec<[Self::Word; DIGEST_SIZE]>, Error> {
+        messages
+            .iter()
+            .map(|blocks| {
+                let mut state = self.init_vector(layouter)?;
+                for block in blocks {
+                    state = self.compress(layouter, &state, *block)?;
+                }
+                self.digest_words(layouter, &state)
+            })
+            .collect()
+    }
 }
 
 /// The output of a RIPEMD-160 circuit
@@ -60,6 +123,25 @@ impl<F: FieldExt, Ripemd160Chip: RIPEMD160Instructions<F>> RIPEMD160<F, Ripemd16
         Ok(RIPEMD160 { chip, state })
     }
 
+    /// Create a new hasher instance starting from the given chaining state, such as the
+    /// state after the first blocks of a long message, which may be private
+    pub fn from_state(
+        chip: Ripemd160Chip,
+        mut layouter: impl Layouter<F>,
+        state: &[Ripemd160Chip::Word; DIGEST_SIZE],
+    ) -> Result<Self, Error> {
+        let state = chip.init_with_state(&mut layouter, state)?;
+        Ok(RIPEMD160 { chip, state })
+    }
+
+    /// Returns the assigned words of the chaining state after the blocks consumed so far
+    pub fn chaining_state(
+        &self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<[Ripemd160Chip::Word; DIGEST_SIZE], Error> {
+        self.chip.digest_words(&mut layouter, &self.state)
+    }
+
     /// Update the internal state by consuming all message blocks
     /// The input is assumed to be already padded to a multiple of 16 Blockwords
     pub fn update(
@@ -95,6 +177,16 @@ impl<F: FieldExt, Ripemd160Chip: RIPEMD160Instructions<F>> RIPEMD160<F, Ripemd16
         hasher.update(layouter.namespace(|| "update"), data)?;
         hasher.finalize(layouter.namespace(|| "finalize"))
     }
+
+    /// Util function to compute the hashes of many messages, each padded to a multiple of
+    /// 16 BlockWords, returning the assigned words of their digests
+    pub fn digest_batch(
+        chip: Ripemd160Chip,
+        mut layouter: impl Layouter<F>,
+        messages: &[Vec<[Ripemd160Chip::BlockWord; BLOCK_SIZE]>],
+    ) -> Result<Vec<[Ripemd160Chip::Word; DIGEST_SIZE]>, Error> {
+        chip.digest_batch(&mut layouter, messages)
+    }
 }
 
 #[cfg(any(feature = "test", test))]
@@ -111,7 +203,10 @@ pub mod dev {
     use super::*;
 
     use ethers_core::types::H160;
-    use halo2_proofs::{circuit::SimpleFloorPlanner, plonk::Circuit};
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        plonk::{Circuit, Error},
+    };
     use std::str::FromStr;
 
     lazy_static::lazy_static! {
@@ -137,6 +232,13 @@ pub mod dev {
             })
             .unzip()
         };
+
+        /// A 41 block message, "1234567890" repeated 256 times
+        pub static ref LONG_INPUT_OUTPUT: (Vec<Vec<u8>>, Vec<H160>) = (
+            vec!["1234567890".repeat(256).into_bytes()],
+            vec![H160::from_str("c02c3afc8a8d7f539f342edd9c660ee28034ccfe")
+                .expect("ripemd-160 hash is 20-bytes")],
+        );
     }
 
     #[derive(Default)]
@@ -166,22 +268,27 @@ pub mod dev {
             let chip = Table16Chip::construct(config.clone());
             Table16Chip::load(config, &mut layouter)?;
 
-            for (input, output) in self.inputs.iter().zip(self.outputs.iter()) {
-                // Preprocessing data
-                let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(input.clone())
-                    .into_iter()
-                    .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
-                    .collect();
-
-                // Hash the data
-                let digest =
-                    RIPEMD160::digest(chip.clone(), layouter.namespace(|| "digest"), &data)?;
-
-                // Assert check
-                let expected: [u32; DIGEST_SIZE] =
-                    convert_byte_slice_to_u32_slice(output.0.clone());
-                for (i, digest) in digest.0.iter().enumerate() {
-                    digest.0.assert_if_known(|v| *v == expected[i]);
+            // Preprocessing data
+            let messages: Vec<Vec<[BlockWord; BLOCK_SIZE]>> = self
+                .inputs
+                .iter()
+                .map(|input| {
+                    pad_message_bytes(input.clone())
+                        .into_iter()
+                        .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                        .collect::<Result<_, _>>()
+                })
+                .collect::<Result<_, _>>()?;
+
+            // Hash all messages together
+            let digests =
+                RIPEMD160::digest_batch(chip, layouter.namespace(|| "digest"), &messages)?;
+
+            // Assert check
+            for (digest, output) in digests.iter().zip(self.outputs.iter()) {
+                let expected: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(output.0)?;
+                for (word, expected) in digest.iter().zip(expected) {
+                    word.value_u32().assert_if_known(|v| *v == expected);
                 }
             }
 
@@ -192,10 +299,26 @@ pub mod dev {
 
 #[cfg(test)]
 mod tests {
-    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use bitwise_gadgets::{BitwiseChip, BitwiseConfig};
+    use ethers_core::types::H160;
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+    };
     use std::marker::PhantomData;
 
-    use crate::dev::{Ripemd160TestCircuit, INPUTS_OUTPUTS};
+    use super::{RIPEMD160Instructions, RIPEMD160};
+    use crate::{
+        constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE, INITIAL_VALUES},
+        dev::{Ripemd160TestCircuit, INPUTS_OUTPUTS, LONG_INPUT_OUTPUT},
+        native::{get_compress_state, pad_message_bytes, State},
+        table16::{
+            util::{convert_byte_slice_to_blockword_slice, convert_byte_slice_to_u32_slice},
+            BlockWord, SpreadTable, Table16Chip, Table16Config,
+        },
+    };
 
     #[test]
     fn test_ripemd160_circuit() {
@@ -210,18 +333,213 @@ mod tests {
         let k = 17;
         let prover = MockProver::run(k, &circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_ripemd160_circuit_long_input() {
+        let (inputs, outputs) = LONG_INPUT_OUTPUT.clone();
+
+        let circuit: Ripemd160TestCircuit<Fr> = Ripemd160TestCircuit {
+            inputs,
+            outputs,
+            _marker: PhantomData,
+        };
+
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
 
-        // Add circuit layout diagram
-        use plotters::prelude::*;
+    /// Hashes the first `split` blocks of a message, then hashes the rest starting from
+    /// the chaining state as a later proof would, with the state as a private witness
+    struct MidstateTestCircuit {
+        input: Vec<u8>,
+        output: H160,
+        split: usize,
+    }
 
-        let root = BitMapBackend::new("ripemd160.png", (1024, 512)).into_drawing_area();
-        root.fill(&WHITE).unwrap();
-        let root = root
-            .titled("Ripemd160 hash Layout", ("sans-serif", 30))
-            .unwrap();
+    impl<F: FieldExt> Circuit<F> for MidstateTestCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            MidstateTestCircuit {
+                input: vec![],
+                output: H160::zero(),
+                split: self.split,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let padded = pad_message_bytes(self.input.clone());
+            let data: Vec<[BlockWord; BLOCK_SIZE]> = padded
+                .iter()
+                .map(|block| {
+                    convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(*block)
+                })
+                .collect::<Result<_, _>>()?;
+            let (prefix, suffix) = data.split_at(self.split);
+
+            let mut hasher = RIPEMD160::new(chip.clone(), layouter.namespace(|| "prefix init"))?;
+            hasher.update(layouter.namespace(|| "prefix"), &prefix.to_vec())?;
+            let midstate = hasher.chaining_state(layouter.namespace(|| "midstate"))?;
+
+            let expected_midstate: [u32; DIGEST_SIZE] = padded[..self.split]
+                .iter()
+                .fold(State::from(INITIAL_VALUES), |state, block| {
+                    get_compress_state(state, (*block).into())
+                })
+                .into();
+            for (word, expected) in midstate.iter().zip(expected_midstate) {
+                word.value_u32().assert_if_known(|v| *v == expected);
+            }
+
+            let midstate = chip.witness_words(
+                &mut layouter.namespace(|| "witness midstate"),
+                &expected_midstate.map(|word| BlockWord(Value::known(word))),
+            )?;
+            let mut hasher = RIPEMD160::from_state(
+                chip,
+                layouter.namespace(|| "suffix init"),
+                &midstate.try_into().unwrap(),
+            )?;
+            hasher.update(layouter.namespace(|| "suffix"), &suffix.to_vec())?;
+            let digest = hasher.finalize(layouter.namespace(|| "finalize"))?;
+
+            let expected: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(self.output.0)?;
+            for (word, expected) in digest.0.iter().zip(expected) {
+                word.0.assert_if_known(|v| *v == expected);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ripemd160_circuit_midstate() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+
+        // A two block message, split after the first block
+        let circuit = MidstateTestCircuit {
+            input: inputs[2].clone(),
+            output: outputs[2],
+            split: 1,
+        };
+        assert_eq!(pad_message_bytes(circuit.input.clone()).len(), 2);
+
+        let prover = MockProver::<Fr>::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    /// Hashes messages next to a bitwise chip, which XORs the first two words of each
+    /// digest, with the two chips sharing one spread table
+    struct SharedTableTestCircuit {
+        inputs: Vec<Vec<u8>>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for SharedTableTestCircuit {
+        type Config = (
+            Table16Config<F>,
+            BitwiseConfig<F>,
+            SpreadTable,
+            Column<Instance>,
+        );
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            SharedTableTestCircuit {
+                inputs: self
+                    .inputs
+                    .iter()
+                    .map(|input| vec![0; input.len()])
+                    .collect(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+
+            let table = SpreadTable::configure(meta);
+            (
+                Table16Chip::configure_with_spread_table(meta, &table, None),
+                BitwiseChip::configure_with_spread_table(meta, &table),
+                table,
+                instance,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            (ripemd_config, bitwise_config, table, instance): Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            // The circuit loads the table it owns, and the loads of the chips do nothing
+            table.load(&mut layouter)?;
+            Table16Chip::load(ripemd_config.clone(), &mut layouter)?;
+            BitwiseChip::load(bitwise_config.clone(), &mut layouter)?;
+            let chip = Table16Chip::construct(ripemd_config);
+            let bitwise = BitwiseChip::construct(bitwise_config);
+
+            let messages: Vec<Vec<[BlockWord; BLOCK_SIZE]>> = self
+                .inputs
+                .iter()
+                .map(|input| {
+                    pad_message_bytes(input.clone())
+                        .into_iter()
+                        .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                        .collect::<Result<_, _>>()
+                })
+                .collect::<Result<_, _>>()?;
+            let digests =
+                RIPEMD160::digest_batch(chip, layouter.namespace(|| "digest"), &messages)?;
+
+            for (row, digest) in digests.iter().enumerate() {
+                let mut words = vec![];
+                for word in &digest[..2] {
+                    let copy = bitwise.assign_word(&mut layouter, word.value_u32())?;
+                    layouter.assign_region(
+                        || "copy digest word",
+                        |mut region| region.constrain_equal(word.cell(), copy.word.cell()),
+                    )?;
+                    words.push(copy);
+                }
+                let xor = bitwise.xor(&mut layouter, &words[0], &words[1])?;
+                layouter.constrain_instance(xor.word.cell(), instance, row)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_shared_spread_table() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let xors: Vec<Fr> = outputs
+            .iter()
+            .map(|output| {
+                let words: [u32; DIGEST_SIZE] = convert_byte_slice_to_u32_slice(output.0).unwrap();
+                Fr::from((words[0] ^ words[1]) as u64)
+            })
+            .collect();
+        let circuit = SharedTableTestCircuit { inputs };
+
+        let prover = MockProver::run(17, &circuit, vec![xors.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
 
-        halo2_proofs::dev::CircuitLayout::default()
-            .render(k, &circuit, &root)
-            .unwrap();
+        let mut wrong = xors;
+        wrong[1] += Fr::one();
+        let prover = MockProver::run(17, &circuit, vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
     }
 }
@@ -3,6 +3,7 @@ use crate::constants::{
     MSG_SEL_IDX_RIGHT, ROL_AMOUNT_LEFT, ROL_AMOUNT_RIGHT, ROUNDS, ROUND_CONSTANTS_LEFT,
     ROUND_CONSTANTS_RIGHT, ROUND_PHASE_SIZE,
 };
+use crate::ripemd::RipemdVariant;
 use std::convert::TryInto;
 
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
@@ -73,11 +74,28 @@ impl From<[u8; BLOCK_SIZE_BYTES]> for MessageBlock {
     }
 }
 
+impl From<[u32; BLOCK_SIZE]> for MessageBlock {
+    fn from(words: [u32; BLOCK_SIZE]) -> Self {
+        MessageBlock(words)
+    }
+}
+
 const ROUND_FUNC_LEFT: [fn(u32, u32, u32) -> u32; 5] = [f1, f2, f3, f4, f5];
 const ROUND_FUNC_RIGHT: [fn(u32, u32, u32) -> u32; 5] = [f5, f4, f3, f2, f1];
 
 pub fn pad_message_bytes(msg_bytes: Vec<u8>) -> Vec<[u8; BLOCK_SIZE_BYTES]> {
+    pad_message_bytes_with_prefix(msg_bytes, 0)
+}
+
+/// Pads a message that follows `prefix_len` bytes of already processed blocks, such as
+/// the key block of an HMAC.
+pub fn pad_message_bytes_with_prefix(
+    msg_bytes: Vec<u8>,
+    prefix_len: usize,
+) -> Vec<[u8; BLOCK_SIZE_BYTES]> {
     const PAD_BYTE: u8 = 0b1000_0000;
+    assert!(prefix_len % BLOCK_SIZE_BYTES == 0);
+
     let mut padded_msg: Vec<u8> = vec![];
     padded_msg.extend(msg_bytes.clone());
     padded_msg.push(PAD_BYTE);
@@ -89,7 +107,7 @@ pub fn pad_message_bytes(msg_bytes: Vec<u8>) -> Vec<[u8; BLOCK_SIZE_BYTES]> {
         padded_msg.extend(vec![0_u8; gap - 8]);
     }
 
-    let msg_len_in_bits = (msg_bytes.len() << 3) as u64;
+    let msg_len_in_bits = ((prefix_len + msg_bytes.len()) << 3) as u64;
     padded_msg.extend(msg_len_in_bits.to_le_bytes());
     assert!(padded_msg.len() % BLOCK_SIZE_BYTES == 0);
 
@@ -175,14 +193,243 @@ pub fn get_compress_state(s: State, msg_block: MessageBlock) -> State {
     chain_state
 }
 
+/// A step of either line of any RIPEMD variant. The four-word lines of RIPEMD-128 and
+/// RIPEMD-256 are kept in a `State` with E at zero.
+pub fn variant_step(
+    variant: RipemdVariant,
+    round_idx: usize,
+    left: bool,
+    s: State,
+    msg_block: MessageBlock,
+) -> State {
+    if variant.line_words() == DIGEST_SIZE {
+        return if left {
+            left_step(round_idx, s, msg_block)
+        } else {
+            right_step(round_idx, s, msg_block)
+        };
+    }
+
+    let phase_idx = round_idx / ROUND_PHASE_SIZE;
+    let (f_idx, m, k, shift) = if left {
+        (
+            variant.round_function_left(phase_idx),
+            msg_block.get_word(MSG_SEL_IDX_LEFT[round_idx]),
+            variant.round_constant_left(phase_idx),
+            ROL_AMOUNT_LEFT[round_idx],
+        )
+    } else {
+        (
+            variant.round_function_right(phase_idx),
+            msg_block.get_word(MSG_SEL_IDX_RIGHT[round_idx]),
+            variant.round_constant_right(phase_idx),
+            ROL_AMOUNT_RIGHT[round_idx],
+        )
+    };
+    let f = ROUND_FUNC_LEFT[f_idx - 1];
+    let t = rol(
+        s.a.wrapping_add(f(s.b, s.c, s.d))
+            .wrapping_add(m)
+            .wrapping_add(k),
+        shift,
+    );
+
+    State {
+        a: s.d,
+        b: t,
+        c: s.b,
+        d: s.c,
+        e: s.e,
+    }
+}
+
+/// Compresses a block for any RIPEMD variant, given the chaining state of
+/// `variant.digest_words()` words
+pub fn get_compress_state_variant(
+    variant: RipemdVariant,
+    state: &[u32],
+    msg_block: MessageBlock,
+) -> Vec<u32> {
+    assert_eq!(state.len(), variant.digest_words());
+
+    let n = variant.line_words();
+    let line_state = |words: &[u32]| {
+        let mut s = [0; DIGEST_SIZE];
+        s[..n].copy_from_slice(words);
+        State::from(s)
+    };
+    let init_left = line_state(&state[..n]);
+    let init_right = if variant.combines_lines() {
+        init_left
+    } else {
+        line_state(&state[n..])
+    };
+
+    let mut left = init_left;
+    let mut right = init_right;
+    for j in 0..variant.rounds() {
+        left = variant_step(variant, j, true, left, msg_block);
+        right = variant_step(variant, j, false, right, msg_block);
+
+        if j % ROUND_PHASE_SIZE == ROUND_PHASE_SIZE - 1 {
+            if let Some(register) = variant.exchanged_register(j / ROUND_PHASE_SIZE) {
+                let mut l: [u32; DIGEST_SIZE] = left.into();
+                let mut r: [u32; DIGEST_SIZE] = right.into();
+                std::mem::swap(&mut l[register], &mut r[register]);
+                left = l.into();
+                right = r.into();
+            }
+        }
+    }
+
+    match variant {
+        RipemdVariant::Ripemd160 => {
+            <[u32; DIGEST_SIZE]>::from(combine_left_right_states(init_left, left, right)).to_vec()
+        }
+        RipemdVariant::Ripemd128 => {
+            let (h, l, r) = (init_left, left, right);
+            vec![
+                h.b.wrapping_add(l.c).wrapping_add(r.d),
+                h.c.wrapping_add(l.d).wrapping_add(r.a),
+                h.d.wrapping_add(l.a).wrapping_add(r.b),
+                h.a.wrapping_add(l.b).wrapping_add(r.c),
+            ]
+        }
+        RipemdVariant::Ripemd256 | RipemdVariant::Ripemd320 => {
+            let l: [u32; DIGEST_SIZE] = left.into();
+            let r: [u32; DIGEST_SIZE] = right.into();
+            state
+                .iter()
+                .zip(l[..n].iter().chain(r[..n].iter()))
+                .map(|(h, word)| h.wrapping_add(*word))
+                .collect()
+        }
+    }
+}
+
+/// Hashes a message with any RIPEMD variant
+pub fn hash_variant(variant: RipemdVariant, msg: Vec<u8>) -> Vec<u8> {
+    let mut state = variant.initial_values();
+    for block in pad_message_bytes(msg) {
+        state = get_compress_state_variant(variant, &state, block.into());
+    }
+    state.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
 pub fn hash(msg: Vec<u8>) -> [u8; DIGEST_SIZE_BYTES] {
-    let msg_blocks: Vec<[u8; BLOCK_SIZE_BYTES]> = pad_message_bytes(msg);
-    assert!(msg_blocks.len() > 0);
-    let mut state = get_compress_state(INITIAL_VALUES.into(), msg_blocks[0].into());
-    for block in &msg_blocks[1..] {
-        state = get_compress_state(state, (*block).into());
+    let mut hasher = Ripemd160::new();
+    hasher.update(&msg);
+    hasher.finalize()
+}
+
+/// The states of both lines after each of the steps of a compression
+#[derive(Debug, Clone)]
+pub struct CompressionTrace {
+    pub left: Vec<State>,
+    pub right: Vec<State>,
+}
+
+/// Same as [`get_compress_state`], also returning the states after each step
+pub fn get_compress_trace(s: State, msg_block: MessageBlock) -> (State, CompressionTrace) {
+    let mut trace = CompressionTrace {
+        left: Vec::with_capacity(ROUNDS),
+        right: Vec::with_capacity(ROUNDS),
+    };
+    let mut left_state = s;
+    let mut right_state = s;
+    for j in 0..ROUNDS {
+        left_state = left_step(j, left_state, msg_block);
+        right_state = right_step(j, right_state, msg_block);
+        trace.left.push(left_state);
+        trace.right.push(right_state);
+    }
+    (combine_left_right_states(s, left_state, right_state), trace)
+}
+
+/// A streaming RIPEMD-160 hasher, which buffers partial blocks between updates
+#[derive(Debug, Clone)]
+pub struct Ripemd160 {
+    state: State,
+    buffer: Vec<u8>,
+    processed_len: usize,
+    traces: Option<Vec<CompressionTrace>>,
+}
+
+impl Default for Ripemd160 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ripemd160 {
+    pub fn new() -> Self {
+        Ripemd160 {
+            state: INITIAL_VALUES.into(),
+            buffer: Vec::with_capacity(BLOCK_SIZE_BYTES),
+            processed_len: 0,
+            traces: None,
+        }
+    }
+
+    /// Creates a hasher that records the trace of each compressed block, for debugging
+    /// the witnesses of a circuit
+    pub fn with_traces() -> Self {
+        Ripemd160 {
+            traces: Some(vec![]),
+            ..Self::new()
+        }
+    }
+
+    /// Absorbs the given bytes, compressing every block that is complete
+    pub fn update(&mut self, data: &[u8]) {
+        let mut data = data;
+        while !data.is_empty() {
+            let take = (BLOCK_SIZE_BYTES - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+
+            if self.buffer.len() == BLOCK_SIZE_BYTES {
+                let block: [u8; BLOCK_SIZE_BYTES] =
+                    self.buffer.as_slice().try_into().expect("Incorrect length");
+                self.compress(block);
+                self.buffer.clear();
+                self.processed_len += BLOCK_SIZE_BYTES;
+            }
+        }
+    }
+
+    /// Pads the buffered bytes and returns the digest
+    pub fn finalize(self) -> [u8; DIGEST_SIZE_BYTES] {
+        self.finalize_with_traces().0
+    }
+
+    /// Pads the buffered bytes and returns the digest, with the traces of all the
+    /// compressed blocks, including the padded last ones, if the hasher records them
+    pub fn finalize_with_traces(
+        mut self,
+    ) -> ([u8; DIGEST_SIZE_BYTES], Option<Vec<CompressionTrace>>) {
+        let buffer = std::mem::take(&mut self.buffer);
+        for block in pad_message_bytes_with_prefix(buffer, self.processed_len) {
+            self.compress(block);
+        }
+        (self.state.into(), self.traces)
+    }
+
+    /// The traces of the blocks compressed so far, if the hasher records them
+    pub fn traces(&self) -> Option<&[CompressionTrace]> {
+        self.traces.as_deref()
+    }
+
+    fn compress(&mut self, block: [u8; BLOCK_SIZE_BYTES]) {
+        match &mut self.traces {
+            Some(traces) => {
+                let (state, trace) = get_compress_trace(self.state, block.into());
+                self.state = state;
+                traces.push(trace);
+            }
+            None => self.state = get_compress_state(self.state, block.into()),
+        }
     }
-    state.into()
 }
 
 // Helper functions
@@ -206,7 +453,106 @@ pub fn f5(x: u32, y: u32, z: u32) -> u32 {
     x ^ (y | !z)
 }
 
+/// Rotates a word to the left, by any amount modulo 32
 pub fn rol(word: u32, amount: u8) -> u32 {
-    assert!(amount < 16);
-    (word << amount) | (word >> (32 - amount))
+    word.rotate_left(amount as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        combine_left_right_states, get_compress_state, hash, pad_message_bytes, rol, Ripemd160,
+        State,
+    };
+    use crate::constants::{BLOCK_SIZE_BYTES, INITIAL_VALUES, ROUNDS};
+
+    #[test]
+    fn test_rol() {
+        assert_eq!(rol(0x8000_0001, 0), 0x8000_0001);
+        assert_eq!(rol(0x8000_0001, 1), 0x0000_0003);
+        assert_eq!(rol(0x1234_5678, 16), 0x5678_1234);
+        assert_eq!(rol(0x8000_0001, 31), 0xC000_0000);
+        assert_eq!(rol(0x1234_5678, 32), 0x1234_5678);
+    }
+
+    #[test]
+    fn test_streaming_matches_one_shot() {
+        let msg: Vec<u8> = (0..200u32).map(|i| (i * 7 + 3) as u8).collect();
+        for len in [0, 1, 55, 56, 63, 64, 65, 119, 120, 128, 200] {
+            let expected = hash(msg[..len].to_vec());
+            for chunk in [1, 3, 63, 64, 65, 200] {
+                let mut hasher = Ripemd160::new();
+                for part in msg[..len].chunks(chunk) {
+                    hasher.update(part);
+                }
+                assert_eq!(hasher.finalize(), expected, "len {} chunk {}", len, chunk);
+            }
+        }
+    }
+
+    #[test]
+    fn test_reference_vectors() {
+        let mut hasher = Ripemd160::new();
+        for _ in 0..8 {
+            hasher.update(b"1234567890");
+        }
+        assert_eq!(
+            hasher.finalize(),
+            [
+                0x9b, 0x75, 0x2e, 0x45, 0x57, 0x3d, 0x4b, 0x39, 0xf4, 0xdb, 0xd3, 0x32, 0x3c, 0xab,
+                0x82, 0xbf, 0x63, 0x32, 0x6b, 0xfb
+            ]
+        );
+    }
+
+    #[test]
+    fn test_traces() {
+        let msg = vec![0x61; 100];
+        let mut hasher = Ripemd160::with_traces();
+        hasher.update(&msg);
+        let traces = hasher.traces().unwrap();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].left.len(), ROUNDS);
+        assert_eq!(traces[0].right.len(), ROUNDS);
+
+        let block: [u8; BLOCK_SIZE_BYTES] = msg[..BLOCK_SIZE_BYTES].try_into().unwrap();
+        let mut left_state: State = INITIAL_VALUES.into();
+        let mut right_state = left_state;
+        for j in 0..ROUNDS {
+            left_state = super::left_step(j, left_state, block.into());
+            right_state = super::right_step(j, right_state, block.into());
+            assert_eq!(traces[0].left[j], left_state);
+            assert_eq!(traces[0].right[j], right_state);
+        }
+
+        let mut state: State = INITIAL_VALUES.into();
+        for block in pad_message_bytes(msg) {
+            state = get_compress_state(state, block.into());
+        }
+        let digest: [u8; 20] = state.into();
+        assert_eq!(hasher.finalize(), digest);
+    }
+
+    #[test]
+    fn test_traces_of_short_message() {
+        let mut hasher = Ripemd160::with_traces();
+        hasher.update(b"abc");
+        assert!(hasher.traces().unwrap().is_empty());
+
+        let (digest, traces) = hasher.finalize_with_traces();
+        assert_eq!(digest, hash(b"abc".to_vec()));
+        let traces = traces.unwrap();
+        assert_eq!(traces.len(), 1);
+
+        let iv: State = INITIAL_VALUES.into();
+        let state =
+            combine_left_right_states(iv, traces[0].left[ROUNDS - 1], traces[0].right[ROUNDS - 1]);
+        let expected: [u8; 20] = state.into();
+        assert_eq!(expected, digest);
+
+        // A hasher without traces returns none
+        let mut hasher = Ripemd160::new();
+        hasher.update(b"abc");
+        assert!(hasher.finalize_with_traces().1.is_none());
+    }
 }
@@ -0,0 +1,339 @@
+//! The RIPEMD family of hash functions: RIPEMD-128, RIPEMD-160, RIPEMD-256 and RIPEMD-320.
+//!
+//! All of them run a left and a right line of steps over each message block. RIPEMD-128 and
+//! RIPEMD-256 use four phases of 16 steps on four-word lines, RIPEMD-160 and RIPEMD-320 use
+//! five phases on five-word lines. RIPEMD-128 and RIPEMD-160 combine both lines into one
+//! chaining state at the end of each block, while RIPEMD-256 and RIPEMD-320 keep a chaining
+//! state for each line and exchange one register between the lines after each phase.
+
+use std::fmt;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Chip, Layouter},
+};
+
+use crate::constants::{
+    BLOCK_SIZE, INITIAL_VALUES, INITIAL_VALUES_RIGHT, ROUND_CONSTANTS_LEFT, ROUND_CONSTANTS_RIGHT,
+    ROUND_CONSTANTS_RIGHT_4_PHASES, ROUND_PHASE_SIZE,
+};
+use crate::Error;
+
+/// A member of the RIPEMD family
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RipemdVariant {
+    Ripemd128,
+    Ripemd160,
+    Ripemd256,
+    Ripemd320,
+}
+
+impl RipemdVariant {
+    /// Number of 32-bit words in the state of each line
+    pub fn line_words(&self) -> usize {
+        match self {
+            RipemdVariant::Ripemd128 | RipemdVariant::Ripemd256 => 4,
+            RipemdVariant::Ripemd160 | RipemdVariant::Ripemd320 => 5,
+        }
+    }
+
+    /// Number of phases of 16 steps in each line
+    pub fn phases(&self) -> usize {
+        match self {
+            RipemdVariant::Ripemd128 | RipemdVariant::Ripemd256 => 4,
+            RipemdVariant::Ripemd160 | RipemdVariant::Ripemd320 => 5,
+        }
+    }
+
+    /// Number of steps in each line
+    pub fn rounds(&self) -> usize {
+        self.phases() * ROUND_PHASE_SIZE
+    }
+
+    /// Whether both lines are combined into one chaining state at the end of each block
+    pub fn combines_lines(&self) -> bool {
+        matches!(self, RipemdVariant::Ripemd128 | RipemdVariant::Ripemd160)
+    }
+
+    /// Number of 32-bit words in the digest, which is also the chaining state
+    pub fn digest_words(&self) -> usize {
+        if self.combines_lines() {
+            self.line_words()
+        } else {
+            2 * self.line_words()
+        }
+    }
+
+    /// The initial chaining state
+    pub fn initial_values(&self) -> Vec<u32> {
+        let n = self.line_words();
+        let mut iv = INITIAL_VALUES[..n].to_vec();
+        if !self.combines_lines() {
+            iv.extend_from_slice(&INITIAL_VALUES_RIGHT[..n]);
+        }
+        iv
+    }
+
+    /// The round constant of the given phase of the left line
+    pub fn round_constant_left(&self, phase_idx: usize) -> u32 {
+        ROUND_CONSTANTS_LEFT[phase_idx]
+    }
+
+    /// The round constant of the given phase of the right line
+    pub fn round_constant_right(&self, phase_idx: usize) -> u32 {
+        match self.phases() {
+            4 => ROUND_CONSTANTS_RIGHT_4_PHASES[phase_idx],
+            _ => ROUND_CONSTANTS_RIGHT[phase_idx],
+        }
+    }
+
+    /// Index, from 1 to 5, of the boolean function f used in the given phase of the left line
+    pub fn round_function_left(&self, phase_idx: usize) -> usize {
+        phase_idx + 1
+    }
+
+    /// Index, from 1 to 5, of the boolean function f used in the given phase of the right
+    /// line, which uses them in reverse order
+    pub fn round_function_right(&self, phase_idx: usize) -> usize {
+        self.phases() - phase_idx
+    }
+
+    /// The register, with A = 0, that the lines exchange after the given phase
+    pub fn exchanged_register(&self, phase_idx: usize) -> Option<usize> {
+        match self {
+            RipemdVariant::Ripemd128 | RipemdVariant::Ripemd160 => None,
+            // A, B, C, D
+            RipemdVariant::Ripemd256 => Some(phase_idx),
+            // B, D, A, C, E
+            RipemdVariant::Ripemd320 => Some([1, 3, 0, 2, 4][phase_idx]),
+        }
+    }
+}
+
+/// The set of circuit instructions required to use the [`Ripemd`] gadget.
+pub trait RipemdInstructions<F: FieldExt>: Chip<F> {
+    /// Variable representing the chaining state of the variant.
+    type State: Clone + fmt::Debug;
+    /// Variable representing a 32-bit word of the input block to the compression function
+    type BlockWord: Copy + fmt::Debug + Default;
+
+    /// The variant computed by this chip
+    fn variant(&self) -> RipemdVariant;
+
+    /// Places the IV of the variant in the circuit, returning the initial state variable
+    fn init_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error>;
+
+    /// Starting from the given initialized state, processes a block of input and returns the final state
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error>;
+
+    /// Converts the given state into a message digest of `variant().digest_words()` words
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<Vec<Self::BlockWord>, Error>;
+}
+
+/// The output of a RIPEMD circuit, with `variant.digest_words()` words
+#[derive(Debug)]
+pub struct RipemdDigest<BlockWord>(Vec<BlockWord>);
+
+/// A gadget that constrains a hash of any variant of the RIPEMD family.
+#[derive(Debug)]
+pub struct Ripemd<F: FieldExt, CS: RipemdInstructions<F>> {
+    chip: CS,
+    state: CS::State,
+}
+
+impl<F: FieldExt, RipemdChip: RipemdInstructions<F>> Ripemd<F, RipemdChip> {
+    /// Create a new hasher instance
+    pub fn new(chip: RipemdChip, mut layouter: impl Layouter<F>) -> Result<Self, Error> {
+        let state = chip.init_vector(&mut layouter)?;
+        Ok(Ripemd { chip, state })
+    }
+
+    /// Update the internal state by consuming all message blocks
+    /// The input is assumed to be already padded to a multiple of 16 Blockwords
+    pub fn update(
+        &mut self,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[RipemdChip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<(), Error> {
+        for block in data {
+            self.state = self.chip.compress(&mut layouter, &self.state, *block)?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve result and consume hasher instance.
+    pub fn finalize(
+        self,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<RipemdDigest<RipemdChip::BlockWord>, Error> {
+        self.chip
+            .digest(&mut layouter, &self.state)
+            .map(RipemdDigest)
+    }
+
+    /// Util function to compute hash of the data
+    pub fn digest(
+        chip: RipemdChip,
+        mut layouter: impl Layouter<F>,
+        data: &Vec<[RipemdChip::BlockWord; BLOCK_SIZE]>,
+    ) -> Result<RipemdDigest<RipemdChip::BlockWord>, Error> {
+        let mut hasher = Self::new(chip, layouter.namespace(|| "init"))?;
+        hasher.update(layouter.namespace(|| "update"), data)?;
+        hasher.finalize(layouter.namespace(|| "finalize"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    use super::{Ripemd, RipemdVariant};
+    use crate::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
+    use crate::native::{hash_variant, pad_message_bytes};
+    use crate::table16::{
+        util::convert_byte_slice_to_blockword_slice, BlockWord, Table16Chip, Table16Config,
+    };
+
+    const MSG_56: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+    // Digests of "", "abc", "abcdefghijklmnopqrstuvwxyz" and MSG_56
+    fn reference_vectors(variant: RipemdVariant) -> [&'static str; 4] {
+        match variant {
+            RipemdVariant::Ripemd128 => [
+                "cdf26213a150dc3ecb610f18f6b38b46",
+                "c14a12199c66e4ba84636b0f69144c77",
+                "fd2aa607f71dc8f510714922b371834e",
+                "a1aa0689d0fafa2ddc22e88b49133a06",
+            ],
+            RipemdVariant::Ripemd160 => [
+                "9c1185a5c5e9fc54612808977ee8f548b2258d31",
+                "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc",
+                "f71c27109c692c1b56bbdceb5b9d2865b3708dbc",
+                "12a053384a9c0c88e405a06c27dcf49ada62eb2b",
+            ],
+            RipemdVariant::Ripemd256 => [
+                "02ba4c4e5f8ecd1877fc52d64d30e37a2d9774fb1e5d026380ae0168e3c5522d",
+                "afbd6e228b9d8cbbcef5ca2d03e6dba10ac0bc7dcbe4680e1e42d2e975459b65",
+                "649d3034751ea216776bf9a18acc81bc7896118a5197968782dd1fd97d8d5133",
+                "3843045583aac6c8c8d9128573e7a9809afb2a0f34ccc36ea9e72f16f6368e3f",
+            ],
+            RipemdVariant::Ripemd320 => [
+                "22d65d5661536cdc75c1fdf5c6de7b41b9f27325ebc61e8557177d705a0ec880151c3a32a00899b8",
+                "de4c01b3054f8930a79d09ae738e92301e5a17085beffdc1b8d116713e74f82fa942d64cdbc4682d",
+                "cabdb1810b92470a2093aa6bce05952c28348cf43ff60841975166bb40ed234004b8824463e6b009",
+                "d034a7950cf722021ba4b84df769a5de2060e259df4c9bb4a4268c0e935bbc7470a969c9d072a1ac",
+            ],
+        }
+    }
+
+    const VARIANTS: [RipemdVariant; 4] = [
+        RipemdVariant::Ripemd128,
+        RipemdVariant::Ripemd160,
+        RipemdVariant::Ripemd256,
+        RipemdVariant::Ripemd320,
+    ];
+
+    fn messages() -> [Vec<u8>; 4] {
+        [
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abcdefghijklmnopqrstuvwxyz".to_vec(),
+            MSG_56.to_vec(),
+        ]
+    }
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&hex[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_native_variants() {
+        for variant in VARIANTS {
+            for (msg, digest) in messages().into_iter().zip(reference_vectors(variant)) {
+                assert_eq!(hash_variant(variant, msg), decode_hex(digest));
+            }
+        }
+    }
+
+    struct RipemdTestCircuit {
+        variant: RipemdVariant,
+        input: Vec<u8>,
+        digest: Vec<u8>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for RipemdTestCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            RipemdTestCircuit {
+                variant: self.variant,
+                input: vec![],
+                digest: vec![],
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let chip = Table16Chip::construct_variant(config.clone(), self.variant);
+            Table16Chip::load(config, &mut layouter)?;
+
+            let input: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.input.clone())
+                .into_iter()
+                .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                .collect::<Result<_, _>>()?;
+
+            let digest = Ripemd::digest(chip, layouter.namespace(|| "ripemd"), &input)?;
+
+            assert_eq!(digest.0.len(), self.variant.digest_words());
+            for (idx, word) in digest.0.iter().enumerate() {
+                let expected =
+                    u32::from_le_bytes(self.digest[4 * idx..4 * idx + 4].try_into().unwrap());
+                word.0.assert_if_known(|v| *v == expected);
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ripemd_variants_circuit() {
+        for variant in VARIANTS {
+            for (input, digest) in messages().into_iter().zip(reference_vectors(variant)) {
+                let circuit = RipemdTestCircuit {
+                    variant,
+                    input,
+                    digest: decode_hex(digest),
+                };
+
+                let prover = MockProver::<Fr>::run(17, &circuit, vec![]).unwrap();
+                assert_eq!(prover.verify(), Ok(()));
+            }
+        }
+    }
+}
@@ -5,21 +5,39 @@ Based on code from https://github.com/privacy-scaling-explorations/halo2/blob/8c
 use halo2_proofs::{
     circuit::{AssignedCell, Chip, Layouter, Region, Value},
     halo2curves::FieldExt,
-    plonk::{Advice, Any, Assigned, Column, ConstraintSystem, Error},
+    plonk::{Advice, Challenge, Column, ConstraintSystem},
 };
 
 mod compression;
+mod cost;
 mod gates;
 mod message_schedule;
-mod spread_table;
+mod precompile;
+mod rlc;
+#[cfg(test)]
+mod tamper;
 pub(crate) mod util;
 
 use compression::*;
+pub use cost::{Columns, Cost, CostReport, RegionCost, SPREAD_TABLE_ROWS};
 use message_schedule::*;
-use spread_table::*;
-use util::*;
+pub use precompile::Ripemd160Table;
+pub use rlc::bytes_rlc;
+use rlc::RlcConfig;
 
-use crate::{constants::INITIAL_VALUES, RIPEMD160Instructions};
+use bitwise_gadgets::{
+    util::i2lebsp, SpreadInputs, SpreadTableChip, SpreadTableConfig, SpreadVar, SpreadWord,
+};
+pub use bitwise_gadgets::{AssignedBits, SpreadTable};
+
+use crate::{
+    constants::{
+        BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE, DIGEST_SIZE_BYTES, INITIAL_VALUES,
+        SHA256_DIGEST_SIZE,
+    },
+    Error, Hash160Instructions, HmacInstructions, RIPEMD160Instructions, RipemdInstructions,
+    RipemdVariant,
+};
 
 /// A word in `Table16` message block.
 #[derive(Clone, Copy, Debug, Default)]
@@ -31,194 +49,37 @@ impl From<u32> for BlockWord {
     }
 }
 
-/// Little-endian bits (up to 64 bits)
-#[derive(Debug, Clone)]
-pub struct Bits<const LEN: usize>([bool; LEN]);
-
-impl<const LEN: usize> Bits<LEN> {
-    fn spread<const SPREAD: usize>(&self) -> [bool; SPREAD] {
-        spread_bits(self.0)
-    }
-}
-
-impl<const LEN: usize> std::ops::Deref for Bits<LEN> {
-    type Target = [bool; LEN];
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl<const LEN: usize> From<[bool; LEN]> for Bits<LEN> {
-    fn from(bits: [bool; LEN]) -> Self {
-        Self(bits)
-    }
-}
-
-impl<const LEN: usize> From<&Bits<LEN>> for [bool; LEN] {
-    fn from(bits: &Bits<LEN>) -> Self {
-        bits.0
-    }
-}
-
-impl<const LEN: usize, F: FieldExt> From<&Bits<LEN>> for Assigned<F> {
-    fn from(bits: &Bits<LEN>) -> Self {
-        assert!(LEN <= 64);
-        F::from(lebs2ip(&bits.0)).into()
-    }
-}
-
-impl From<&Bits<16>> for u16 {
-    fn from(bits: &Bits<16>) -> Self {
-        lebs2ip(&bits.0) as u16
-    }
-}
-
-impl From<u16> for Bits<16> {
-    fn from(value: u16) -> Self {
-        Bits(i2lebsp::<16>(value.into()))
-    }
-}
-
-impl From<&Bits<32>> for u32 {
-    fn from(bits: &Bits<32>) -> Self {
-        lebs2ip(&bits.0) as u32
-    }
-}
-
-impl From<u32> for Bits<32> {
-    fn from(value: u32) -> Self {
-        Bits(i2lebsp::<32>(value.into()))
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct AssignedBits<const LEN: usize, F: FieldExt>(AssignedCell<Bits<LEN>, F>);
-
-impl<const LEN: usize, F: FieldExt> std::ops::Deref for AssignedBits<LEN, F> {
-    type Target = AssignedCell<Bits<LEN>, F>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl<const LEN: usize, F: FieldExt> AssignedBits<LEN, F> {
-    fn assign_bits<A, AR, T: TryInto<[bool; LEN]> + std::fmt::Debug + Clone>(
-        region: &mut Region<'_, F>,
-        annotation: A,
-        column: impl Into<Column<Any>>,
-        offset: usize,
-        value: Value<T>,
-    ) -> Result<Self, Error>
-    where
-        A: Fn() -> AR,
-        AR: Into<String>,
-        <T as TryInto<[bool; LEN]>>::Error: std::fmt::Debug,
-    {
-        let value: Value<[bool; LEN]> = value.map(|v| v.try_into().unwrap());
-        let value: Value<Bits<LEN>> = value.map(|v| v.into());
-
-        let column: Column<Any> = column.into();
-        match column.column_type() {
-            Any::Advice(_) => {
-                region.assign_advice(annotation, column.try_into().unwrap(), offset, || {
-                    value.clone()
-                })
-            }
-            Any::Fixed => {
-                region.assign_fixed(annotation, column.try_into().unwrap(), offset, || {
-                    value.clone()
-                })
-            }
-            _ => panic!("Cannot assign to instance column"),
-        }
-        .map(AssignedBits)
-    }
-}
-
-impl<F: FieldExt> AssignedBits<16, F> {
-    fn value_u16(&self) -> Value<u16> {
-        self.value().map(|v| v.into())
-    }
-
-    fn assign<A, AR>(
-        region: &mut Region<'_, F>,
-        annotation: A,
-        column: impl Into<Column<Any>>,
-        offset: usize,
-        value: Value<u16>,
-    ) -> Result<Self, Error>
-    where
-        A: Fn() -> AR,
-        AR: Into<String>,
-    {
-        let column: Column<Any> = column.into();
-        let value: Value<Bits<16>> = value.map(|v| v.into());
-        match column.column_type() {
-            Any::Advice(_) => {
-                region.assign_advice(annotation, column.try_into().unwrap(), offset, || {
-                    value.clone()
-                })
-            }
-            Any::Fixed => {
-                region.assign_fixed(annotation, column.try_into().unwrap(), offset, || {
-                    value.clone()
-                })
-            }
-            _ => panic!("Cannot assign to instance column"),
-        }
-        .map(AssignedBits)
-    }
-}
-
-impl<F: FieldExt> AssignedBits<32, F> {
-    fn value_u32(&self) -> Value<u32> {
-        self.value().map(|v| v.into())
-    }
-
-    fn assign<A, AR>(
-        region: &mut Region<'_, F>,
-        annotation: A,
-        column: impl Into<Column<Any>>,
-        offset: usize,
-        value: Value<u32>,
-    ) -> Result<Self, Error>
-    where
-        A: Fn() -> AR,
-        AR: Into<String>,
-    {
-        let column: Column<Any> = column.into();
-        let value: Value<Bits<32>> = value.map(|v| v.into());
-        match column.column_type() {
-            Any::Advice(_) => {
-                region.assign_advice(annotation, column.try_into().unwrap(), offset, || {
-                    value.clone()
-                })
-            }
-            Any::Fixed => {
-                region.assign_fixed(annotation, column.try_into().unwrap(), offset, || {
-                    value.clone()
-                })
-            }
-            _ => panic!("Cannot assign to instance column"),
-        }
-        .map(AssignedBits)
-    }
-}
-
 /// Configuration of [`Table16Chip`]
 #[derive(Clone, Debug)]
 pub struct Table16Config<F: FieldExt> {
     lookup: SpreadTableConfig,
     message_schedule: MessageScheduleConfig<F>,
     compression: CompressionConfig<F>,
+    compression_right: CompressionConfig<F>,
+    rlc: Option<RlcConfig<F>>,
+    // The table of the calls, when configured as a precompile
+    table: Option<Ripemd160Table>,
+}
+
+/// The assigned words and digest of a message along with the RLCs of the bytes of the
+/// message and of the digest, as returned by [`Table16Chip::digest_batch_with_rlc`].
+#[derive(Clone, Debug)]
+pub struct RlcDigest<F: FieldExt> {
+    /// The words of the padded blocks of the message
+    pub words: Vec<AssignedBits<32, F>>,
+    pub digest: [AssignedBits<32, F>; DIGEST_SIZE],
+    pub input_rlc: AssignedCell<F, F>,
+    pub digest_rlc: AssignedCell<F, F>,
 }
 
 /// A chip that implement the RIPEMD-160 with a maximum lookup table size of $2^16$.
+///
+/// The chip can also compute the other variants of the RIPEMD family through
+/// [`RipemdInstructions`], which share the same gates.
 #[derive(Debug, Clone)]
 pub struct Table16Chip<F: FieldExt> {
     config: Table16Config<F>,
+    variant: RipemdVariant,
 }
 
 impl<F: FieldExt> Chip<F> for Table16Chip<F> {
@@ -237,11 +98,50 @@ impl<F: FieldExt> Chip<F> for Table16Chip<F> {
 impl<F: FieldExt> Table16Chip<F> {
     /// Reconstructs this chip from the given config.
     pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
-        Self { config }
+        Self::construct_variant(config, RipemdVariant::Ripemd160)
+    }
+
+    /// Reconstructs this chip from the given config, computing the given variant through
+    /// [`RipemdInstructions`]. [`RIPEMD160Instructions`] always compute RIPEMD-160.
+    pub fn construct_variant(config: <Self as Chip<F>>::Config, variant: RipemdVariant) -> Self {
+        Self { config, variant }
     }
 
     /// Configure a circuit to include this chip.
     pub fn configure(meta: &mut ConstraintSystem<F>) -> <Self as Chip<F>>::Config {
+        Self::configure_with(meta, None, None)
+    }
+
+    /// Configure a circuit to include this chip along with a second-phase advice column
+    /// for the RLCs of [`digest_batch_with_rlc`](Self::digest_batch_with_rlc). The RLCs use
+    /// `challenge`, which should be the challenge of the EVM circuit so that they match the
+    /// RLCs of its lookup tables.
+    pub fn configure_with_rlc(
+        meta: &mut ConstraintSystem<F>,
+        challenge: Challenge,
+    ) -> <Self as Chip<F>>::Config {
+        Self::configure_with(meta, None, Some(challenge))
+    }
+
+    /// Configure a circuit to include this chip, looking up a spread table shared with
+    /// the other chips of the circuit, such as a [`bitwise_gadgets::BitwiseChip`] or
+    /// another hash chip, instead of configuring a table of its own. The circuit loads
+    /// the table once with [`SpreadTable::load`], and [`load`](Self::load) does nothing.
+    /// The RLCs are configured as in
+    /// [`configure_with_rlc`](Self::configure_with_rlc) when `challenge` is given.
+    pub fn configure_with_spread_table(
+        meta: &mut ConstraintSystem<F>,
+        table: &SpreadTable,
+        challenge: Option<Challenge>,
+    ) -> <Self as Chip<F>>::Config {
+        Self::configure_with(meta, Some(table), challenge)
+    }
+
+    fn configure_with(
+        meta: &mut ConstraintSystem<F>,
+        table: Option<&SpreadTable>,
+        challenge: Option<Challenge>,
+    ) -> <Self as Chip<F>>::Config {
         // columns required for this chip
         let advice = meta.advice_column();
 
@@ -250,7 +150,16 @@ impl<F: FieldExt> Table16Chip<F> {
         let input_dense = meta.advice_column();
         let input_spread = meta.advice_column();
 
-        let lookup = SpreadTableChip::configure(meta, input_tag, input_dense, input_spread);
+        let lookup = match table {
+            Some(table) => SpreadTableChip::configure_with_table(
+                meta,
+                table,
+                input_tag,
+                input_dense,
+                input_spread,
+            ),
+            None => SpreadTableChip::configure(meta, input_tag, input_dense, input_spread),
+        };
         let lookup_inputs = lookup.input.clone();
 
         // Rename these here for ease of matching the gates to the specification.
@@ -264,6 +173,10 @@ impl<F: FieldExt> Table16Chip<F> {
             meta.enable_equality(*col);
         }
 
+        // Fixed column for constants such as the padding of fixed-length messages
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
         let s_decompose_word = meta.selector();
 
         let compression =
@@ -272,22 +185,103 @@ impl<F: FieldExt> Table16Chip<F> {
         let message_schedule =
             MessageScheduleConfig::configure(meta, lookup_inputs, advice, s_decompose_word);
 
+        // The right line of the compression function gets its own copy of the columns
+        // above, looking up into the same table, so it can be laid out next to the left line.
+        let advice_right = meta.advice_column();
+        let input_tag_right = meta.advice_column();
+        let input_dense_right = meta.advice_column();
+        let input_spread_right = meta.advice_column();
+
+        let lookup_inputs_right = SpreadTableChip::configure_inputs(
+            meta,
+            &lookup.table,
+            input_tag_right,
+            input_dense_right,
+            input_spread_right,
+        );
+
+        for col in [input_dense_right, input_spread_right, advice_right].iter() {
+            meta.enable_equality(*col);
+        }
+
+        let s_decompose_word_right = meta.selector();
+
+        let compression_right = CompressionConfig::configure(
+            meta,
+            lookup_inputs_right,
+            advice_right,
+            s_decompose_word_right,
+        );
+
+        // The RLCs look up the bytes of the words in the lookup columns of the left line
+        let rlc = challenge
+            .map(|challenge| RlcConfig::configure(meta, lookup.input.clone(), advice, challenge));
+
         Table16Config {
             lookup,
             message_schedule,
             compression,
+            compression_right,
+            rlc,
+            table: None,
         }
     }
 
-    /// Loads the lookup table required by this chip into the circuit
+    /// Loads the lookup table required by this chip into the circuit, unless the chip is
+    /// configured with a shared table
     pub fn load(config: Table16Config<F>, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        SpreadTableChip::load(config.lookup, layouter)
+        Ok(SpreadTableChip::load(config.lookup, layouter)?)
+    }
+
+    /// Hashes each of the given padded messages like
+    /// [`digest_batch`](RIPEMD160Instructions::digest_batch), where each message comes with
+    /// its length in bytes before padding. Also assigns the RLC of the bytes of the
+    /// message and the RLC of the 20 bytes of its digest.
+    ///
+    /// The bytes of the blocks after the message are constrained to its padding, so the
+    /// digest is the hash of the bytes of the input RLC.
+    pub fn digest_batch_with_rlc(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        messages: &[(Vec<[BlockWord; BLOCK_SIZE]>, usize)],
+    ) -> Result<Vec<RlcDigest<F>>, Error> {
+        let config = self.config();
+        let rlc = config.rlc.as_ref().ok_or(Error::RlcNotConfigured)?;
+        for (blocks, len) in messages {
+            // The padding takes a 0x80 byte and the 8-byte length
+            if blocks.len() != (len + 8) / BLOCK_SIZE_BYTES + 1 {
+                return Err(Error::MessageLength {
+                    len: *len,
+                    blocks: blocks.len(),
+                });
+            }
+        }
+
+        let blocks: Vec<_> = messages.iter().map(|(blocks, _)| blocks.clone()).collect();
+        let digests =
+            config
+                .compression
+                .digest_batch(layouter, &config.compression_right, &blocks)?;
+
+        digests
+            .into_iter()
+            .zip(messages)
+            .map(|((words, digest), (_, len))| {
+                Ok(RlcDigest {
+                    input_rlc: rlc.assign_rlc(layouter, &words, *len, true)?,
+                    digest_rlc: rlc.assign_rlc(layouter, &digest, DIGEST_SIZE_BYTES, false)?,
+                    words,
+                    digest,
+                })
+            })
+            .collect()
     }
 }
 
 impl<F: FieldExt> RIPEMD160Instructions<F> for Table16Chip<F> {
     type State = State<F>;
     type BlockWord = BlockWord;
+    type Word = AssignedBits<32, F>;
 
     fn init_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error> {
         self.config()
@@ -295,30 +289,188 @@ impl<F: FieldExt> RIPEMD160Instructions<F> for Table16Chip<F> {
             .init_with_iv(layouter, INITIAL_VALUES)
     }
 
+    fn init_with_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[Self::Word; DIGEST_SIZE],
+    ) -> Result<Self::State, Error> {
+        self.config().compression.init_with_state(layouter, state)
+    }
+
+    fn witness_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[Self::BlockWord],
+    ) -> Result<Vec<Self::Word>, Error> {
+        self.config()
+            .message_schedule
+            .witness_words(layouter, words)
+    }
+
     // Given an initialized state and an input message block, compress the
     // message block and return the final state
     fn compress(
         &self,
         layouter: &mut impl Layouter<F>,
         initialized_state: &Self::State,
-        input: [Self::BlockWord; crate::constants::BLOCK_SIZE],
+        input: [Self::BlockWord; BLOCK_SIZE],
     ) -> Result<Self::State, Error> {
         let config = self.config();
         let (_, w_halves) = config.message_schedule.process(layouter, input)?;
-        config
-            .compression
-            .compress(layouter, initialized_state.clone(), w_halves)
+        config.compression.compress(
+            layouter,
+            &config.compression_right,
+            initialized_state.clone(),
+            w_halves,
+        )
     }
 
     fn digest(
         &self,
         layouter: &mut impl Layouter<F>,
         state: &Self::State,
-    ) -> Result<[Self::BlockWord; crate::constants::DIGEST_SIZE], Error> {
+    ) -> Result<[Self::BlockWord; DIGEST_SIZE], Error> {
         // Copy the dense forms of the state variable chunks down to this gate.
         // Reconstruct the 32-bit dense words.
+        let digest = self.config().compression.digest(layouter, state.clone())?;
+        Ok(digest.map(|word| BlockWord(word.value_u32())))
+    }
+
+    fn digest_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<[Self::Word; DIGEST_SIZE], Error> {
         self.config().compression.digest(layouter, state.clone())
     }
+
+    // Lays out all messages in one region, next to each other in both sets of columns
+    fn digest_batch(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        messages: &[Vec<[Self::BlockWord; BLOCK_SIZE]>],
+    ) -> Result<Vec<[Self::Word; DIGEST_SIZE]>, Error> {
+        let config = self.config();
+        let digests =
+            config
+                .compression
+                .digest_batch(layouter, &config.compression_right, messages)?;
+        Ok(digests.into_iter().map(|(_, digest)| digest).collect())
+    }
+}
+
+impl<F: FieldExt> RipemdInstructions<F> for Table16Chip<F> {
+    type State = ChainingState<F>;
+    type BlockWord = BlockWord;
+
+    fn variant(&self) -> RipemdVariant {
+        self.variant
+    }
+
+    fn init_vector(&self, layouter: &mut impl Layouter<F>) -> Result<Self::State, Error> {
+        self.config()
+            .compression
+            .init_variant(layouter, self.variant)
+    }
+
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::BlockWord; BLOCK_SIZE],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        let (_, w_halves) = config.message_schedule.process(layouter, input)?;
+        config.compression.compress_variant(
+            layouter,
+            &config.compression_right,
+            self.variant,
+            initialized_state.clone(),
+            w_halves,
+        )
+    }
+
+    fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &Self::State,
+    ) -> Result<Vec<Self::BlockWord>, Error> {
+        let digest =
+            self.config()
+                .compression
+                .digest_variant(layouter, self.variant, state.clone())?;
+        Ok(digest
+            .into_iter()
+            .map(|word| BlockWord(word.value_u32()))
+            .collect())
+    }
+}
+
+impl<F: FieldExt> Hash160Instructions<F> for Table16Chip<F> {
+    fn compress_be_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: [Self::Word; SHA256_DIGEST_SIZE],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        let w_halves = config.message_schedule.process_be_words(layouter, input)?;
+        config.compression.compress(
+            layouter,
+            &config.compression_right,
+            initialized_state.clone(),
+            w_halves,
+        )
+    }
+}
+
+impl<F: FieldExt> HmacInstructions<F> for Table16Chip<F> {
+    type Block = [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE];
+
+    fn key_pads(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[Self::Word],
+    ) -> Result<(Self::Block, Self::Block), Error> {
+        self.config()
+            .message_schedule
+            .process_key_pads(layouter, key)
+    }
+
+    fn compress_block(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        input: &Self::Block,
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        config.compression.compress(
+            layouter,
+            &config.compression_right,
+            initialized_state.clone(),
+            input.clone(),
+        )
+    }
+
+    fn compress_digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        initialized_state: &Self::State,
+        digest: [Self::Word; DIGEST_SIZE],
+    ) -> Result<Self::State, Error> {
+        let config = self.config();
+        let w_halves = config.message_schedule.process_digest_words(
+            layouter,
+            digest,
+            BLOCK_SIZE_BYTES + DIGEST_SIZE_BYTES,
+        )?;
+        config.compression.compress(
+            layouter,
+            &config.compression_right,
+            initialized_state.clone(),
+            w_halves,
+        )
+    }
 }
 
 /// Common assignment patterns used by Table16 regions.
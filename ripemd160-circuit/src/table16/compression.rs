@@ -3,22 +3,37 @@ use std::marker::PhantomData;
 use halo2_proofs::{
     circuit::{Layouter, Value},
     halo2curves::FieldExt,
-    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+    plonk::{Advice, Column, ConstraintSystem, Fixed, Selector},
     poly::Rotation,
 };
 
 use crate::{
-    constants::{BLOCK_SIZE, DIGEST_SIZE, ROUNDS},
+    constants::{BLOCK_SIZE, DIGEST_SIZE, INITIAL_VALUES, ROUND_PHASE_SIZE},
+    error::assign_region,
     table16::{compression::compression_gates::CompressionGate, gates::Gate},
+    Error, RipemdVariant,
 };
 
-use super::{spread_table::SpreadInputs, AssignedBits, BlockWord, Table16Assignment};
+use super::{AssignedBits, BlockWord, SpreadInputs, Table16Assignment};
 
 mod compression_gates;
 mod compression_util;
 mod subregion_digest;
 mod subregion_initial;
 mod subregion_main;
+mod witness;
+
+use compression_util::match_state;
+use witness::{CompressionWitness, RoundWitness};
+
+// Rows needed for each gate of the compression function
+pub const F1_ROWS: usize = 6;
+pub const F2F4_ROWS: usize = 11;
+pub const F3F5_ROWS: usize = 10;
+pub const ROTATE_LEFT_ROWS: usize = 4;
+pub const STEP_ROWS: usize = 12;
+pub const SPREAD_WORD_ROWS: usize = 2;
+pub const SUM_COMBINE_ILR_ROWS: usize = 7;
 
 #[derive(Debug, Clone)]
 pub struct RoundWordDense<F: FieldExt>(AssignedBits<16, F>, AssignedBits<16, F>);
@@ -79,6 +94,9 @@ pub struct State<F: FieldExt> {
     c: Option<StateWord<F>>,
     d: Option<StateWord<F>>,
     e: Option<StateWord<F>>,
+    // rotate_left(B, 10) and rotate_left(C, 10) when already computed by an earlier step
+    rol10_b: Option<RoundWord<F>>,
+    rol10_c: Option<RoundWord<F>>,
 }
 
 impl<F: FieldExt> State<F> {
@@ -95,6 +113,8 @@ impl<F: FieldExt> State<F> {
             c: Some(c),
             d: Some(d),
             e: Some(e),
+            rol10_b: None,
+            rol10_c: None,
         }
     }
 
@@ -105,8 +125,65 @@ impl<F: FieldExt> State<F> {
             c: None,
             d: None,
             e: None,
+            rol10_b: None,
+            rol10_c: None,
+        }
+    }
+
+    /// Exchanges a register, with A = 0, with the state of the other line, together with
+    /// its rotate_left(_, 10) word when already computed.
+    pub fn exchange(&mut self, other: &mut Self, register: usize) {
+        match register {
+            0 => std::mem::swap(&mut self.a, &mut other.a),
+            1 => {
+                std::mem::swap(&mut self.b, &mut other.b);
+                std::mem::swap(&mut self.rol10_b, &mut other.rol10_b);
+            }
+            2 => {
+                std::mem::swap(&mut self.c, &mut other.c);
+                std::mem::swap(&mut self.rol10_c, &mut other.rol10_c);
+            }
+            3 => std::mem::swap(&mut self.d, &mut other.d),
+            4 => std::mem::swap(&mut self.e, &mut other.e),
+            _ => unreachable!(),
         }
     }
+
+    /// The values of the words of the state
+    pub fn value(&self) -> Value<[u32; DIGEST_SIZE]> {
+        let (a, b, c, d, e) = match_state(self.clone());
+        [
+            a.value(),
+            b.dense_halves.value(),
+            c.dense_halves.value(),
+            d.dense_halves.value(),
+            e.value(),
+        ]
+        .into_iter()
+        .collect::<Value<Vec<u32>>>()
+        .map(|words| words.try_into().unwrap())
+    }
+
+    /// Attaches the already computed rotate_left(B, 10) and rotate_left(C, 10) words.
+    pub fn with_rol10(
+        mut self,
+        rol10_b: Option<RoundWord<F>>,
+        rol10_c: Option<RoundWord<F>>,
+    ) -> Self {
+        self.rol10_b = rol10_b;
+        self.rol10_c = rol10_c;
+        self
+    }
+}
+
+/// Chaining state of a RIPEMD variant. RIPEMD-256 and RIPEMD-320 keep a separate state for
+/// the right line, while RIPEMD-128 and RIPEMD-160 combine both lines into the left state.
+/// The four-word lines of RIPEMD-128 and RIPEMD-256 keep E at a constant zero, so that they
+/// share the step gate of the five-word lines.
+#[derive(Debug, Clone)]
+pub struct ChainingState<F: FieldExt> {
+    left: State<F>,
+    right: Option<State<F>>,
 }
 
 #[derive(Debug, Clone)]
@@ -126,16 +203,16 @@ pub enum RoundSide {
 
 #[derive(Debug, Clone)]
 pub(super) struct CompressionConfig<F: FieldExt> {
-    lookup: SpreadInputs,
+    pub(super) lookup: SpreadInputs,
     advice: Column<Advice>,
 
     s_decompose_word: Selector,
     s_f1: Selector,
     s_f2f4: Selector,
     s_f3f5: Selector,
-    s_rotate_left: [Selector; 11], // Rotate left with shifts from 5 to 15(inclusive)
-    s_sum_afxk: Selector,
-    s_sum_re: Selector,
+    s_rotate_left: Selector, // Rotate left with shifts from 1 to 31 (inclusive)
+    s_step: Selector,
+    coeffs: Column<Fixed>, // Shift-dependent coefficients and round constants
     s_sum_combine_ilr: Selector,
 
     _marker: PhantomData<F>,
@@ -153,21 +230,9 @@ impl<F: FieldExt> CompressionConfig<F> {
         let s_f1 = meta.selector();
         let s_f2f4 = meta.selector();
         let s_f3f5 = meta.selector();
-        let s_rotate_left = [
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-            meta.selector(),
-        ];
-        let s_sum_afxk = meta.selector();
-        let s_sum_re = meta.selector();
+        let s_rotate_left = meta.selector();
+        let s_step = meta.selector();
+        let coeffs = meta.fixed_column();
         let s_sum_combine_ilr = meta.selector();
 
         let a_0 = lookup.tag;
@@ -355,312 +420,39 @@ impl<F: FieldExt> CompressionConfig<F> {
             )
         });
 
-        // rotate_left_5 on a, b, c words
-        // s_rol5 | a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  b       |        | a_lo        |
-        //        |     |  c       |        | a_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
+        // rotate_left on a word W by any shift in 1..32, with s = shift % 16
+        // The half H of W that is split into (b, a) = (16 - s, s) bit chunks is word_hi when
+        // shift < 16 and word_lo otherwise (swap = 1). The shift-dependent coefficients are
+        // stored in a fixed column.
+        // s_rotate_left | a_0   |   a_1           |   a_2    |    a_3      | coeffs     |
+        //   1           | tag_a |  a              | spread_a | word_lo     | 2^(16 - s) |
+        //               | tag_b |  b              | spread_b | word_hi     | 2^s        |
+        //               |       |  a * 2^(16 - s) |          | rol_word_lo | swap       |
+        //               |       |  b * 2^s        |          | rol_word_hi |            |
         //
-        meta.create_gate("rotate_left_5", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[0]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation(0));
-            let a_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_5_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_6 on a, b, c words
-        // s_rol6 | a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  b       |        | a_lo        |
-        //        |     |  c       |        | a_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
-        //
-        meta.create_gate("rotate_left_6", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[1]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation(0));
-            let a_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_6_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_7 on a, b, c words
-        // s_rol7 | a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  b       |        | a_lo        |
-        //        |     |  c       |        | a_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
-        //
-        meta.create_gate("rotate_left_7", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[2]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation(0));
-            let a_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_7_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_8 on a, b, c words
-        // s_rol8 | a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  b       |        | a_lo        |
-        //        |     |  c       |        | a_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
-        //
-        meta.create_gate("rotate_left_8", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[3]);
-            let tag_b = meta.query_advice(a_0, Rotation::cur());
-            let b = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let a_lo = meta.query_advice(a_3, Rotation(0));
-            let a_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_8_gate(
-                s_rotate_left,
-                a_lo,
-                a_hi,
-                b,
-                tag_b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_9 on a, b, c words
-        // s_rol9 | a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  a       |        | b_lo        |
-        //        |     |  c       |        | b_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
-        //
-        meta.create_gate("s_rotate_left_9", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[4]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation(0));
-            let b_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_9_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_10 on a, b, c words
-        // s_rol10| a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  a       |        | b_lo        |
-        //        |     |  c       |        | b_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
-        //
-        meta.create_gate("s_rotate_left_10", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[5]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation(0));
-            let b_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_10_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_11 on a, b, c words
-        // s_rol11| a_0 |   a_1  |  a_2  |    a_3      |
-        //   1    |  1  |  a     |       | b_lo        |
-        //        |     |  c     |       | b_hi        |
-        //        |     |        |       | word_lo     |
-        //        |     |        |       | word_hi     |
-        //        |     |        |       | rol_word_lo |
-        //        |     |        |       | rol_word_hi |
-        //
-        meta.create_gate("s_rotate_left_11", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[6]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation(0));
-            let b_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_11_gate(
+        meta.create_gate("s_rotate_left", |meta| {
+            let s_rotate_left = meta.query_selector(s_rotate_left);
+            let coef_a = meta.query_fixed(coeffs, Rotation(0));
+            let coef_b = meta.query_fixed(coeffs, Rotation(1));
+            let swap = meta.query_fixed(coeffs, Rotation(2));
+            let a = meta.query_advice(a_1, Rotation(0));
+            let b = meta.query_advice(a_1, Rotation(1));
+            let a_shifted = meta.query_advice(a_1, Rotation(2));
+            let b_shifted = meta.query_advice(a_1, Rotation(3));
+            let word_lo = meta.query_advice(a_3, Rotation(0));
+            let word_hi = meta.query_advice(a_3, Rotation(1));
+            let rol_word_lo = meta.query_advice(a_3, Rotation(2));
+            let rol_word_hi = meta.query_advice(a_3, Rotation(3));
+
+            CompressionGate::rotate_left_gate(
                 s_rotate_left,
+                coef_a,
+                coef_b,
+                swap,
                 a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_12 on a, b, c words
-        // s_rol12| a_0 |   a_1    |   a_2  |    a_3      |
-        //   1    |  1  |  a       |        | b_lo        |
-        //        |     |  c       |        | b_hi        |
-        //        |     |          |        | word_lo     |
-        //        |     |          |        | word_hi     |
-        //        |     |          |        | rol_word_lo |
-        //        |     |          |        | rol_word_hi |
-        //
-        meta.create_gate("s_rotate_left_12", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[7]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b_lo = meta.query_advice(a_3, Rotation(0));
-            let b_hi = meta.query_advice(a_3, Rotation(1));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_12_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b_lo,
-                b_hi,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_13 on a, b, c words
-        // s_rol13| a_0 |   a_1  |  a_2  |    a_3      |
-        //   1    |  1  |  a     |       | b           |
-        //        |     |  c     |       |             |
-        //        |     |        |       | word_lo     |
-        //        |     |        |       | word_hi     |
-        //        |     |        |       | rol_word_lo |
-        //        |     |        |       | rol_word_hi |
-        //
-        meta.create_gate("s_rotate_left_13", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[8]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b = meta.query_advice(a_3, Rotation(0));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_13_gate(
-                s_rotate_left,
-                a,
-                tag_a,
                 b,
-                c,
+                a_shifted,
+                b_shifted,
                 word_lo,
                 word_hi,
                 rol_word_lo,
@@ -668,125 +460,79 @@ impl<F: FieldExt> CompressionConfig<F> {
             )
         });
 
-        // rotate_left_14 on a, b, c words
-        // s_rol14| a_0 |   a_1  |  a_2  |    a_3      |
-        //   1    |  1  |  a     |       | b           |
-        //        |     |  c     |       |             |
-        //        |     |        |       | word_lo     |
-        //        |     |        |       | word_hi     |
-        //        |     |        |       | rol_word_lo |
-        //        |     |        |       | rol_word_hi |
+        // s_step computes T = rotate_left(A + f(j, B, C, D) + X[r[j]] + K[j], s) + E for
+        // 0 < s < 16, together with rotate_left(T, 10)
+        // s_step   | a_0     | a_1                | a_2               | a_3       | coeffs     |
+        //   1      | tag     | sum_lo             | spread_sum_lo     | a_lo      | 2^(16 - s) |
+        //          | tag     | sum_a              | spread_sum_a      | a_hi      | 2^s        |
+        //          | tag     | sum_b              | spread_sum_b      | f_lo      | K          |
+        //          | tag     | sum_a * 2^(16 - s) |                   | f_hi      |            |
+        //          | tag     | sum_b * 2^s        |                   | x_lo      |            |
+        //          | tag     | t_lo               | spread_t_lo       | x_hi      |            |
+        //          | tag     | t_hi               | spread_t_hi       | e_lo      |            |
+        //          | tag_r_a | r_a                | spread_r_a        | e_hi      |            |
+        //          | tag     | r_b                | spread_r_b        | carry_sum |            |
+        //          | tag     | r_b * 2^10         |                   | carry_t   |            |
+        //          | tag     | rol10_t_lo         | spread_rol10_t_lo |           |            |
+        //          | tag     | rol10_t_hi         | spread_rol10_t_hi |           |            |
         //
-        meta.create_gate("s_rotate_left_14", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[9]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b = meta.query_advice(a_3, Rotation(0));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_14_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // rotate_left_15 on a, b, c words
-        // s_rol15| a_0 |   a_1  |   a_2  |    a_3      |
-        //   1    |  1  |  a     |        | b           |
-        //        |     |  c     |        |             |
-        //        |     |        |        | word_lo     |
-        //        |     |        |        | word_hi     |
-        //        |     |        |        | rol_word_lo |
-        //        |     |        |        | rol_word_hi |
-        //
-        meta.create_gate("s_rotate_left_15", |meta| {
-            let s_rotate_left = meta.query_selector(s_rotate_left[10]);
-            let tag_a = meta.query_advice(a_0, Rotation::cur());
-            let a = meta.query_advice(a_1, Rotation::cur());
-            let c = meta.query_advice(a_1, Rotation::next());
-            let b = meta.query_advice(a_3, Rotation(0));
-            let word_lo = meta.query_advice(a_3, Rotation(2));
-            let word_hi = meta.query_advice(a_3, Rotation(3));
-            let rol_word_lo = meta.query_advice(a_3, Rotation(4));
-            let rol_word_hi = meta.query_advice(a_3, Rotation(5));
-
-            CompressionGate::rotate_left_15_gate(
-                s_rotate_left,
-                a,
-                tag_a,
-                b,
-                c,
-                word_lo,
-                word_hi,
-                rol_word_lo,
-                rol_word_hi,
-            )
-        });
-
-        // s_sum_afxk
-        // s_sum_afxk | a_0 |   a_1    |  a_2  |    a_3  |
-        //   1        |     | sum_lo   |       | a_lo    |
-        //            |     | sum_hi   |       | a_hi    |
-        //            |     |          |       | f_lo    |
-        //            |     |          |       | f_hi    |
-        //            |     |          |       | x_lo    |
-        //            |     |          |       | x_hi    |
-        //            |     |          |       | k_lo    |
-        //            |     |          |       | k_hi    |
-        //            |     |          |       | carry   |
-        //
-        meta.create_gate("s_sum_afxk", |meta| {
-            let s_sum_afxk = meta.query_selector(s_sum_afxk);
-            let sum_lo = meta.query_advice(a_1, Rotation::cur());
-            let sum_hi = meta.query_advice(a_1, Rotation::next());
-
+        meta.create_gate("s_step", |meta| {
+            let s_step = meta.query_selector(s_step);
+            let coef_a = meta.query_fixed(coeffs, Rotation(0));
+            let coef_b = meta.query_fixed(coeffs, Rotation(1));
+            let k = meta.query_fixed(coeffs, Rotation(2));
+            let sum_lo = meta.query_advice(a_1, Rotation(0));
+            let sum_a = meta.query_advice(a_1, Rotation(1));
+            let sum_b = meta.query_advice(a_1, Rotation(2));
+            let sum_a_shifted = meta.query_advice(a_1, Rotation(3));
+            let sum_b_shifted = meta.query_advice(a_1, Rotation(4));
+            let t_lo = meta.query_advice(a_1, Rotation(5));
+            let t_hi = meta.query_advice(a_1, Rotation(6));
+            let tag_r_a = meta.query_advice(a_0, Rotation(7));
+            let r_a = meta.query_advice(a_1, Rotation(7));
+            let r_b = meta.query_advice(a_1, Rotation(8));
+            let r_b_shifted = meta.query_advice(a_1, Rotation(9));
+            let rol10_t_lo = meta.query_advice(a_1, Rotation(10));
+            let rol10_t_hi = meta.query_advice(a_1, Rotation(11));
             let a_lo = meta.query_advice(a_3, Rotation(0));
             let a_hi = meta.query_advice(a_3, Rotation(1));
             let f_lo = meta.query_advice(a_3, Rotation(2));
             let f_hi = meta.query_advice(a_3, Rotation(3));
             let x_lo = meta.query_advice(a_3, Rotation(4));
             let x_hi = meta.query_advice(a_3, Rotation(5));
-
-            let k_lo = meta.query_advice(a_3, Rotation(6));
-            let k_hi = meta.query_advice(a_3, Rotation(7));
-            let carry = meta.query_advice(a_3, Rotation(8));
-
-            CompressionGate::sum_afxk_gate(
-                s_sum_afxk, sum_lo, sum_hi, carry, a_lo, a_hi, f_lo, f_hi, x_lo, x_hi, k_lo, k_hi,
-            )
-        });
-
-        // s_sum_re
-        // s_sum_re | a_0 |   a_1  |  a_2  |    a_3   |
-        //   1      |     | sum_lo |       | rol_lo   |
-        //          |     | sum_hi |       | rol_hi   |
-        //          |     |        |       | e_lo     |
-        //          |     |        |       | e_hi     |
-        //          |     |        |       | carry    |
-        //
-        meta.create_gate("s_sum_re", |meta| {
-            let s_sum_re = meta.query_selector(s_sum_re);
-            let sum_lo = meta.query_advice(a_1, Rotation::cur());
-            let sum_hi = meta.query_advice(a_1, Rotation::next());
-            let rol_lo = meta.query_advice(a_3, Rotation(0));
-            let rol_hi = meta.query_advice(a_3, Rotation(1));
-            let e_lo = meta.query_advice(a_3, Rotation(2));
-            let e_hi = meta.query_advice(a_3, Rotation(3));
-            let carry = meta.query_advice(a_3, Rotation(4));
-
-            CompressionGate::sum_re_gate(
-                s_sum_re, sum_lo, sum_hi, carry, rol_lo, rol_hi, e_lo, e_hi,
+            let e_lo = meta.query_advice(a_3, Rotation(6));
+            let e_hi = meta.query_advice(a_3, Rotation(7));
+            let carry_sum = meta.query_advice(a_3, Rotation(8));
+            let carry_t = meta.query_advice(a_3, Rotation(9));
+
+            CompressionGate::step_gate(
+                s_step,
+                coef_a,
+                coef_b,
+                k,
+                sum_lo,
+                sum_a,
+                sum_b,
+                sum_a_shifted,
+                sum_b_shifted,
+                carry_sum,
+                a_lo,
+                a_hi,
+                f_lo,
+                f_hi,
+                x_lo,
+                x_hi,
+                e_lo,
+                e_hi,
+                t_lo,
+                t_hi,
+                carry_t,
+                tag_r_a,
+                r_a,
+                r_b,
+                r_b_shifted,
+                rol10_t_lo,
+                rol10_t_hi,
             )
         });
 
@@ -834,8 +580,8 @@ impl<F: FieldExt> CompressionConfig<F> {
             s_f2f4,
             s_f3f5,
             s_rotate_left,
-            s_sum_afxk,
-            s_sum_re,
+            s_step,
+            coeffs,
             s_sum_combine_ilr,
             _marker: PhantomData,
         }
@@ -849,62 +595,342 @@ impl<F: FieldExt> CompressionConfig<F> {
         init_state: [u32; DIGEST_SIZE],
     ) -> Result<State<F>, Error> {
         let mut new_state = State::empty_state();
-        layouter.assign_region(
+        assign_region(
+            layouter,
             || "init_with_iv",
             |mut region| {
-                new_state = self.init_iv(&mut region, init_state)?;
+                new_state = self.init_iv(&mut region, init_state, &mut 0)?;
                 Ok(())
             },
         )?;
         Ok(new_state)
     }
 
+    pub(super) fn init_with_state(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[AssignedBits<32, F>; DIGEST_SIZE],
+    ) -> Result<State<F>, Error> {
+        let mut new_state = State::empty_state();
+        assign_region(
+            layouter,
+            || "init_with_state",
+            |mut region| {
+                new_state = self.init_state(&mut region, state, &mut 0)?;
+                Ok(())
+            },
+        )?;
+        Ok(new_state)
+    }
+
+    /// Initialize compression with the constant IV of the given variant.
+    pub(super) fn init_variant(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        variant: RipemdVariant,
+    ) -> Result<ChainingState<F>, Error> {
+        let iv = variant.initial_values();
+        let n = variant.line_words();
+        // Four-word lines have E = 0
+        let line_iv = |words: &[u32]| {
+            let mut line_iv = [0; DIGEST_SIZE];
+            line_iv[..n].copy_from_slice(words);
+            line_iv
+        };
+
+        let left = self.init_with_iv(layouter, line_iv(&iv[..n]))?;
+        let right = if variant.combines_lines() {
+            None
+        } else {
+            Some(self.init_with_iv(layouter, line_iv(&iv[n..]))?)
+        };
+
+        Ok(ChainingState { left, right })
+    }
+
     /// Given an initialized state and a message schedule, perform 80 compression rounds.
+    ///
+    /// The right line is assigned with `right_line`, which uses its own set of columns so
+    /// that both lines are laid out side by side.
     pub(super) fn compress(
         &self,
         layouter: &mut impl Layouter<F>,
+        right_line: &Self,
         initialized_state: State<F>,
         w_halves: [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
     ) -> Result<State<F>, Error> {
-        let mut left_state = State::empty_state();
-        let mut right_state = State::empty_state();
-        let mut final_state = State::empty_state();
+        let state = self.compress_variant(
+            layouter,
+            right_line,
+            RipemdVariant::Ripemd160,
+            ChainingState {
+                left: initialized_state,
+                right: None,
+            },
+            w_halves,
+        )?;
+        Ok(state.left)
+    }
+
+    /// Given an initialized state and a message schedule, perform the compression rounds
+    /// of the given variant.
+    ///
+    /// Each phase of each line is assigned in its own region, so that the lines can
+    /// exchange registers between phases while still being laid out side by side. The
+    /// witness of the whole block is computed once, before any region is assigned.
+    pub(super) fn compress_variant(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        right_line: &Self,
+        variant: RipemdVariant,
+        initialized_state: ChainingState<F>,
+        w_halves: [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
+    ) -> Result<ChainingState<F>, Error> {
+        let block: Value<[u32; BLOCK_SIZE]> = w_halves
+            .iter()
+            .map(|(lo, hi)| RoundWordDense(lo.clone(), hi.clone()).value())
+            .collect::<Value<Vec<u32>>>()
+            .map(|words| words.try_into().unwrap());
+        let init_right = match &initialized_state.right {
+            Some(right) => right.value().map(Some),
+            None => Value::known(None),
+        };
+        let witness = initialized_state
+            .left
+            .value()
+            .zip(init_right)
+            .zip(block)
+            .map(|((init_left, init_right), block)| {
+                CompressionWitness::new(variant, init_left, init_right, block)
+            });
+        let witness = witness.as_ref();
+
+        let mut left_state = initialized_state.left.clone();
+        let mut right_state = initialized_state
+            .right
+            .clone()
+            .unwrap_or_else(|| initialized_state.left.clone());
+
+        for phase_idx in 0..variant.phases() {
+            left_state = self.compress_phase(
+                layouter,
+                variant,
+                phase_idx,
+                left_state,
+                &w_halves,
+                RoundSide::Left,
+                witness.map(|w| &w.left[..]),
+            )?;
+            right_state = right_line.compress_phase(
+                layouter,
+                variant,
+                phase_idx,
+                right_state,
+                &w_halves,
+                RoundSide::Right,
+                witness.map(|w| &w.right[..]),
+            )?;
+
+            if let Some(register) = variant.exchanged_register(phase_idx) {
+                left_state.exchange(&mut right_state, register);
+            }
+        }
 
-        layouter.assign_region(
-            || "compress",
+        let mut final_state = initialized_state.clone();
+        assign_region(
+            layouter,
+            || "combine",
             |mut region| {
                 let mut row: usize = 0;
-                left_state = initialized_state.clone();
-                right_state = initialized_state.clone();
-                for idx in 0..ROUNDS {
-                    left_state = self.assign_round(
-                        &mut region,
-                        idx,
-                        left_state.clone(),
-                        w_halves.clone(),
-                        &mut row,
-                        RoundSide::Left,
-                    )?;
-                    right_state = self.assign_round(
+                final_state = match initialized_state.right.clone() {
+                    None => ChainingState {
+                        left: self.assign_combine_ilr(
+                            &mut region,
+                            variant,
+                            initialized_state.left.clone(),
+                            left_state.clone(),
+                            right_state.clone(),
+                            witness.map(|w| &w.combine[..]),
+                            &mut row,
+                        )?,
+                        right: None,
+                    },
+                    Some(initialized_right) => ChainingState {
+                        left: self.assign_combine_line(
+                            &mut region,
+                            variant,
+                            initialized_state.left.clone(),
+                            left_state.clone(),
+                            witness.map(|w| &w.combine[..variant.line_words()]),
+                            &mut row,
+                        )?,
+                        right: Some(self.assign_combine_line(
+                            &mut region,
+                            variant,
+                            initialized_right,
+                            right_state.clone(),
+                            witness.map(|w| &w.combine[variant.line_words()..]),
+                            &mut row,
+                        )?),
+                    },
+                };
+                Ok(())
+            },
+        )?;
+        Ok(final_state)
+    }
+
+    /// Hashes the given padded messages with RIPEMD-160 in a single region, starting all of
+    /// them from one assignment of the IV.
+    ///
+    /// The left line and the combination of the lines are assigned in the columns of `self`
+    /// and the right line in the columns of `right_line`. Each message block and digest goes
+    /// in whichever set of columns is the shortest so far, which keeps both about as high.
+    ///
+    /// Returns the assigned words of the blocks of each message along with its digest.
+    pub(super) fn digest_batch(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        right_line: &Self,
+        messages: &[Vec<[BlockWord; BLOCK_SIZE]>],
+    ) -> Result<Vec<(Vec<AssignedBits<32, F>>, [AssignedBits<32, F>; DIGEST_SIZE])>, Error> {
+        let variant = RipemdVariant::Ripemd160;
+        let blocks: Vec<Vec<_>> = messages
+            .iter()
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .map(|block| block.map(|word| word.0))
+                    .collect()
+            })
+            .collect();
+        let witnesses = CompressionWitness::batch(INITIAL_VALUES, &blocks);
+
+        let mut digests = vec![];
+        assign_region(
+            layouter,
+            || "batch",
+            |mut region| {
+                digests = Vec::with_capacity(messages.len());
+                let mut row: usize = 0;
+                let mut right_row: usize = 0;
+
+                let iv = self.init_iv(&mut region, INITIAL_VALUES, &mut row)?;
+                for (blocks, witnesses) in messages.iter().zip(&witnesses) {
+                    let mut state = iv.clone();
+                    let mut words = Vec::with_capacity(blocks.len() * BLOCK_SIZE);
+                    for (block, witness) in blocks.iter().zip(witnesses) {
+                        let witness = witness.as_ref();
+                        let (w, w_halves) = if right_row <= row {
+                            right_line.assign_message_block(&mut region, *block, &mut right_row)?
+                        } else {
+                            self.assign_message_block(&mut region, *block, &mut row)?
+                        };
+                        words.extend(w);
+
+                        let mut left_state = state.clone();
+                        let mut right_state = state.clone();
+                        for idx in 0..variant.rounds() {
+                            left_state = self.assign_round(
+                                &mut region,
+                                variant,
+                                idx,
+                                left_state,
+                                w_halves.clone(),
+                                &mut row,
+                                RoundSide::Left,
+                                witness.map(|w| &w.left[idx]),
+                            )?;
+                            right_state = right_line.assign_round(
+                                &mut region,
+                                variant,
+                                idx,
+                                right_state,
+                                w_halves.clone(),
+                                &mut right_row,
+                                RoundSide::Right,
+                                witness.map(|w| &w.right[idx]),
+                            )?;
+                        }
+
+                        state = self.assign_combine_ilr(
+                            &mut region,
+                            variant,
+                            state,
+                            left_state,
+                            right_state,
+                            witness.map(|w| &w.combine[..]),
+                            &mut row,
+                        )?;
+                    }
+
+                    let digest = if right_row < row {
+                        right_line.assign_digest(&mut region, state, &mut right_row)?
+                    } else {
+                        self.assign_digest(&mut region, state, &mut row)?
+                    };
+                    digests.push((words, digest));
+                }
+
+                Ok(())
+            },
+        )?;
+        Ok(digests)
+    }
+
+    // Assigns the 16 rounds of a phase of one line, given the witnesses of all the rounds
+    // of the line
+    fn compress_phase(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        variant: RipemdVariant,
+        phase_idx: usize,
+        initialized_state: State<F>,
+        w_halves: &[(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
+        round_side: RoundSide,
+        witness: Value<&[RoundWitness]>,
+    ) -> Result<State<F>, Error> {
+        let mut state = State::empty_state();
+        assign_region(
+            layouter,
+            || match round_side {
+                RoundSide::Left => "compress left",
+                RoundSide::Right => "compress right",
+            },
+            |mut region| {
+                let mut row: usize = 0;
+                state = initialized_state.clone();
+                for idx in phase_idx * ROUND_PHASE_SIZE..(phase_idx + 1) * ROUND_PHASE_SIZE {
+                    state = self.assign_round(
                         &mut region,
+                        variant,
                         idx,
-                        right_state.clone(),
+                        state.clone(),
                         w_halves.clone(),
                         &mut row,
-                        RoundSide::Right,
+                        round_side.clone(),
+                        witness.map(|w| &w[idx]),
                     )?;
                 }
-                final_state = self.assign_combine_ilr(
-                    &mut region,
-                    initialized_state.clone(),
-                    left_state.clone(),
-                    right_state.clone(),
-                    &mut row,
-                )?;
                 Ok(())
             },
         )?;
-        Ok(final_state)
+        Ok(state)
+    }
+
+    /// Convert the chaining state of the given variant into its digest words.
+    pub(super) fn digest_variant(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        variant: RipemdVariant,
+        state: ChainingState<F>,
+    ) -> Result<Vec<AssignedBits<32, F>>, Error> {
+        let n = variant.line_words();
+        let mut digest = self.digest(layouter, state.left)?[..n].to_vec();
+        if let Some(right) = state.right {
+            digest.extend_from_slice(&self.digest(layouter, right)?[..n]);
+        }
+        Ok(digest)
     }
 
     /// After the final round, convert the state into the final digest.
@@ -912,17 +938,12 @@ impl<F: FieldExt> CompressionConfig<F> {
         &self,
         layouter: &mut impl Layouter<F>,
         state: State<F>,
-    ) -> Result<[BlockWord; DIGEST_SIZE], Error> {
-        let mut digest = [BlockWord(Value::known(0)); DIGEST_SIZE];
-        layouter.assign_region(
+    ) -> Result<[AssignedBits<32, F>; DIGEST_SIZE], Error> {
+        assign_region(
+            layouter,
             || "digest",
-            |mut region| {
-                digest = self.assign_digest(&mut region, state.clone())?;
-                Ok(())
-            },
-        )?;
-
-        Ok(digest)
+            |mut region| self.assign_digest(&mut region, state.clone(), &mut 0),
+        )
     }
 }
 
@@ -971,9 +992,9 @@ mod tests {
                 let input: [u32; BLOCK_SIZE] =
                     convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(
                         pad_message_bytes(input_bytes.to_vec())[0],
-                    );
+                    )?;
                 let output: [u32; DIGEST_SIZE] =
-                    convert_byte_slice_to_u32_slice(hash(input_bytes.to_vec()));
+                    convert_byte_slice_to_u32_slice(hash(input_bytes.to_vec()))?;
 
                 let (_, w_halves) = config
                     .message_schedule
@@ -982,9 +1003,12 @@ mod tests {
                 let compression = config.compression.clone();
                 let initial_state = compression.init_with_iv(&mut layouter, INITIAL_VALUES)?;
 
-                let state = config
-                    .compression
-                    .compress(&mut layouter, initial_state, w_halves)?;
+                let state = config.compression.compress(
+                    &mut layouter,
+                    &config.compression_right,
+                    initial_state,
+                    w_halves,
+                )?;
                 let (a, b, c, d, e) = match_state(state.clone());
 
                 let a_3 = config.compression.advice;
@@ -1128,7 +1152,9 @@ mod tests {
 
                 let digest = config.compression.digest(&mut layouter, state)?;
                 for (idx, digest_word) in digest.iter().enumerate() {
-                    digest_word.0.assert_if_known(|v| *v == output[idx]);
+                    digest_word
+                        .value_u32()
+                        .assert_if_known(|v| *v == output[idx]);
                 }
 
                 Ok(())
@@ -1,10 +1,11 @@
+use bitwise_gadgets::util::MASK_EVEN_32;
 use halo2_proofs::{
     arithmetic::FieldExt,
     plonk::{Constraints, Expression},
 };
 use std::marker::PhantomData;
 
-use crate::table16::{gates::Gate, util::MASK_EVEN_32};
+use crate::table16::gates::Gate;
 
 pub struct CompressionGate<F: FieldExt>(PhantomData<F>);
 
@@ -240,596 +241,138 @@ impl<F: FieldExt> CompressionGate<F> {
         )
     }
 
-    // Gate for rotate_left(W, 5)
-    // word = (a,b,c) = (5, 11, 16) chunks with a = (a_hi, a_lo) = (3, 2) chunks
-    pub fn rotate_left_5_gate(
-        s_rotate_left_5: Expression<F>,
-        a_lo: Expression<F>,
-        a_hi: Expression<F>,
-        b: Expression<F>,
-        tag_b: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_5_word_lo: Expression<F>,
-        rol_5_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_b = Gate::range_check(tag_b, 0, 3); // tag <= 3 => b < 2^11
-        let range_check_a_lo = Gate::two_bit_range(a_lo.clone());
-        let range_check_a_hi = Gate::three_bit_range(a_hi.clone());
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a_lo.clone() * F::from(1 << 27)
-            + a_hi.clone() * F::from(1 << 29)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_5_word_check = a_lo
-            + a_hi * F::from(1 << 2)
-            + c * F::from(1 << 5)
-            + b * F::from(1 << 21)
-            + rol_5_word_lo * (-F::one())
-            + rol_5_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_5,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_b", range_check_tag_b)))
-                .chain(range_check_a_lo)
-                .chain(range_check_a_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_5_word_check", rol_5_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 6)
-    // word = (a,b,c) = (6, 10, 16) chunks with a = (a_hi, a_lo) = (3, 3) chunks
-    pub fn rotate_left_6_gate(
-        s_rotate_left_6: Expression<F>,
-        a_lo: Expression<F>,
-        a_hi: Expression<F>,
-        b: Expression<F>,
-        tag_b: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_6_word_lo: Expression<F>,
-        rol_6_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_b = Gate::range_check(tag_b, 0, 2); // tag <= 2 => b < 2^10
-        let range_check_a_lo = Gate::three_bit_range(a_lo.clone());
-        let range_check_a_hi = Gate::three_bit_range(a_hi.clone());
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a_lo.clone() * F::from(1 << 26)
-            + a_hi.clone() * F::from(1 << 29)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_6_word_check = a_lo
-            + a_hi * F::from(1 << 3)
-            + c * F::from(1 << 6)
-            + b * F::from(1 << 22)
-            + rol_6_word_lo * (-F::one())
-            + rol_6_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_6,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_b", range_check_tag_b)))
-                .chain(range_check_a_lo)
-                .chain(range_check_a_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_6_word_check", rol_6_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 7)
-    // word = (a,b,c) = (7, 9, 16) chunks with a = (a_hi, a_lo) = (4, 3) chunks
-    pub fn rotate_left_7_gate(
-        s_rotate_left_7: Expression<F>,
-        a_lo: Expression<F>,
-        a_hi: Expression<F>,
-        b: Expression<F>,
-        tag_b: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_7_word_lo: Expression<F>,
-        rol_7_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_b = Gate::range_check(tag_b, 0, 1); // tag <= 1 => b < 2^9
-        let range_check_a_lo = Gate::three_bit_range(a_lo.clone());
-        let range_check_a_hi = Gate::four_bit_range(a_hi.clone());
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a_lo.clone() * F::from(1 << 25)
-            + a_hi.clone() * F::from(1 << 28)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_7_word_check = a_lo
-            + a_hi * F::from(1 << 3)
-            + c * F::from(1 << 7)
-            + b * F::from(1 << 23)
-            + rol_7_word_lo * (-F::one())
-            + rol_7_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_7,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_b", range_check_tag_b)))
-                .chain(range_check_a_lo)
-                .chain(range_check_a_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_7_word_check", rol_7_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 8)
-    // word = (a,b,c) = (8, 8, 16) chunks with a = (a_hi, a_lo) = (4, 4) chunks
-    pub fn rotate_left_8_gate(
-        s_rotate_left_8: Expression<F>,
-        a_lo: Expression<F>,
-        a_hi: Expression<F>,
-        b: Expression<F>,
-        tag_b: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_8_word_lo: Expression<F>,
-        rol_8_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_b = Gate::range_check(tag_b, 0, 0); // tag = 0 => b < 2^8
-        let range_check_a_lo = Gate::four_bit_range(a_lo.clone());
-        let range_check_a_hi = Gate::four_bit_range(a_hi.clone());
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a_lo.clone() * F::from(1 << 24)
-            + a_hi.clone() * F::from(1 << 28)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_8_word_check = a_lo
-            + a_hi * F::from(1 << 4)
-            + c * F::from(1 << 8)
-            + b * F::from(1 << 24)
-            + rol_8_word_lo * (-F::one())
-            + rol_8_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_8,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_b", range_check_tag_b)))
-                .chain(range_check_a_lo)
-                .chain(range_check_a_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_8_word_check", rol_8_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 9)
-    // word = (a,b,c) = (9, 7, 16) chunks with b = (b_hi, b_lo) = (4, 3) chunks
-    pub fn rotate_left_9_gate(
-        s_rotate_left_9: Expression<F>,
-        a: Expression<F>,
-        tag_a: Expression<F>,
-        b_lo: Expression<F>,
-        b_hi: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_9_word_lo: Expression<F>,
-        rol_9_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 1); // tag <= 1 => a < 2^9
-        let range_check_b_lo = Gate::three_bit_range(b_lo.clone());
-        let range_check_b_hi = Gate::four_bit_range(b_hi.clone());
-
-        let word_check = c.clone()
-            + b_lo.clone() * F::from(1 << 16)
-            + b_hi.clone() * F::from(1 << 19)
-            + a.clone() * F::from(1 << 23)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_9_word_check = a
-            + c * F::from(1 << 9)
-            + b_lo * F::from(1 << 25)
-            + b_hi * F::from(1 << 28)
-            + rol_9_word_lo * (-F::one())
-            + rol_9_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_9,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
-                .chain(range_check_b_lo)
-                .chain(range_check_b_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_9_word_check", rol_9_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 10)
-    // word = (a,b,c) = (10, 6, 16) chunks with b = (b_hi, b_lo) = (3, 3) chunks
-    pub fn rotate_left_10_gate(
-        s_rotate_left_10: Expression<F>,
-        a: Expression<F>,
-        tag_a: Expression<F>,
-        b_lo: Expression<F>,
-        b_hi: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_10_word_lo: Expression<F>,
-        rol_10_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 2); // tag <= 2 => a < 2^10
-        let range_check_b_lo = Gate::three_bit_range(b_lo.clone());
-        let range_check_b_hi = Gate::three_bit_range(b_hi.clone());
-
-        let word_check = c.clone()
-            + b_lo.clone() * F::from(1 << 16)
-            + b_hi.clone() * F::from(1 << 19)
-            + a.clone() * F::from(1 << 22)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_10_word_check = a
-            + c * F::from(1 << 10)
-            + b_lo * F::from(1 << 26)
-            + b_hi * F::from(1 << 29)
-            + rol_10_word_lo * (-F::one())
-            + rol_10_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_10,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
-                .chain(range_check_b_lo)
-                .chain(range_check_b_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_10_word_check", rol_10_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 11)
-    // word = (a,b,c) = (11, 5, 16) chunks with b = (b_hi, b_lo) = (3, 2) chunks
-    pub fn rotate_left_11_gate(
-        s_rotate_left_11: Expression<F>,
-        a: Expression<F>,
-        tag_a: Expression<F>,
-        b_lo: Expression<F>,
-        b_hi: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_11_word_lo: Expression<F>,
-        rol_11_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 3); // tag <= 3 => a < 2^11
-        let range_check_b_lo = Gate::two_bit_range(b_lo.clone());
-        let range_check_b_hi = Gate::three_bit_range(b_hi.clone());
-
-        let word_check = c.clone()
-            + b_lo.clone() * F::from(1 << 16)
-            + b_hi.clone() * F::from(1 << 18)
-            + a.clone() * F::from(1 << 21)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_11_word_check = a
-            + c * F::from(1 << 11)
-            + b_lo * F::from(1 << 27)
-            + b_hi * F::from(1 << 29)
-            + rol_11_word_lo * (-F::one())
-            + rol_11_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_11,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
-                .chain(range_check_b_lo)
-                .chain(range_check_b_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_11_word_check", rol_11_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 12)
-    // word = (a,b,c) = (12, 4, 16) chunks with b = (b_hi, b_lo) = (2, 2) chunks
-    pub fn rotate_left_12_gate(
-        s_rotate_left_12: Expression<F>,
-        a: Expression<F>,
-        tag_a: Expression<F>,
-        b_lo: Expression<F>,
-        b_hi: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_12_word_lo: Expression<F>,
-        rol_12_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 4); // tag <= 4 => a < 2^12
-        let range_check_b_lo = Gate::two_bit_range(b_lo.clone());
-        let range_check_b_hi = Gate::two_bit_range(b_hi.clone());
-
-        let word_check = c.clone()
-            + b_lo.clone() * F::from(1 << 16)
-            + b_hi.clone() * F::from(1 << 18)
-            + a.clone() * F::from(1 << 20)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_12_word_check = a
-            + c * F::from(1 << 12)
-            + b_lo * F::from(1 << 28)
-            + b_hi * F::from(1 << 30)
-            + rol_12_word_lo * (-F::one())
-            + rol_12_word_hi * F::from(1 << 16) * (-F::one());
-
-        Constraints::with_selector(
-            s_rotate_left_12,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
-                .chain(range_check_b_lo)
-                .chain(range_check_b_hi)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_12_word_check", rol_12_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 13)
-    // word = (a,b,c) = (13, 3, 16) chunks
-    pub fn rotate_left_13_gate(
-        s_rotate_left_13: Expression<F>,
+    // Gate for rotate_left(W, shift) with 0 < shift < 32 and s = shift % 16
+    // The split half H of W is word_hi if shift < 16 and word_lo otherwise (swap = 1), and
+    // L is the other half. With H = (b, a) = (16 - s, s) chunks,
+    // rotate_left(W, shift) = a + L * 2^s + b * 2^(16 + s)
+    pub fn rotate_left_gate(
+        s_rotate_left: Expression<F>,
+        coef_a: Expression<F>,
+        coef_b: Expression<F>,
+        swap: Expression<F>,
         a: Expression<F>,
-        tag_a: Expression<F>,
         b: Expression<F>,
-        c: Expression<F>,
+        a_shifted: Expression<F>,
+        b_shifted: Expression<F>,
         word_lo: Expression<F>,
         word_hi: Expression<F>,
-        rol_13_word_lo: Expression<F>,
-        rol_13_word_hi: Expression<F>,
+        rol_word_lo: Expression<F>,
+        rol_word_hi: Expression<F>,
     ) -> Constraints<
         F,
         (&'static str, Expression<F>),
         impl Iterator<Item = (&'static str, Expression<F>)>,
     > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 5); // tag <= 5 => a < 2^13
-        let range_check_b = Gate::three_bit_range(b.clone());
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a.clone() * F::from(1 << 19)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_13_word_check = a
-            + c * F::from(1 << 13)
-            + b * F::from(1 << 29)
-            + rol_13_word_lo * (-F::one())
-            + rol_13_word_hi * F::from(1 << 16) * (-F::one());
+        // Note: a, b, a * 2^(16 - s) and b * 2^s are all constrained to be 16 bits by the
+        // lookup table, which gives a < 2^s and b < 2^(16 - s)
+        let range_check_a = a_shifted - a.clone() * coef_a.clone();
+        let range_check_b = b_shifted - b.clone() * coef_b.clone();
 
-        Constraints::with_selector(
-            s_rotate_left_13,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
-                .chain(range_check_b)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_13_word_check", rol_13_word_check))),
-        )
-    }
+        let split_half = word_hi.clone() + swap.clone() * (word_lo.clone() - word_hi.clone());
+        let other_half = word_lo.clone() + swap * (word_hi - word_lo);
 
-    // Gate for rotate_left(W, 14)
-    // word = (a,b,c) = (14, 2, 16) chunks
-    pub fn rotate_left_14_gate(
-        s_rotate_left_14: Expression<F>,
-        a: Expression<F>,
-        tag_a: Expression<F>,
-        b: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_14_word_lo: Expression<F>,
-        rol_14_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 6); // tag <= 6 => a < 2^14
-        let range_check_b = Gate::two_bit_range(b.clone());
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a.clone() * F::from(1 << 18)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_14_word_check = a
-            + c * F::from(1 << 14)
-            + b * F::from(1 << 30)
-            + rol_14_word_lo * (-F::one())
-            + rol_14_word_hi * F::from(1 << 16) * (-F::one());
+        let word_check = b.clone() + a.clone() * coef_a + split_half * (-F::one());
 
-        Constraints::with_selector(
-            s_rotate_left_14,
-            std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
-                .chain(range_check_b)
-                .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_14_word_check", rol_14_word_check))),
-        )
-    }
-
-    // Gate for rotate_left(W, 14)
-    // word = (a,b,c) = (15, 1, 16) chunks
-    pub fn rotate_left_15_gate(
-        s_rotate_left_15: Expression<F>,
-        a: Expression<F>,
-        tag_a: Expression<F>,
-        b: Expression<F>,
-        c: Expression<F>,
-        word_lo: Expression<F>,
-        word_hi: Expression<F>,
-        rol_15_word_lo: Expression<F>,
-        rol_15_word_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        // Note: There is no need to check the tag of c as it will be constrained to be 16 bits
-        // by the lookup table
-        let range_check_tag_a = Gate::range_check(tag_a, 0, 7); // tag <= 7 => a < 2^15
-        let range_check_b = Gate::range_check(b.clone(), 0, 1);
-
-        let word_check = c.clone()
-            + b.clone() * F::from(1 << 16)
-            + a.clone() * F::from(1 << 17)
-            + word_lo * (-F::one())
-            + word_hi * F::from(1 << 16) * (-F::one());
-
-        let rol_15_word_check = a
-            + c * F::from(1 << 15)
-            + b * F::from(1 << 31)
-            + rol_15_word_lo * (-F::one())
-            + rol_15_word_hi * F::from(1 << 16) * (-F::one());
+        let rol_word_check = a
+            + other_half * coef_b.clone()
+            + b * coef_b * F::from(1 << 16)
+            + rol_word_lo * (-F::one())
+            + rol_word_hi * F::from(1 << 16) * (-F::one());
 
         Constraints::with_selector(
-            s_rotate_left_15,
+            s_rotate_left,
             std::iter::empty()
-                .chain(Some(("range_check_tag_a", range_check_tag_a)))
+                .chain(Some(("range_check_a", range_check_a)))
                 .chain(Some(("range_check_b", range_check_b)))
                 .chain(Some(("word_check", word_check)))
-                .chain(Some(("rol_15_word_check", rol_15_word_check))),
+                .chain(Some(("rol_word_check", rol_word_check))),
         )
     }
 
-    // Gate for  A + f(j, B, C, D) + X[r[j]] + K[j]  where r is the rotate amount array
-    pub fn sum_afxk_gate(
-        s_sum_afxk: Expression<F>,
+    // Gate for one RIPEMD-160 step with 0 < shift < 16
+    //   sum = A + f(j, B, C, D) + X[r[j]] + K[j]
+    //   T = rotate_left(sum, shift) + E
+    // together with rotate_left(T, 10), which is needed as D two steps later.
+    // With sum_hi = (b, a) = (16 - s, s) bit chunks,
+    //   rotate_left(sum, s) = a + sum_lo * 2^s + b * 2^(16 + s)
+    // and with t_hi = (r_b, r_a) = (6, 10) bit chunks,
+    //   rotate_left(T, 10) = r_a + t_lo * 2^10 + r_b * 2^26
+    pub fn step_gate(
+        s_step: Expression<F>,
+        coef_a: Expression<F>,
+        coef_b: Expression<F>,
+        k: Expression<F>,
         sum_lo: Expression<F>,
-        sum_hi: Expression<F>,
-        carry: Expression<F>,
+        sum_a: Expression<F>,
+        sum_b: Expression<F>,
+        sum_a_shifted: Expression<F>,
+        sum_b_shifted: Expression<F>,
+        carry_sum: Expression<F>,
         a_lo: Expression<F>,
         a_hi: Expression<F>,
         f_lo: Expression<F>,
         f_hi: Expression<F>,
         x_lo: Expression<F>,
         x_hi: Expression<F>,
-        k_lo: Expression<F>,
-        k_hi: Expression<F>,
-    ) -> Constraints<
-        F,
-        (&'static str, Expression<F>),
-        impl Iterator<Item = (&'static str, Expression<F>)>,
-    > {
-        let range_check_carry = Gate::range_check(carry.clone(), 0, 2);
-
-        let lo = a_lo + f_lo + x_lo + k_lo;
-        let hi = a_hi + f_hi + x_hi + k_hi;
-        let sum = lo + hi * F::from(1 << 16);
-        let mod_sum = sum_lo + sum_hi * F::from(1 << 16);
-
-        let sum_check = sum - (carry * F::from(1 << 32)) - mod_sum;
-
-        Constraints::with_selector(
-            s_sum_afxk,
-            std::iter::empty()
-                .chain(Some(("range_check_carry", range_check_carry)))
-                .chain(Some(("sum_afxk", sum_check))),
-        )
-    }
-
-    // Gate for T = rol + E  where rol is
-    // the rotated version of A + f(j, B,C,D) + X[r[j]] + K[j]
-    pub fn sum_re_gate(
-        s_sum_re: Expression<F>,
-        sum_lo: Expression<F>,
-        sum_hi: Expression<F>,
-        carry: Expression<F>,
-        rol_lo: Expression<F>,
-        rol_hi: Expression<F>,
         e_lo: Expression<F>,
         e_hi: Expression<F>,
+        t_lo: Expression<F>,
+        t_hi: Expression<F>,
+        carry_t: Expression<F>,
+        tag_r_a: Expression<F>,
+        r_a: Expression<F>,
+        r_b: Expression<F>,
+        r_b_shifted: Expression<F>,
+        rol10_t_lo: Expression<F>,
+        rol10_t_hi: Expression<F>,
     ) -> Constraints<
         F,
         (&'static str, Expression<F>),
         impl Iterator<Item = (&'static str, Expression<F>)>,
     > {
-        let range_check_carry = Gate::range_check(carry.clone(), 0, 1);
-
-        let lo = rol_lo + e_lo;
-        let hi = rol_hi + e_hi;
-        let sum = lo + hi * F::from(1 << 16);
-        let mod_sum = sum_lo + sum_hi * F::from(1 << 16);
-
-        let sum_check = sum - (carry * F::from(1 << 32)) - mod_sum;
+        // Note: sum_lo, sum_a, sum_b, sum_a * 2^(16 - s), sum_b * 2^s, t_lo, t_hi, r_a, r_b,
+        // r_b * 2^10 and rol10_t_lo, rol10_t_hi are all constrained to be 16 bits by the
+        // lookup table, which gives sum_a < 2^s, sum_b < 2^(16 - s) and r_b < 2^6.
+        // r_a < 2^10 follows from its tag.
+        let range_check_sum_a = sum_a_shifted - sum_a.clone() * coef_a.clone();
+        let range_check_sum_b = sum_b_shifted - sum_b.clone() * coef_b.clone();
+        let range_check_carry_sum = Gate::range_check(carry_sum.clone(), 0, 3);
+        let range_check_carry_t = Gate::range_check(carry_t.clone(), 0, 1);
+        let range_check_tag_r_a = Gate::range_check(tag_r_a, 0, 2);
+        let range_check_r_b = r_b_shifted - r_b.clone() * F::from(1 << 10);
+
+        let lo = a_lo + f_lo + x_lo;
+        let hi = a_hi + f_hi + x_hi;
+        let sum_hi = sum_b.clone() + sum_a.clone() * coef_a;
+        let sum_check = lo + hi * F::from(1 << 16) + k
+            - carry_sum * F::from(1 << 32)
+            - sum_lo.clone()
+            - sum_hi * F::from(1 << 16);
+
+        let rol = sum_a + sum_lo * coef_b.clone() + sum_b * coef_b * F::from(1 << 16);
+        let t_check = rol + e_lo + e_hi * F::from(1 << 16)
+            - carry_t * F::from(1 << 32)
+            - t_lo.clone()
+            - t_hi.clone() * F::from(1 << 16);
+
+        let t_hi_check = r_b.clone() + r_a.clone() * F::from(1 << 6) - t_hi;
+        let rol10_t_check = r_a + t_lo * F::from(1 << 10) + r_b * F::from(1 << 26)
+            - rol10_t_lo
+            - rol10_t_hi * F::from(1 << 16);
 
         Constraints::with_selector(
-            s_sum_re,
+            s_step,
             std::iter::empty()
-                .chain(Some(("range_check_carry", range_check_carry)))
-                .chain(Some(("sum_re", sum_check))),
+                .chain(Some(("range_check_sum_a", range_check_sum_a)))
+                .chain(Some(("range_check_sum_b", range_check_sum_b)))
+                .chain(Some(("range_check_carry_sum", range_check_carry_sum)))
+                .chain(Some(("range_check_carry_t", range_check_carry_t)))
+                .chain(Some(("range_check_tag_r_a", range_check_tag_r_a)))
+                .chain(Some(("range_check_r_b", range_check_r_b)))
+                .chain(Some(("sum_afxk", sum_check)))
+                .chain(Some(("t", t_check)))
+                .chain(Some(("t_hi", t_hi_check)))
+                .chain(Some(("rol10_t", rol10_t_check))),
         )
     }
 
@@ -851,7 +394,8 @@ impl<F: FieldExt> CompressionGate<F> {
         (&'static str, Expression<F>),
         impl Iterator<Item = (&'static str, Expression<F>)>,
     > {
-        let range_check_carry = Gate::range_check(carry.clone(), 0, 1);
+        // Three words are added, so the carry can be 2
+        let range_check_carry = Gate::range_check(carry.clone(), 0, 2);
 
         let lo = init_state_lo + left_state_lo + right_state_lo;
         let hi = init_state_hi + left_state_hi + right_state_hi;
@@ -879,9 +423,12 @@ mod tests {
     use rand::Rng;
 
     use crate::native::*;
+    use crate::table16::compression::witness::{
+        F1Witness, F2F4Witness, F3F5Witness, RotateLeftWitness, StepWitness, SumCombineWitness,
+    };
     use crate::table16::compression::{CompressionConfig, RoundWordDense};
-    use crate::table16::spread_table::{SpreadTableChip, SpreadTableConfig};
     use crate::table16::Table16Assignment;
+    use bitwise_gadgets::{SpreadTableChip, SpreadTableConfig};
 
     #[derive(Debug, Clone)]
     struct CompressionGateTesterConfig<F: FieldExt> {
@@ -899,20 +446,9 @@ mod tests {
         pub f3_bcd: u32,
         pub f4_bcd: u32,
         pub f5_bcd: u32,
-        pub rol_5_b: u32,
-        pub rol_6_b: u32,
-        pub rol_7_b: u32,
-        pub rol_8_b: u32,
-        pub rol_9_b: u32,
-        pub rol_10_b: u32,
-        pub rol_11_b: u32,
-        pub rol_12_b: u32,
-        pub rol_13_b: u32,
-        pub rol_14_b: u32,
-        pub rol_15_b: u32,
-        pub sum_bc: u32,
+        pub rol_b: [u32; 31], // rotate_left(b, shift) for shifts from 1 to 31
+        pub step_t: [(u32, u32); 15], // (T, rotate_left(T, 10)) for shifts from 1 to 15
         pub sum_bcd: u32,
-        pub sum_bcdk: u32,
     }
 
     impl Circuit<Fp> for CompressionGateTester {
@@ -930,20 +466,9 @@ mod tests {
                 f3_bcd: 0,
                 f4_bcd: 0,
                 f5_bcd: 0,
-                rol_5_b: 0,
-                rol_6_b: 0,
-                rol_7_b: 0,
-                rol_8_b: 0,
-                rol_9_b: 0,
-                rol_10_b: 0,
-                rol_11_b: 0,
-                rol_12_b: 0,
-                rol_13_b: 0,
-                rol_14_b: 0,
-                rol_15_b: 0,
-                sum_bc: 0,
+                rol_b: [0; 31],
+                step_t: [(0, 0); 15],
                 sum_bcd: 0,
-                sum_bcdk: 0,
             }
         }
 
@@ -1048,6 +573,7 @@ mod tests {
                         spread_halves_b.clone().into(),
                         spread_halves_c.clone().into(),
                         spread_halves_d.clone().into(),
+                        Value::known(&F1Witness::new(self.b, self.c, self.d)),
                     )?;
                     row += 6; // f1 requires 6 rows
 
@@ -1069,6 +595,7 @@ mod tests {
                         spread_halves_b.clone().into(),
                         spread_halves_c.clone().into(),
                         spread_halves_d.clone().into(),
+                        Value::known(&F2F4Witness::new(self.b, self.c, self.d)),
                     )?;
                     row += 11; // f2 requires eleven rows
 
@@ -1090,6 +617,7 @@ mod tests {
                         spread_halves_b.clone().into(),
                         spread_halves_c.clone().into(),
                         spread_halves_d.clone().into(),
+                        Value::known(&F2F4Witness::new(self.d, self.b, self.c)),
                     )?;
                     row += 11; // f4 requires eleven rows
 
@@ -1111,6 +639,7 @@ mod tests {
                         spread_halves_b.clone().into(),
                         spread_halves_c.clone().into(),
                         spread_halves_d.clone().into(),
+                        Value::known(&F3F5Witness::new(self.b, self.c, self.d)),
                     )?;
                     row += 10; // f3 requires ten rows
 
@@ -1132,6 +661,7 @@ mod tests {
                         spread_halves_b.clone().into(),
                         spread_halves_c.clone().into(),
                         spread_halves_d.clone().into(),
+                        Value::known(&F3F5Witness::new(self.c, self.d, self.b)),
                     )?;
                     row += 10; // f5 requires ten rows
 
@@ -1146,231 +676,34 @@ mod tests {
                     row += 3;
 
                     // row = 72
-                    // Testing rotate_left_5 gate
+                    // Testing rotate_left gate for all shifts from 1 to 31
                     let b_round_word_dense = RoundWordDense(
                         spread_b_var_lo.clone().dense,
                         spread_b_var_hi.clone().dense,
                     );
-                    let rol_5_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        5,
-                    )?;
-                    row += 6; // rotate_left_5 requires six rows
-
-                    // row = 78
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_5_b.0,
-                        rol_5_b.1,
-                        Value::known(self.rol_5_b),
-                    )?;
-                    row += 3;
-
-                    // row = 81
-                    // Testing rotate_left_6 gate
-                    let rol_6_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        6,
-                    )?;
-                    row += 6; // rotate_left_6 requires six rows
-
-                    // row = 87
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_6_b.0,
-                        rol_6_b.1,
-                        Value::known(self.rol_6_b),
-                    )?;
-                    row += 3;
-
-                    // row = 90
-                    // Testing rotate_left_7 gate
-                    let rol_7_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        7,
-                    )?;
-                    row += 6; // rotate_left_7 requires six rows
-
-                    // row = 96
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_7_b.0,
-                        rol_7_b.1,
-                        Value::known(self.rol_7_b),
-                    )?;
-                    row += 3;
-
-                    // row = 99
-                    // Testing rotate_left_8 gate
-                    let rol_8_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        8,
-                    )?;
-                    row += 6; // rotate_left_8 requires six rows
-
-                    // row = 105
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_8_b.0,
-                        rol_8_b.1,
-                        Value::known(self.rol_8_b),
-                    )?;
-                    row += 3;
-
-                    // row = 108
-                    // Testing rotate_left_9 gate
-                    let rol_9_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        9,
-                    )?;
-                    row += 6; // rotate_left_9 requires six rows
-
-                    // row = 114
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_9_b.0,
-                        rol_9_b.1,
-                        Value::known(self.rol_9_b),
-                    )?;
-                    row += 3;
-
-                    // row = 117
-                    // Testing rotate_left_10 gate
-                    let rol_10_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        10,
-                    )?;
-                    row += 6; // rotate_left_10 requires six rows
-
-                    // row = 123
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_10_b.0,
-                        rol_10_b.1,
-                        Value::known(self.rol_10_b),
-                    )?;
-                    row += 3;
-
-                    // row = 126
-                    // Testing rotate_left_11 gate
-                    let rol_11_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        11,
-                    )?;
-                    row += 6; // rotate_left_11 requires six rows
-
-                    // row = 132
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_11_b.0,
-                        rol_11_b.1,
-                        Value::known(self.rol_11_b),
-                    )?;
-                    row += 3;
-
-                    // row = 135
-                    // Testing rotate_left_12 gate
-                    let rol_12_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        12,
-                    )?;
-                    row += 6; // rotate_left_12 requires six rows
-
-                    // row = 141
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_12_b.0,
-                        rol_12_b.1,
-                        Value::known(self.rol_12_b),
-                    )?;
-                    row += 3;
-
-                    // row = 144
-                    // Testing rotate_left_13 gate
-                    let rol_13_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        13,
-                    )?;
-                    row += 6; // rotate_left_13 requires six rows
-
-                    // row = 150
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_13_b.0,
-                        rol_13_b.1,
-                        Value::known(self.rol_13_b),
-                    )?;
-                    row += 3;
-
-                    // row = 153
-                    // Testing rotate_left_14 gate
-                    let rol_14_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        14,
-                    )?;
-                    row += 6; // rotate_left_14 requires six rows
-
-                    // row = 159
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_14_b.0,
-                        rol_14_b.1,
-                        Value::known(self.rol_14_b),
-                    )?;
-                    row += 3;
-
-                    // row = 162
-                    // Testing rotate_left_15 gate
-                    let rol_15_b = config.compression.assign_rotate_left(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        15,
-                    )?;
-                    row += 6; // rotate_left_15 requires six rows
+                    for (idx, rol_b) in self.rol_b.iter().enumerate() {
+                        let rol_shift_b = config.compression.assign_rotate_left(
+                            &mut region,
+                            row,
+                            b_round_word_dense.clone(),
+                            idx as u8 + 1,
+                            Value::known(&RotateLeftWitness::new(self.b, idx as u8 + 1)),
+                        )?;
+                        row += 4; // rotate_left requires four rows
 
-                    // row = 168
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        rol_15_b.0,
-                        rol_15_b.1,
-                        Value::known(self.rol_15_b),
-                    )?;
-                    row += 3;
+                        config.compression.assign_decompose_word(
+                            &mut region,
+                            row,
+                            rol_shift_b.0,
+                            rol_shift_b.1,
+                            Value::known(*rol_b),
+                        )?;
+                        row += 3;
+                    }
 
-                    // row = 171
-                    // Testing sum_afxk_gate
+                    // row = 289
+                    // Testing step gate with (A, F, X, E) = (b, c, d, c) for all shifts
+                    // from 1 to 15
                     let c_round_word_dense = RoundWordDense(
                         spread_c_var_lo.clone().dense,
                         spread_c_var_hi.clone().dense,
@@ -1379,58 +712,59 @@ mod tests {
                         spread_d_var_lo.clone().dense,
                         spread_d_var_hi.clone().dense,
                     );
-                    let sum_dense = config.compression.assign_sum_afxk(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        c_round_word_dense.clone(),
-                        d_round_word_dense.clone(),
-                        self.k,
-                    )?;
-                    row += 9; // sum_afxk_gate requires nine rows
-
-                    // row = 180
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        sum_dense.0,
-                        sum_dense.1,
-                        Value::known(self.sum_bcdk),
-                    )?;
-                    row += 3;
+                    for (idx, (t, rol10_t)) in self.step_t.iter().enumerate() {
+                        let (t_word, rol10_t_word) = config.compression.assign_step(
+                            &mut region,
+                            row,
+                            b_round_word_dense.clone(),
+                            c_round_word_dense.clone(),
+                            d_round_word_dense.clone(),
+                            c_round_word_dense.clone(),
+                            self.k,
+                            idx as u8 + 1,
+                            Value::known(&StepWitness::new(
+                                self.b,
+                                self.c,
+                                self.d,
+                                self.c,
+                                self.k,
+                                idx as u8 + 1,
+                            )),
+                        )?;
+                        row += 12; // step requires twelve rows
 
-                    // row = 183
-                    // Testing sum_re_gate
-                    let sum = config.compression.assign_sum_re(
-                        &mut region,
-                        row,
-                        b_round_word_dense.clone(),
-                        c_round_word_dense.clone(),
-                    )?;
-                    row += 5; // sum_re_gate requires five rows
+                        config.compression.assign_decompose_word(
+                            &mut region,
+                            row,
+                            t_word.dense_halves.0,
+                            t_word.dense_halves.1,
+                            Value::known(*t),
+                        )?;
+                        row += 3;
 
-                    // row = 188
-                    config.compression.assign_decompose_word(
-                        &mut region,
-                        row,
-                        sum.dense_halves.0,
-                        sum.dense_halves.1,
-                        Value::known(self.sum_bc),
-                    )?;
-                    row += 3;
+                        config.compression.assign_decompose_word(
+                            &mut region,
+                            row,
+                            rol10_t_word.dense_halves.0,
+                            rol10_t_word.dense_halves.1,
+                            Value::known(*rol10_t),
+                        )?;
+                        row += 3;
+                    }
 
-                    // row = 191
-                    // Testing sum_re_gate
+                    // row = 559
+                    // Testing sum_combine_ilr_gate
                     let sum = config.compression.assign_sum_combine_ilr(
                         &mut region,
                         row,
                         b_round_word_dense.clone(),
                         c_round_word_dense.clone(),
-                        d_round_word_dense.clone(),
+                        Some(d_round_word_dense.clone()),
+                        Value::known(&SumCombineWitness::new(self.b, self.c, self.d)),
                     )?;
                     row += 7; // sum_combine_ilr_gate requires seven rows
 
-                    // row = 198
+                    // row = 566
                     config.compression.assign_decompose_word(
                         &mut region,
                         row,
@@ -1458,20 +792,19 @@ mod tests {
         let f3_bcd: u32 = f3(b, c, d);
         let f4_bcd: u32 = f4(b, c, d);
         let f5_bcd: u32 = f5(b, c, d);
-        let rol_5_b: u32 = rol(b, 5);
-        let rol_6_b: u32 = rol(b, 6);
-        let rol_7_b: u32 = rol(b, 7);
-        let rol_8_b: u32 = rol(b, 8);
-        let rol_9_b: u32 = rol(b, 9);
-        let rol_10_b: u32 = rol(b, 10);
-        let rol_11_b: u32 = rol(b, 11);
-        let rol_12_b: u32 = rol(b, 12);
-        let rol_13_b: u32 = rol(b, 13);
-        let rol_14_b: u32 = rol(b, 14);
-        let rol_15_b: u32 = rol(b, 15);
-        let sum_bc = b.overflowing_add(c).0;
-        let sum_bcd = sum_bc.overflowing_add(d).0;
-        let sum_bcdk = sum_bcd.overflowing_add(k).0;
+        let mut rol_b = [0_u32; 31];
+        for (idx, rol_shift_b) in rol_b.iter_mut().enumerate() {
+            *rol_shift_b = b.rotate_left(idx as u32 + 1);
+        }
+        let sum_bcd = b.wrapping_add(c).wrapping_add(d);
+        let mut step_t = [(0_u32, 0_u32); 15];
+        for (idx, (t, rol10_t)) in step_t.iter_mut().enumerate() {
+            *t = sum_bcd
+                .wrapping_add(k)
+                .rotate_left(idx as u32 + 1)
+                .wrapping_add(c);
+            *rol10_t = t.rotate_left(10);
+        }
 
         let circuit = CompressionGateTester {
             b,
@@ -1483,20 +816,9 @@ mod tests {
             f3_bcd,
             f4_bcd,
             f5_bcd,
-            rol_5_b,
-            rol_6_b,
-            rol_7_b,
-            rol_8_b,
-            rol_9_b,
-            rol_10_b,
-            rol_11_b,
-            rol_12_b,
-            rol_13_b,
-            rol_14_b,
-            rol_15_b,
-            sum_bc,
+            rol_b,
+            step_t,
             sum_bcd,
-            sum_bcdk,
         };
 
         let prover = MockProver::run(17, &circuit, vec![]).unwrap();
@@ -1,8 +1,9 @@
+use halo2_proofs::circuit::Region;
 use halo2_proofs::halo2curves::FieldExt;
-use halo2_proofs::{circuit::Region, plonk::Error};
 
 use crate::table16::compression::compression_util::*;
-use crate::{constants::DIGEST_SIZE, table16::BlockWord};
+use crate::table16::message_schedule::DECOMPOSE_WORD_ROWS;
+use crate::{constants::DIGEST_SIZE, table16::AssignedBits, Error};
 
 use super::{CompressionConfig, State};
 
@@ -11,26 +12,21 @@ impl<F: FieldExt> CompressionConfig<F> {
         &self,
         region: &mut Region<'_, F>,
         state: State<F>,
-    ) -> Result<[BlockWord; DIGEST_SIZE], Error> {
+        row: &mut usize,
+    ) -> Result<[AssignedBits<32, F>; DIGEST_SIZE], Error> {
         let (a, b, c, d, e) = match_state(state);
 
-        let mut row: usize = 0;
-        self.assign_decompose_word_dense(region, row, a.clone())?;
-        row += 3;
-        self.assign_decompose_word_dense(region, row, b.clone().dense_halves)?;
-        row += 3;
-        self.assign_decompose_word_dense(region, row, c.clone().dense_halves)?;
-        row += 3;
-        self.assign_decompose_word_dense(region, row, d.clone().dense_halves)?;
-        row += 3;
-        self.assign_decompose_word_dense(region, row, e.clone())?;
+        let a = self.assign_decompose_word_dense(region, *row, a)?;
+        *row += DECOMPOSE_WORD_ROWS;
+        let b = self.assign_decompose_word_dense(region, *row, b.dense_halves)?;
+        *row += DECOMPOSE_WORD_ROWS;
+        let c = self.assign_decompose_word_dense(region, *row, c.dense_halves)?;
+        *row += DECOMPOSE_WORD_ROWS;
+        let d = self.assign_decompose_word_dense(region, *row, d.dense_halves)?;
+        *row += DECOMPOSE_WORD_ROWS;
+        let e = self.assign_decompose_word_dense(region, *row, e)?;
+        *row += DECOMPOSE_WORD_ROWS;
 
-        Ok([
-            BlockWord(a.value()),
-            BlockWord(b.dense_halves.value()),
-            BlockWord(c.dense_halves.value()),
-            BlockWord(d.dense_halves.value()),
-            BlockWord(e.value()),
-        ])
+        Ok([a, b, c, d, e])
     }
 }
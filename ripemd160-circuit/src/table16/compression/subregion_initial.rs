@@ -1,97 +1,133 @@
+use halo2_proofs::circuit::{Region, Value};
 use halo2_proofs::halo2curves::FieldExt;
-use halo2_proofs::{
-    circuit::{Region, Value},
-    plonk::Error,
-};
 
-use crate::constants::DIGEST_SIZE;
-use crate::table16::Table16Assignment;
+use crate::constants::{BLOCK_SIZE, DIGEST_SIZE};
+use crate::table16::message_schedule::DECOMPOSE_WORD_ROWS;
+use crate::table16::{AssignedBits, BlockWord, Table16Assignment};
+use crate::Error;
 
 use super::{CompressionConfig, RoundWord, RoundWordDense, RoundWordSpread, State, StateWord};
 
 impl<F: FieldExt> CompressionConfig<F> {
+    /// Assigns the given IV, constraining it to be constant
     pub fn init_iv(
         &self,
         region: &mut Region<'_, F>,
         iv: [u32; DIGEST_SIZE],
+        row: &mut usize,
     ) -> Result<State<F>, Error> {
+        let (state, words) = self.assign_initial_state(region, iv.map(Value::known), row)?;
+        for (word, iv) in words.iter().zip(iv) {
+            region.constrain_constant(word.cell(), F::from(iv as u64))?;
+        }
+
+        Ok(state)
+    }
+
+    /// Assigns a copy of the given chaining state
+    pub fn init_state(
+        &self,
+        region: &mut Region<'_, F>,
+        state: &[AssignedBits<32, F>; DIGEST_SIZE],
+        row: &mut usize,
+    ) -> Result<State<F>, Error> {
+        let (new_state, words) =
+            self.assign_initial_state(region, state.clone().map(|word| word.value_u32()), row)?;
+        for (word, input) in words.iter().zip(state.iter()) {
+            region.constrain_equal(word.cell(), input.cell())?;
+        }
+
+        Ok(new_state)
+    }
+
+    /// Assigns a message block and looks up the halves of its words
+    pub fn assign_message_block(
+        &self,
+        region: &mut Region<'_, F>,
+        input: [BlockWord; BLOCK_SIZE],
+        row: &mut usize,
+    ) -> Result<
+        (
+            [AssignedBits<32, F>; BLOCK_SIZE],
+            [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
+        ),
+        Error,
+    > {
+        let mut w = Vec::with_capacity(BLOCK_SIZE);
+        let mut w_halves = Vec::with_capacity(BLOCK_SIZE);
+        for (word_idx, word) in input.iter().enumerate() {
+            self.s_decompose_word.enable(region, *row)?;
+            let (word, (lo, hi)) = self.assign_word_and_halves(
+                || format!("X_{}", word_idx),
+                region,
+                &self.lookup,
+                self.advice,
+                word.0,
+                *row,
+            )?;
+            w.push(word);
+            w_halves.push((lo.dense, hi.dense));
+            *row += DECOMPOSE_WORD_ROWS;
+        }
+
+        Ok((w.try_into().unwrap(), w_halves.try_into().unwrap()))
+    }
+
+    fn assign_initial_state(
+        &self,
+        region: &mut Region<'_, F>,
+        iv: [Value<u32>; DIGEST_SIZE],
+        row: &mut usize,
+    ) -> Result<(State<F>, [AssignedBits<32, F>; DIGEST_SIZE]), Error> {
         let a_3 = self.advice;
 
-        let mut row: usize = 0;
-        self.s_decompose_word.enable(region, row)?;
-        let (_, (a_lo, a_hi)) = self.assign_word_and_halves(
-            || "assign iv[0]",
-            region,
-            &self.lookup,
-            a_3,
-            Value::known(iv[0]),
-            row,
-        )?;
+        self.s_decompose_word.enable(region, *row)?;
+        let (a_word, (a_lo, a_hi)) =
+            self.assign_word_and_halves(|| "assign iv[0]", region, &self.lookup, a_3, iv[0], *row)?;
         let a = RoundWordDense(a_lo.dense, a_hi.dense);
 
-        row += 3;
-        self.s_decompose_word.enable(region, row)?;
-        let (_, (b_lo, b_hi)) = self.assign_word_and_halves(
-            || "assign iv[1]",
-            region,
-            &self.lookup,
-            a_3,
-            Value::known(iv[1]),
-            row,
-        )?;
+        *row += DECOMPOSE_WORD_ROWS;
+        self.s_decompose_word.enable(region, *row)?;
+        let (b_word, (b_lo, b_hi)) =
+            self.assign_word_and_halves(|| "assign iv[1]", region, &self.lookup, a_3, iv[1], *row)?;
         let b = RoundWord {
             dense_halves: RoundWordDense(b_lo.dense, b_hi.dense),
             spread_halves: RoundWordSpread(b_lo.spread, b_hi.spread),
         };
 
-        row += 3;
-        self.s_decompose_word.enable(region, row)?;
-        let (_, (c_lo, c_hi)) = self.assign_word_and_halves(
-            || "assign iv[2]",
-            region,
-            &self.lookup,
-            a_3,
-            Value::known(iv[2]),
-            row,
-        )?;
+        *row += DECOMPOSE_WORD_ROWS;
+        self.s_decompose_word.enable(region, *row)?;
+        let (c_word, (c_lo, c_hi)) =
+            self.assign_word_and_halves(|| "assign iv[2]", region, &self.lookup, a_3, iv[2], *row)?;
         let c = RoundWord {
             dense_halves: RoundWordDense(c_lo.dense, c_hi.dense),
             spread_halves: RoundWordSpread(c_lo.spread, c_hi.spread),
         };
 
-        row += 3;
-        self.s_decompose_word.enable(region, row)?;
-        let (_, (d_lo, d_hi)) = self.assign_word_and_halves(
-            || "assign iv[3]",
-            region,
-            &self.lookup,
-            a_3,
-            Value::known(iv[3]),
-            row,
-        )?;
+        *row += DECOMPOSE_WORD_ROWS;
+        self.s_decompose_word.enable(region, *row)?;
+        let (d_word, (d_lo, d_hi)) =
+            self.assign_word_and_halves(|| "assign iv[3]", region, &self.lookup, a_3, iv[3], *row)?;
         let d = RoundWord {
             dense_halves: RoundWordDense(d_lo.dense, d_hi.dense),
             spread_halves: RoundWordSpread(d_lo.spread, d_hi.spread),
         };
 
-        row += 3;
-        self.s_decompose_word.enable(region, row)?;
-        let (_, (e_lo, e_hi)) = self.assign_word_and_halves(
-            || "assign iv[4]",
-            region,
-            &self.lookup,
-            a_3,
-            Value::known(iv[4]),
-            row,
-        )?;
+        *row += DECOMPOSE_WORD_ROWS;
+        self.s_decompose_word.enable(region, *row)?;
+        let (e_word, (e_lo, e_hi)) =
+            self.assign_word_and_halves(|| "assign iv[4]", region, &self.lookup, a_3, iv[4], *row)?;
         let e = RoundWordDense(e_lo.dense, e_hi.dense);
+        *row += DECOMPOSE_WORD_ROWS;
 
-        Ok(State::new(
+        let state = State::new(
             StateWord::A(a),
             StateWord::B(b),
             StateWord::C(c),
             StateWord::D(d),
             StateWord::E(e),
-        ))
+        );
+
+        Ok((state, [a_word, b_word, c_word, d_word, e_word]))
     }
 }
@@ -1,76 +1,89 @@
+use halo2_proofs::circuit::{Region, Value};
 use halo2_proofs::halo2curves::FieldExt;
-use halo2_proofs::{circuit::Region, plonk::Error};
 
-use super::RoundSide::{self, Left, Right};
-use super::{CompressionConfig, RoundWordDense, State, StateWord};
+use super::witness::{RoundWitness, SumCombineWitness};
+use super::RoundSide::{self, Left};
+use super::{
+    CompressionConfig, RoundWordDense, State, StateWord, F1_ROWS, F2F4_ROWS, F3F5_ROWS,
+    ROTATE_LEFT_ROWS, SPREAD_WORD_ROWS, STEP_ROWS, SUM_COMBINE_ILR_ROWS,
+};
 use crate::constants::{
-    BLOCK_SIZE, MSG_SEL_IDX_LEFT, MSG_SEL_IDX_RIGHT, ROL_AMOUNT_LEFT, ROL_AMOUNT_RIGHT,
-    ROUND_CONSTANTS_LEFT, ROUND_CONSTANTS_RIGHT, ROUND_PHASE_SIZE,
+    BLOCK_SIZE, DIGEST_SIZE, MSG_SEL_IDX_LEFT, MSG_SEL_IDX_RIGHT, ROL_AMOUNT_LEFT,
+    ROL_AMOUNT_RIGHT, ROUND_PHASE_SIZE,
 };
 use crate::table16::compression::compression_util::*;
 use crate::table16::AssignedBits;
+use crate::{Error, RipemdVariant};
 
 impl<F: FieldExt> CompressionConfig<F> {
     pub fn assign_round(
         &self,
         region: &mut Region<'_, F>,
+        variant: RipemdVariant,
         round_idx: usize,
         state: State<F>,
         message_word_halves: [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
         row: &mut usize,
         round_side: RoundSide,
+        witness: Value<&RoundWitness>,
     ) -> Result<State<F>, Error> {
+        let (rol10_b, rol10_c) = (state.rol10_b.clone(), state.rol10_c.clone());
         let (a, b, c, d, e) = match_state(state);
 
-        let phase_idx = 1 + round_idx / ROUND_PHASE_SIZE;
+        let phase_idx = round_idx / ROUND_PHASE_SIZE;
+        let f_idx = if round_side == Left {
+            variant.round_function_left(phase_idx)
+        } else {
+            variant.round_function_right(phase_idx)
+        };
 
-        let fout = if (phase_idx == 1 && round_side == Left)
-            || (phase_idx == 5 && round_side == Right)
-        {
+        let fout = if f_idx == 1 {
             // f1(B, C, D)
             let f1_out = self.assign_f1(
                 region,
                 *row,
                 b.clone().spread_halves,
-                c.spread_halves,
-                d.spread_halves,
+                c.clone().spread_halves,
+                d.clone().spread_halves,
+                witness.map(|w| w.f.f1()),
             )?;
-            *row += 6; // f1 requires 6 rows
+            *row += F1_ROWS;
             f1_out
-        } else if (phase_idx == 2 && round_side == Left) || (phase_idx == 4 && round_side == Right)
-        {
+        } else if f_idx == 2 {
             // f2(B, C, D)
             let f2_out = self.assign_f2(
                 region,
                 *row,
                 b.clone().spread_halves,
-                c.spread_halves,
-                d.spread_halves,
+                c.clone().spread_halves,
+                d.clone().spread_halves,
+                witness.map(|w| w.f.f2f4()),
             )?;
-            *row += 11; // f2 requires 11 rows
+            *row += F2F4_ROWS;
             f2_out
-        } else if phase_idx == 3 {
+        } else if f_idx == 3 {
             // f3(B, C, D)
             let f3_out = self.assign_f3(
                 region,
                 *row,
                 b.clone().spread_halves,
-                c.spread_halves,
-                d.spread_halves,
+                c.clone().spread_halves,
+                d.clone().spread_halves,
+                witness.map(|w| w.f.f3f5()),
             )?;
-            *row += 10; // f3 requires 10 rows
+            *row += F3F5_ROWS;
             f3_out
-        } else if (phase_idx == 4 && round_side == Left) || (phase_idx == 2 && round_side == Right)
-        {
+        } else if f_idx == 4 {
             // f4(B, C, D)
             let f4_out = self.assign_f4(
                 region,
                 *row,
                 b.clone().spread_halves,
-                c.spread_halves,
-                d.spread_halves,
+                c.clone().spread_halves,
+                d.clone().spread_halves,
+                witness.map(|w| w.f.f2f4()),
             )?;
-            *row += 11; // f4 requires 11 rows
+            *row += F2F4_ROWS;
             f4_out
         } else {
             // f5(B, C, D)
@@ -78,10 +91,11 @@ impl<F: FieldExt> CompressionConfig<F> {
                 region,
                 *row,
                 b.clone().spread_halves,
-                c.spread_halves,
-                d.spread_halves,
+                c.clone().spread_halves,
+                d.clone().spread_halves,
+                witness.map(|w| w.f.f3f5()),
             )?;
-            *row += 10; // f5 requires 10 rows
+            *row += F3F5_ROWS;
             f5_out
         };
 
@@ -97,19 +111,11 @@ impl<F: FieldExt> CompressionConfig<F> {
                 message_word_halves[MSG_SEL_IDX_RIGHT[round_idx]].clone().1,
             )
         };
-        let sum_afxk = self.assign_sum_afxk(
-            region,
-            *row,
-            a,
-            fout.into(),
-            x,
-            if round_side == Left {
-                ROUND_CONSTANTS_LEFT[phase_idx - 1]
-            } else {
-                ROUND_CONSTANTS_RIGHT[phase_idx - 1]
-            },
-        )?;
-        *row += 9; // sum_afxk requires 9 rows
+        let k = if round_side == Left {
+            variant.round_constant_left(phase_idx)
+        } else {
+            variant.round_constant_right(phase_idx)
+        };
 
         // rol = rol_s(j) ( A + f1(B, C, D) + X[r(idx)] + K(idx / 16) )
         let rol_shift = if round_side == Left {
@@ -118,18 +124,57 @@ impl<F: FieldExt> CompressionConfig<F> {
             ROL_AMOUNT_RIGHT[round_idx]
         };
 
-        let rol = self.assign_rotate_left(region, *row, sum_afxk, rol_shift)?;
-        *row += 6; // rotate_left requires 6 rows
-
         // T = rol_s(j) ( A + f1(B,C,D) + X[r(idx)] + K(idx/16) ) + E
-        let t = self.assign_sum_re(region, *row, rol.into(), e.clone())?;
-        *row += 5; // sum_re requires 5 rows
+        let (t, rol10_t) = self.assign_step(
+            region,
+            *row,
+            a,
+            fout.into(),
+            x,
+            e.clone(),
+            k,
+            rol_shift,
+            witness.map(|w| &w.step),
+        )?;
+        *row += STEP_ROWS;
 
-        let rol10_c_dense = self.assign_rotate_left(region, *row, c.dense_halves, 10)?;
-        *row += 6; // rotate_left requires 6 rows
+        // The four-word lines have no E and no rotation of C, with E kept at zero
+        if variant.line_words() < DIGEST_SIZE {
+            return Ok(State::new(
+                StateWord::A(d.dense_halves),
+                StateWord::B(t),
+                StateWord::C(b),
+                StateWord::D(c),
+                StateWord::E(e),
+            ));
+        }
 
-        let rol10_c = self.assign_spread_dense_word(region, &self.lookup, *row, rol10_c_dense)?;
-        *row += 2; // getting the spread version of rol10_c requires 2 rows
+        // rol10(C) was computed two steps earlier as rol10(T), except for the initial words
+        let rol10_c = match rol10_c {
+            Some(rol10_c) => rol10_c,
+            None => {
+                let witness = witness.map(|w| w.rol10_c.as_ref().unwrap());
+                let rol10_c = self.assign_rotate_left(
+                    region,
+                    *row,
+                    c.dense_halves,
+                    10,
+                    witness.map(|(rol10_c, _)| rol10_c),
+                )?;
+                *row += ROTATE_LEFT_ROWS;
+
+                let rol10_c = self.assign_spread_dense_word(
+                    region,
+                    &self.lookup,
+                    *row,
+                    "rol10_c",
+                    rol10_c,
+                    witness.map(|(_, halves)| *halves),
+                )?;
+                *row += SPREAD_WORD_ROWS;
+                rol10_c
+            }
+        };
 
         Ok(State::new(
             StateWord::A(e),
@@ -137,49 +182,118 @@ impl<F: FieldExt> CompressionConfig<F> {
             StateWord::C(b),
             StateWord::D(rol10_c),
             StateWord::E(d.dense_halves),
-        ))
+        )
+        .with_rol10(Some(rol10_t), rol10_b))
     }
 
     pub fn assign_combine_ilr(
         &self,
         region: &mut Region<'_, F>,
+        variant: RipemdVariant,
         init_state: State<F>,
         left_state: State<F>,
         right_state: State<F>,
+        witness: Value<&[SumCombineWitness]>,
         row: &mut usize,
     ) -> Result<State<F>, Error> {
+        let word = |idx: usize| witness.map(|w| &w[idx]);
         let (h0, h1, h2, h3, h4) = match_state(init_state);
         let (a_left, b_left, c_left, d_left, e_left) = match_state(left_state);
         let (a_right, b_right, c_right, d_right, e_right) = match_state(right_state);
 
+        if variant.line_words() < DIGEST_SIZE {
+            // RIPEMD-128 has no E, which stays at zero
+            let a = self.assign_sum_combine_ilr(
+                region,
+                *row,
+                h1.dense_halves,
+                c_left.dense_halves,
+                Some(d_right.dense_halves),
+                word(0),
+            )?;
+            *row += SUM_COMBINE_ILR_ROWS;
+            let b = self.assign_sum_combine_ilr(
+                region,
+                *row,
+                h2.dense_halves,
+                d_left.dense_halves,
+                Some(a_right),
+                word(1),
+            )?;
+            *row += SUM_COMBINE_ILR_ROWS;
+            let c = self.assign_sum_combine_ilr(
+                region,
+                *row,
+                h3.dense_halves,
+                a_left,
+                Some(b_right.dense_halves),
+                word(2),
+            )?;
+            *row += SUM_COMBINE_ILR_ROWS;
+            let d = self.assign_sum_combine_ilr(
+                region,
+                *row,
+                h0,
+                b_left.dense_halves,
+                Some(c_right.dense_halves),
+                word(3),
+            )?;
+            *row += SUM_COMBINE_ILR_ROWS;
+
+            return Ok(State::new(
+                StateWord::A(a.dense_halves),
+                StateWord::B(b),
+                StateWord::C(c),
+                StateWord::D(d),
+                StateWord::E(h4),
+            ));
+        }
+
         let a = self.assign_sum_combine_ilr(
             region,
             *row,
             h1.dense_halves,
             c_left.dense_halves,
-            d_right.dense_halves,
+            Some(d_right.dense_halves),
+            word(0),
         )?;
-        *row += 7;
+        *row += SUM_COMBINE_ILR_ROWS;
         let b = self.assign_sum_combine_ilr(
             region,
             *row,
             h2.dense_halves,
             d_left.dense_halves,
-            e_right,
+            Some(e_right),
+            word(1),
+        )?;
+        *row += SUM_COMBINE_ILR_ROWS;
+        let c = self.assign_sum_combine_ilr(
+            region,
+            *row,
+            h3.dense_halves,
+            e_left,
+            Some(a_right),
+            word(2),
+        )?;
+        *row += SUM_COMBINE_ILR_ROWS;
+        let d = self.assign_sum_combine_ilr(
+            region,
+            *row,
+            h4,
+            a_left,
+            Some(b_right.dense_halves),
+            word(3),
         )?;
-        *row += 7;
-        let c = self.assign_sum_combine_ilr(region, *row, h3.dense_halves, e_left, a_right)?;
-        *row += 7;
-        let d = self.assign_sum_combine_ilr(region, *row, h4, a_left, b_right.dense_halves)?;
-        *row += 7;
+        *row += SUM_COMBINE_ILR_ROWS;
         let e = self.assign_sum_combine_ilr(
             region,
             *row,
             h0,
             b_left.dense_halves,
-            c_right.dense_halves,
+            Some(c_right.dense_halves),
+            word(4),
         )?;
-        *row += 7;
+        *row += SUM_COMBINE_ILR_ROWS;
 
         Ok(State::new(
             StateWord::A(a.dense_halves),
@@ -189,4 +303,67 @@ impl<F: FieldExt> CompressionConfig<F> {
             StateWord::E(e.dense_halves),
         ))
     }
+
+    /// Adds the final state of a line to its initial state, for the variants that do not
+    /// combine the lines
+    pub fn assign_combine_line(
+        &self,
+        region: &mut Region<'_, F>,
+        variant: RipemdVariant,
+        init_state: State<F>,
+        line_state: State<F>,
+        witness: Value<&[SumCombineWitness]>,
+        row: &mut usize,
+    ) -> Result<State<F>, Error> {
+        let word = |idx: usize| witness.map(|w| &w[idx]);
+        let (h0, h1, h2, h3, h4) = match_state(init_state);
+        let (a, b, c, d, e) = match_state(line_state);
+
+        let a = self.assign_sum_combine_ilr(region, *row, h0, a, None, word(0))?;
+        *row += SUM_COMBINE_ILR_ROWS;
+        let b = self.assign_sum_combine_ilr(
+            region,
+            *row,
+            h1.dense_halves,
+            b.dense_halves,
+            None,
+            word(1),
+        )?;
+        *row += SUM_COMBINE_ILR_ROWS;
+        let c = self.assign_sum_combine_ilr(
+            region,
+            *row,
+            h2.dense_halves,
+            c.dense_halves,
+            None,
+            word(2),
+        )?;
+        *row += SUM_COMBINE_ILR_ROWS;
+        let d = self.assign_sum_combine_ilr(
+            region,
+            *row,
+            h3.dense_halves,
+            d.dense_halves,
+            None,
+            word(3),
+        )?;
+        *row += SUM_COMBINE_ILR_ROWS;
+
+        // The four-word line of RIPEMD-256 keeps E at zero
+        let e = if variant.line_words() < DIGEST_SIZE {
+            h4
+        } else {
+            let e = self.assign_sum_combine_ilr(region, *row, h4, e, None, word(4))?;
+            *row += SUM_COMBINE_ILR_ROWS;
+            e.dense_halves
+        };
+
+        Ok(State::new(
+            StateWord::A(a.dense_halves),
+            StateWord::B(b),
+            StateWord::C(c),
+            StateWord::D(d),
+            StateWord::E(e),
+        ))
+    }
 }
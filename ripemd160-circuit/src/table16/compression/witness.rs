@@ -0,0 +1,563 @@
+//! Witness generation for the compression function.
+//!
+//! The values of every cell assigned by the gates of a compression (the limbs, spread
+//! forms and carries) are computed natively from the chaining state and the message block
+//! before any region is assigned. The `assign_*` functions then only write them to cells,
+//! instead of deriving them from the values of their input cells on each pass of the
+//! floor planner. [`CompressionWitness::batch`] generates the witnesses of many blocks in
+//! parallel.
+
+use std::convert::TryInto;
+
+use bitwise_gadgets::util::{even_bits, i2lebsp, lebs2ip, odd_bits, spread_bits};
+use halo2_proofs::{arithmetic::parallelize, circuit::Value};
+
+use super::RoundSide;
+use crate::{
+    constants::{
+        BLOCK_SIZE, DIGEST_SIZE, MSG_SEL_IDX_LEFT, MSG_SEL_IDX_RIGHT, ROL_AMOUNT_LEFT,
+        ROL_AMOUNT_RIGHT, ROUND_PHASE_SIZE,
+    },
+    native::{get_compress_state, variant_step, MessageBlock, State},
+    table16::SpreadWord,
+    RipemdVariant,
+};
+
+/// A 16-bit chunk of a word, with its tag and spread form for the lookup
+pub(super) type SpreadHalf = SpreadWord<16, 32>;
+
+fn spread_half(value: u32) -> SpreadHalf {
+    SpreadWord::new(i2lebsp(value.into()))
+}
+
+/// The dense value of a chunk
+pub(super) fn dense(chunk: &SpreadHalf) -> u32 {
+    lebs2ip(&chunk.dense) as u32
+}
+
+/// The spread form of a word, as the spread form of its low half plus 2^32 times the
+/// spread form of its high half
+fn spread(word: u32) -> u64 {
+    let half = |h: u32| lebs2ip(&spread_bits::<16, 32>(i2lebsp::<16>(h.into())));
+    half(word & 0xffff) + (half(word >> 16) << 32)
+}
+
+/// The even and odd bits of the low and high 32 bits of a sum of spread words, in the
+/// order R_0_even, R_0_odd, R_1_even, R_1_odd
+fn spread_outputs(sum: u64) -> [SpreadHalf; 4] {
+    let bits: [bool; 64] = i2lebsp(sum);
+    let r_0: [bool; 32] = bits[..32].try_into().unwrap();
+    let r_1: [bool; 32] = bits[32..].try_into().unwrap();
+    [even_bits(r_0), odd_bits(r_0), even_bits(r_1), odd_bits(r_1)].map(SpreadWord::new)
+}
+
+/// The low and high halves of a word
+pub(super) fn halves(word: u32) -> [SpreadHalf; 2] {
+    [spread_half(word & 0xffff), spread_half(word >> 16)]
+}
+
+/// Witness of the s_f1 gate for f1(X, Y, Z) = X ^ Y ^ Z
+#[derive(Clone, Debug)]
+pub(super) struct F1Witness {
+    /// R_0_even, R_0_odd, R_1_even and R_1_odd of spread_X + spread_Y + spread_Z
+    pub r: [SpreadHalf; 4],
+}
+
+impl F1Witness {
+    pub(super) fn new(x: u32, y: u32, z: u32) -> Self {
+        F1Witness {
+            r: spread_outputs(spread(x) + spread(y) + spread(z)),
+        }
+    }
+
+    pub(super) fn output(&self) -> u32 {
+        dense(&self.r[0]) + (dense(&self.r[2]) << 16)
+    }
+}
+
+/// Witness of the s_f2f4 gate for f2(X, Y, Z) = (X & Y) | (!X & Z)
+#[derive(Clone, Debug)]
+pub(super) struct F2F4Witness {
+    /// The spread halves of !X
+    pub spread_neg_x: [u32; 2],
+    /// The spread outputs of spread_X + spread_Y
+    pub p: [SpreadHalf; 4],
+    /// The spread outputs of spread_neg_X + spread_Z
+    pub q: [SpreadHalf; 4],
+    pub sum: u32,
+    pub carry: u64,
+}
+
+impl F2F4Witness {
+    pub(super) fn new(x: u32, y: u32, z: u32) -> Self {
+        let spread_neg_x = spread(!x);
+        let p = spread_outputs(spread(x) + spread(y));
+        let q = spread_outputs(spread_neg_x + spread(z));
+
+        // The odd bits of P and Q are X & Y and !X & Z
+        let sum = [&p, &q]
+            .iter()
+            .map(|r| dense(&r[1]) as u64 + ((dense(&r[3]) as u64) << 16))
+            .sum::<u64>();
+
+        F2F4Witness {
+            spread_neg_x: [spread_neg_x as u32, (spread_neg_x >> 32) as u32],
+            p,
+            q,
+            sum: sum as u32,
+            carry: sum >> 32,
+        }
+    }
+}
+
+/// Witness of the s_f3f5 gate for f3(X, Y, Z) = (X | !Y) ^ Z
+#[derive(Clone, Debug)]
+pub(super) struct F3F5Witness {
+    /// The spread halves of !Y
+    pub spread_neg_y: [u32; 2],
+    /// The spread outputs of spread_X + spread_neg_Y
+    pub sum: [SpreadHalf; 4],
+    /// The halves of X | !Y
+    pub or: [SpreadHalf; 2],
+    /// The spread outputs of spread(X | !Y) + spread_Z
+    pub r: [SpreadHalf; 4],
+}
+
+impl F3F5Witness {
+    pub(super) fn new(x: u32, y: u32, z: u32) -> Self {
+        let spread_neg_y = spread(!y);
+        let sum = spread_outputs(spread(x) + spread_neg_y);
+        let or = spread(x) | spread_neg_y;
+        let or_bits: [bool; 64] = i2lebsp(or);
+        let or_lo: [bool; 32] = or_bits[..32].try_into().unwrap();
+        let or_hi: [bool; 32] = or_bits[32..].try_into().unwrap();
+
+        F3F5Witness {
+            spread_neg_y: [spread_neg_y as u32, (spread_neg_y >> 32) as u32],
+            sum,
+            or: [even_bits(or_lo), even_bits(or_hi)].map(SpreadWord::new),
+            r: spread_outputs(or + spread(z)),
+        }
+    }
+
+    pub(super) fn output(&self) -> u32 {
+        dense(&self.r[0]) + (dense(&self.r[2]) << 16)
+    }
+}
+
+/// Witness of one of the boolean functions of a round
+#[derive(Clone, Debug)]
+pub(super) enum FWitness {
+    F1(F1Witness),
+    F2F4(F2F4Witness),
+    F3F5(F3F5Witness),
+}
+
+impl FWitness {
+    /// Computes the witness of f_idx(X, Y, Z), where f4 and f5 use the gates of f2 and f3
+    /// with their arguments reordered
+    pub(super) fn new(f_idx: usize, x: u32, y: u32, z: u32) -> Self {
+        match f_idx {
+            1 => FWitness::F1(F1Witness::new(x, y, z)),
+            2 => FWitness::F2F4(F2F4Witness::new(x, y, z)),
+            3 => FWitness::F3F5(F3F5Witness::new(x, y, z)),
+            4 => FWitness::F2F4(F2F4Witness::new(z, x, y)),
+            5 => FWitness::F3F5(F3F5Witness::new(y, z, x)),
+            _ => unreachable!(),
+        }
+    }
+
+    pub(super) fn f1(&self) -> &F1Witness {
+        match self {
+            FWitness::F1(f1) => f1,
+            _ => panic!("not a witness of f1"),
+        }
+    }
+
+    pub(super) fn f2f4(&self) -> &F2F4Witness {
+        match self {
+            FWitness::F2F4(f2f4) => f2f4,
+            _ => panic!("not a witness of f2 or f4"),
+        }
+    }
+
+    pub(super) fn f3f5(&self) -> &F3F5Witness {
+        match self {
+            FWitness::F3F5(f3f5) => f3f5,
+            _ => panic!("not a witness of f3 or f5"),
+        }
+    }
+
+    pub(super) fn output(&self) -> u32 {
+        match self {
+            FWitness::F1(f1) => f1.output(),
+            FWitness::F2F4(f2f4) => f2f4.sum,
+            FWitness::F3F5(f3f5) => f3f5.output(),
+        }
+    }
+}
+
+/// Witness of the s_rotate_left gate
+#[derive(Clone, Debug)]
+pub(super) struct RotateLeftWitness {
+    pub rol_word: u32,
+    /// a, b, a * 2^(16 - s) and b * 2^s
+    pub chunks: [SpreadHalf; 4],
+}
+
+impl RotateLeftWitness {
+    pub(super) fn new(word: u32, shift: u8) -> Self {
+        assert!(shift > 0 && shift < 32);
+        let s = (shift % 16) as u32;
+        let split_half = if shift >= 16 {
+            word & 0xffff
+        } else {
+            word >> 16
+        };
+        let a = split_half >> (16 - s);
+        let b = split_half & ((1 << (16 - s)) - 1);
+
+        RotateLeftWitness {
+            rol_word: word.rotate_left(shift.into()),
+            chunks: [a, b, a << (16 - s), b << s].map(spread_half),
+        }
+    }
+}
+
+/// Witness of the s_step gate
+#[derive(Clone, Debug)]
+pub(super) struct StepWitness {
+    pub carry_sum: u64,
+    pub carry_t: u64,
+    /// sum_lo, sum_a, sum_b, sum_a * 2^(16 - s), sum_b * 2^s, t_lo, t_hi, r_a, r_b,
+    /// r_b * 2^10, rol10_t_lo and rol10_t_hi
+    pub chunks: [SpreadHalf; 12],
+}
+
+impl StepWitness {
+    pub(super) fn new(a: u32, f: u32, x: u32, e: u32, k: u32, shift: u8) -> Self {
+        assert!(shift > 0 && shift < 16);
+        let s = shift as u32;
+
+        let sum = a as u64 + f as u64 + x as u64 + k as u64;
+        let carry_sum = sum >> 32;
+        let sum = sum as u32;
+        let t = sum.rotate_left(s) as u64 + e as u64;
+        let carry_t = t >> 32;
+        let t = t as u32;
+
+        // Split sum_hi into (sum_b, sum_a) = (16 - s, s) bit chunks and t_hi into
+        // (r_b, r_a) = (6, 10) bit chunks
+        let sum_a = sum >> (32 - s);
+        let sum_b = (sum >> 16) & ((1 << (16 - s)) - 1);
+        let r_b = (t >> 16) & ((1 << 6) - 1);
+        let rol10_t = t.rotate_left(10);
+
+        StepWitness {
+            carry_sum,
+            carry_t,
+            chunks: [
+                sum & 0xffff,
+                sum_a,
+                sum_b,
+                sum_a << (16 - s),
+                sum_b << s,
+                t & 0xffff,
+                t >> 16,
+                t >> 22,
+                r_b,
+                r_b << 10,
+                rol10_t & 0xffff,
+                rol10_t >> 16,
+            ]
+            .map(spread_half),
+        }
+    }
+
+    pub(super) fn t(&self) -> u32 {
+        dense(&self.chunks[5]) + (dense(&self.chunks[6]) << 16)
+    }
+}
+
+/// Witness of the s_sum_combine_ilr gate
+#[derive(Clone, Debug)]
+pub(super) struct SumCombineWitness {
+    pub carry: u64,
+    pub sum: [SpreadHalf; 2],
+}
+
+impl SumCombineWitness {
+    pub(super) fn new(init_state_word: u32, left_state_word: u32, right_state_word: u32) -> Self {
+        let sum = init_state_word as u64 + left_state_word as u64 + right_state_word as u64;
+        SumCombineWitness {
+            carry: sum >> 32,
+            sum: halves(sum as u32),
+        }
+    }
+
+    pub(super) fn output(&self) -> u32 {
+        dense(&self.sum[0]) + (dense(&self.sum[1]) << 16)
+    }
+}
+
+/// Witness of a round of one line
+#[derive(Clone, Debug)]
+pub(super) struct RoundWitness {
+    pub f: FWitness,
+    pub step: StepWitness,
+    /// rotate_left(C, 10) and its halves for the five-word lines, which is only assigned
+    /// when no earlier step has computed it
+    pub rol10_c: Option<(RotateLeftWitness, [SpreadHalf; 2])>,
+}
+
+impl RoundWitness {
+    /// Computes the witness of a round, returning it with the state after the round
+    pub(super) fn new(
+        variant: RipemdVariant,
+        round_idx: usize,
+        round_side: &RoundSide,
+        state: [u32; DIGEST_SIZE],
+        block: [u32; BLOCK_SIZE],
+    ) -> (Self, [u32; DIGEST_SIZE]) {
+        let [a, b, c, d, e] = state;
+        let phase_idx = round_idx / ROUND_PHASE_SIZE;
+        let (f_idx, x, k, shift) = match round_side {
+            RoundSide::Left => (
+                variant.round_function_left(phase_idx),
+                block[MSG_SEL_IDX_LEFT[round_idx]],
+                variant.round_constant_left(phase_idx),
+                ROL_AMOUNT_LEFT[round_idx],
+            ),
+            RoundSide::Right => (
+                variant.round_function_right(phase_idx),
+                block[MSG_SEL_IDX_RIGHT[round_idx]],
+                variant.round_constant_right(phase_idx),
+                ROL_AMOUNT_RIGHT[round_idx],
+            ),
+        };
+
+        let f = FWitness::new(f_idx, b, c, d);
+        let step = StepWitness::new(a, f.output(), x, e, k, shift);
+        let rol10_c = (variant.line_words() == DIGEST_SIZE).then(|| {
+            let rol10_c = RotateLeftWitness::new(c, 10);
+            let rol10_c_halves = halves(rol10_c.rol_word);
+            (rol10_c, rol10_c_halves)
+        });
+
+        let next: [u32; DIGEST_SIZE] = variant_step(
+            variant,
+            round_idx,
+            *round_side == RoundSide::Left,
+            State::from(state),
+            MessageBlock::from(block),
+        )
+        .into();
+        debug_assert_eq!(next[1], step.t());
+
+        (RoundWitness { f, step, rol10_c }, next)
+    }
+}
+
+/// Witness of the compression of a block by both lines, and of the combination of the
+/// lines with the chaining state
+#[derive(Clone, Debug)]
+pub(super) struct CompressionWitness {
+    pub left: Vec<RoundWitness>,
+    pub right: Vec<RoundWitness>,
+    /// The sums in the order they are assigned. For the variants that do not combine their
+    /// lines, the words of the left line come before the words of the right line.
+    pub combine: Vec<SumCombineWitness>,
+}
+
+impl CompressionWitness {
+    /// Computes the witness of compressing `block` from the given states of the lines,
+    /// where the right state is `None` for the variants that combine their lines
+    pub(super) fn new(
+        variant: RipemdVariant,
+        init_left: [u32; DIGEST_SIZE],
+        init_right: Option<[u32; DIGEST_SIZE]>,
+        block: [u32; BLOCK_SIZE],
+    ) -> Self {
+        let mut left_state = init_left;
+        let mut right_state = init_right.unwrap_or(init_left);
+        let mut left = Vec::with_capacity(variant.rounds());
+        let mut right = Vec::with_capacity(variant.rounds());
+
+        for phase_idx in 0..variant.phases() {
+            for idx in phase_idx * ROUND_PHASE_SIZE..(phase_idx + 1) * ROUND_PHASE_SIZE {
+                let (round, state) =
+                    RoundWitness::new(variant, idx, &RoundSide::Left, left_state, block);
+                left.push(round);
+                left_state = state;
+
+                let (round, state) =
+                    RoundWitness::new(variant, idx, &RoundSide::Right, right_state, block);
+                right.push(round);
+                right_state = state;
+            }
+
+            if let Some(register) = variant.exchanged_register(phase_idx) {
+                std::mem::swap(&mut left_state[register], &mut right_state[register]);
+            }
+        }
+
+        // The words follow the order of assign_combine_ilr and assign_combine_line
+        let (h, l, r) = (init_left, left_state, right_state);
+        let combine = match init_right {
+            None if variant.line_words() < DIGEST_SIZE => vec![
+                (h[1], l[2], r[3]),
+                (h[2], l[3], r[0]),
+                (h[3], l[0], r[1]),
+                (h[0], l[1], r[2]),
+            ],
+            None => vec![
+                (h[1], l[2], r[3]),
+                (h[2], l[3], r[4]),
+                (h[3], l[4], r[0]),
+                (h[4], l[0], r[1]),
+                (h[0], l[1], r[2]),
+            ],
+            Some(init_right) => {
+                let n = variant.line_words();
+                (0..n)
+                    .map(|idx| (h[idx], l[idx], 0))
+                    .chain((0..n).map(|idx| (init_right[idx], r[idx], 0)))
+                    .collect()
+            }
+        };
+
+        CompressionWitness {
+            left,
+            right,
+            combine: combine
+                .into_iter()
+                .map(|(h, l, r)| SumCombineWitness::new(h, l, r))
+                .collect(),
+        }
+    }
+
+    /// Computes the witnesses of the RIPEMD-160 compressions of the blocks of each message,
+    /// chaining the blocks of a message from the IV. The blocks are witnessed in parallel.
+    pub(super) fn batch(
+        iv: [u32; DIGEST_SIZE],
+        messages: &[Vec<[Value<u32>; BLOCK_SIZE]>],
+    ) -> Vec<Vec<Value<Self>>> {
+        let num_blocks: usize = messages.iter().map(Vec::len).sum();
+        let blocks: Value<Vec<[u32; BLOCK_SIZE]>> = messages
+            .iter()
+            .flatten()
+            .map(|block| {
+                block
+                    .iter()
+                    .copied()
+                    .collect::<Value<Vec<u32>>>()
+                    .map(|words| words.try_into().unwrap())
+            })
+            .collect();
+
+        // The chaining states are cheap to compute natively, after which every block can
+        // be witnessed on its own
+        let witnesses = blocks.map(|blocks| {
+            let mut inputs = Vec::with_capacity(num_blocks);
+            let mut blocks = blocks.into_iter();
+            for message in messages {
+                let mut state = iv;
+                for block in blocks.by_ref().take(message.len()) {
+                    inputs.push((state, block));
+                    state = get_compress_state(state.into(), block.into()).into();
+                }
+            }
+
+            let mut witnesses = vec![None; num_blocks];
+            parallelize(&mut witnesses, |witnesses, start| {
+                for (witness, (state, block)) in witnesses.iter_mut().zip(&inputs[start..]) {
+                    *witness = Some(Self::new(RipemdVariant::Ripemd160, *state, None, *block));
+                }
+            });
+            witnesses
+                .into_iter()
+                .map(Option::unwrap)
+                .collect::<Vec<_>>()
+        });
+
+        let mut witnesses = witnesses.transpose_vec(num_blocks).into_iter();
+        messages
+            .iter()
+            .map(|message| witnesses.by_ref().take(message.len()).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::Value;
+
+    use super::{CompressionWitness, FWitness};
+    use crate::{
+        constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES, INITIAL_VALUES},
+        native::{f1, f2, f3, f4, f5, get_compress_state, pad_message_bytes},
+        table16::util::convert_byte_slice_to_u32_slice,
+    };
+
+    #[test]
+    fn test_f_outputs() {
+        let words = [
+            0x0123_4567,
+            0x89ab_cdef,
+            0xfedc_ba98,
+            0x7654_3210,
+            0xffff_0000,
+        ];
+        for &x in &words {
+            for &y in &words {
+                for &z in &words {
+                    for (f_idx, f) in [f1, f2, f3, f4, f5].iter().enumerate() {
+                        assert_eq!(FWitness::new(f_idx + 1, x, y, z).output(), f(x, y, z));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch() {
+        let messages: Vec<Vec<u8>> = vec![b"".to_vec(), b"1234567890".repeat(20)];
+        let blocks: Vec<Vec<[u32; BLOCK_SIZE]>> = messages
+            .iter()
+            .map(|message| {
+                pad_message_bytes(message.clone())
+                    .into_iter()
+                    .map(convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                    .collect::<Result<_, _>>()
+                    .unwrap()
+            })
+            .collect();
+
+        let witnesses = CompressionWitness::batch(
+            INITIAL_VALUES,
+            &blocks
+                .iter()
+                .map(|blocks| blocks.iter().map(|block| block.map(Value::known)).collect())
+                .collect::<Vec<_>>(),
+        );
+
+        for (blocks, witnesses) in blocks.iter().zip(witnesses) {
+            assert_eq!(blocks.len(), witnesses.len());
+            let mut state = INITIAL_VALUES;
+            for (block, witness) in blocks.iter().zip(witnesses) {
+                state = get_compress_state(state.into(), (*block).into()).into();
+                // The combined words are assigned in the order of the chaining state
+                witness.assert_if_known(|witness| {
+                    let combined: Vec<u32> =
+                        witness.combine.iter().map(|sum| sum.output()).collect();
+                    combined == state
+                });
+            }
+        }
+
+        // Unknown blocks give unknown witnesses of the same shape
+        let unknown =
+            CompressionWitness::batch(INITIAL_VALUES, &[vec![[Value::unknown(); BLOCK_SIZE]; 2]]);
+        assert_eq!(unknown[0].len(), 2);
+    }
+}
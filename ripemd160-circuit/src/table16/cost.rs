@@ -0,0 +1,558 @@
+//! The cost of hashing with the table16 chip, as laid out by the `SimpleFloorPlanner`.
+//!
+//! The message schedule, the left line and the combination of the lines are assigned in
+//! one set of columns, while the right line has its own set of columns next to it. The
+//! height of a circuit is the largest of the heights of both sets of columns, the number
+//! of fixed constants and the size of the spread table.
+
+use std::{
+    iter::Sum,
+    ops::{Add, AddAssign, Mul},
+};
+
+use halo2_proofs::{arithmetic::FieldExt, plonk::ConstraintSystem};
+
+use super::{
+    compression::{
+        F1_ROWS, F2F4_ROWS, F3F5_ROWS, ROTATE_LEFT_ROWS, SPREAD_WORD_ROWS, STEP_ROWS,
+        SUM_COMBINE_ILR_ROWS,
+    },
+    message_schedule::DECOMPOSE_WORD_ROWS,
+    Table16Chip,
+};
+use crate::{
+    constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE, ROUND_PHASE_SIZE},
+    RipemdVariant,
+};
+
+pub use bitwise_gadgets::SPREAD_TABLE_ROWS;
+
+/// The rows, lookups and copy constraints used by an assignment
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cost {
+    /// Number of rows
+    pub rows: usize,
+    /// Number of rows looked up in the spread table
+    pub lookups: usize,
+    /// Number of copy constraints, including the ones to fixed constants
+    pub copies: usize,
+    /// Number of fixed constants, each taking a row of the constants column
+    pub constants: usize,
+}
+
+impl Cost {
+    const fn new(rows: usize, lookups: usize, copies: usize, constants: usize) -> Self {
+        Cost {
+            rows,
+            lookups,
+            copies,
+            constants,
+        }
+    }
+}
+
+impl Add for Cost {
+    type Output = Cost;
+
+    fn add(self, other: Cost) -> Cost {
+        Cost {
+            rows: self.rows + other.rows,
+            lookups: self.lookups + other.lookups,
+            copies: self.copies + other.copies,
+            constants: self.constants + other.constants,
+        }
+    }
+}
+
+impl AddAssign for Cost {
+    fn add_assign(&mut self, other: Cost) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Cost {
+    fn sum<I: Iterator<Item = Cost>>(iter: I) -> Cost {
+        iter.fold(Cost::default(), Add::add)
+    }
+}
+
+impl Mul<usize> for Cost {
+    type Output = Cost;
+
+    fn mul(self, n: usize) -> Cost {
+        Cost {
+            rows: self.rows * n,
+            lookups: self.lookups * n,
+            copies: self.copies * n,
+            constants: self.constants * n,
+        }
+    }
+}
+
+// A word and its looked up halves
+const WORD_AND_HALVES: Cost = Cost::new(DECOMPOSE_WORD_ROWS, 2, 2, 0);
+// A word recomposed from copies of its halves
+const DECOMPOSE_WORD: Cost = Cost::new(DECOMPOSE_WORD_ROWS, 0, 2, 0);
+// A cell constrained to a fixed constant
+const CONSTANT: Cost = Cost::new(0, 0, 1, 1);
+
+const F1: Cost = Cost::new(F1_ROWS, 4, 6, 0);
+const F2F4: Cost = Cost::new(F2F4_ROWS, 8, 6, 0);
+const F3F5: Cost = Cost::new(F3F5_ROWS, 10, 6, 0);
+const STEP: Cost = Cost::new(STEP_ROWS, 12, 8, 0);
+const ROTATE_LEFT: Cost = Cost::new(ROTATE_LEFT_ROWS, 4, 2, 0);
+// A word looked up from copies of its halves
+const SPREAD_WORD: Cost = Cost::new(SPREAD_WORD_ROWS, 2, 2, 0);
+// Adds three words, or two words and a constant zero
+const SUM_COMBINE_ILR: Cost = Cost::new(SUM_COMBINE_ILR_ROWS, 2, 6, 0);
+const SUM_COMBINE_LINE: Cost = Cost::new(SUM_COMBINE_ILR_ROWS, 2, 4, 0);
+
+/// The set of columns a region is assigned in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Columns {
+    /// The columns of the message schedule, the left line and the combination of the lines
+    Main,
+    /// The columns of the right line
+    Right,
+}
+
+/// The cost of a region
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionCost {
+    /// The name of the region
+    pub name: &'static str,
+    /// The set of columns of the region
+    pub columns: Columns,
+    /// The rows, lookups and copy constraints of the region
+    pub cost: Cost,
+}
+
+/// The cost of hashing a message with the table16 chip
+#[derive(Clone, Debug)]
+pub struct CostReport {
+    /// The hashed variant
+    pub variant: RipemdVariant,
+    /// Number of hashed blocks
+    pub blocks: usize,
+    /// The regions of the hash, in the order they are assigned
+    pub regions: Vec<RegionCost>,
+    /// Number of rows at the end of each column that cannot be assigned
+    pub blinding_factors: usize,
+    /// Number of advice columns
+    pub advice_columns: usize,
+    /// Number of fixed columns, without the ones of the selectors
+    pub fixed_columns: usize,
+    /// Number of selectors
+    pub selectors: usize,
+    /// Number of lookup arguments
+    pub lookup_arguments: usize,
+}
+
+impl CostReport {
+    /// Returns the cost of hashing `blocks` padded blocks with the given variant.
+    pub fn new<F: FieldExt>(variant: RipemdVariant, blocks: usize) -> Self {
+        let lines = if variant.combines_lines() { 1 } else { 2 };
+        let mut regions = vec![];
+        for _ in 0..lines {
+            regions.push(RegionCost {
+                name: "init_with_iv",
+                columns: Columns::Main,
+                cost: iv_cost(),
+            });
+        }
+
+        for _ in 0..blocks {
+            regions.push(RegionCost {
+                name: "process message block",
+                columns: Columns::Main,
+                cost: message_block_cost(),
+            });
+            for phase_idx in 0..variant.phases() {
+                regions.push(RegionCost {
+                    name: "compress left",
+                    columns: Columns::Main,
+                    cost: phase_cost(variant, phase_idx, variant.round_function_left(phase_idx)),
+                });
+                regions.push(RegionCost {
+                    name: "compress right",
+                    columns: Columns::Right,
+                    cost: phase_cost(variant, phase_idx, variant.round_function_right(phase_idx)),
+                });
+            }
+            regions.push(RegionCost {
+                name: "combine",
+                columns: Columns::Main,
+                cost: combine_cost(variant),
+            });
+        }
+
+        for _ in 0..lines {
+            regions.push(RegionCost {
+                name: "digest",
+                columns: Columns::Main,
+                cost: digest_cost(),
+            });
+        }
+
+        Self::with_regions::<F>(variant, blocks, regions)
+    }
+
+    /// Returns the cost of hashing messages of the given numbers of padded blocks with
+    /// RIPEMD-160 in one region, as laid out by [`crate::RIPEMD160::digest_batch`]. The
+    /// region is reported as one part for each set of columns.
+    pub fn batch<F: FieldExt>(message_blocks: &[usize]) -> Self {
+        let variant = RipemdVariant::Ripemd160;
+        let left: Cost = (0..variant.phases())
+            .map(|idx| phase_cost(variant, idx, variant.round_function_left(idx)))
+            .sum();
+        let right: Cost = (0..variant.phases())
+            .map(|idx| phase_cost(variant, idx, variant.round_function_right(idx)))
+            .sum();
+
+        // The message blocks and digests go in the shortest set of columns
+        let mut main = iv_cost();
+        let mut other = Cost::default();
+        for blocks in message_blocks {
+            for _ in 0..*blocks {
+                if other.rows <= main.rows {
+                    other += message_block_cost();
+                } else {
+                    main += message_block_cost();
+                }
+                main += left + combine_cost(variant);
+                other += right;
+            }
+            if other.rows < main.rows {
+                other += digest_cost();
+            } else {
+                main += digest_cost();
+            }
+        }
+
+        let regions = vec![
+            RegionCost {
+                name: "batch",
+                columns: Columns::Main,
+                cost: main,
+            },
+            RegionCost {
+                name: "batch",
+                columns: Columns::Right,
+                cost: other,
+            },
+        ];
+        Self::with_regions::<F>(variant, message_blocks.iter().sum(), regions)
+    }
+
+    fn with_regions<F: FieldExt>(
+        variant: RipemdVariant,
+        blocks: usize,
+        regions: Vec<RegionCost>,
+    ) -> Self {
+        let mut meta = ConstraintSystem::<F>::default();
+        Table16Chip::configure(&mut meta);
+
+        CostReport {
+            variant,
+            blocks,
+            regions,
+            blinding_factors: meta.blinding_factors(),
+            advice_columns: meta.num_advice_columns(),
+            fixed_columns: meta.num_fixed_columns(),
+            selectors: meta.num_selectors(),
+            lookup_arguments: meta.lookups().len(),
+        }
+    }
+
+    /// Returns the cost of hashing a message of `len` bytes with the given variant.
+    pub fn for_message_len<F: FieldExt>(variant: RipemdVariant, len: usize) -> Self {
+        // The padding adds a 0x80 byte and the 8-byte length
+        Self::new::<F>(variant, (len + 8) / BLOCK_SIZE_BYTES + 1)
+    }
+
+    /// Returns the sum of the costs of all regions.
+    pub fn total(&self) -> Cost {
+        self.regions.iter().map(|region| region.cost).sum()
+    }
+
+    /// Returns the height of the given set of columns.
+    pub fn column_rows(&self, columns: Columns) -> usize {
+        self.regions
+            .iter()
+            .filter(|region| region.columns == columns)
+            .map(|region| region.cost.rows)
+            .sum()
+    }
+
+    /// Returns the number of rows needed by the circuit, including the spread table.
+    pub fn rows(&self) -> usize {
+        [
+            self.column_rows(Columns::Main),
+            self.column_rows(Columns::Right),
+            self.total().constants,
+            SPREAD_TABLE_ROWS,
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+
+    /// Returns the number of rows that can be assigned in a circuit of `2^k` rows.
+    pub fn usable_rows(&self, k: u32) -> usize {
+        (1_usize << k).saturating_sub(self.blinding_factors + 1)
+    }
+
+    /// Returns the smallest `k` such that the hash fits in a circuit of `2^k` rows.
+    pub fn min_k(&self) -> u32 {
+        let rows = self.rows();
+        (1..).find(|k| self.usable_rows(*k) >= rows).unwrap()
+    }
+}
+
+fn iv_cost() -> Cost {
+    (WORD_AND_HALVES + CONSTANT) * DIGEST_SIZE
+}
+
+fn message_block_cost() -> Cost {
+    WORD_AND_HALVES * BLOCK_SIZE
+}
+
+fn digest_cost() -> Cost {
+    DECOMPOSE_WORD * DIGEST_SIZE
+}
+
+fn combine_cost(variant: RipemdVariant) -> Cost {
+    if variant.combines_lines() {
+        SUM_COMBINE_ILR * variant.line_words()
+    } else {
+        (SUM_COMBINE_LINE + CONSTANT * 2) * variant.line_words() * 2
+    }
+}
+
+// Returns the cost of a phase of a line using the given round function
+fn phase_cost(variant: RipemdVariant, phase_idx: usize, f_idx: usize) -> Cost {
+    let f = match f_idx {
+        1 => F1,
+        2 | 4 => F2F4,
+        _ => F3F5,
+    };
+
+    (phase_idx * ROUND_PHASE_SIZE..(phase_idx + 1) * ROUND_PHASE_SIZE).fold(
+        Cost::default(),
+        |cost, round_idx| {
+            // rotate_left(C, 10) is computed for the first two rounds of a five-word line,
+            // and taken from earlier rounds afterwards
+            let rol10_c = if variant.line_words() == DIGEST_SIZE && round_idx < 2 {
+                ROTATE_LEFT + SPREAD_WORD
+            } else {
+                Cost::default()
+            };
+            cost + f + STEP + rol10_c
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::{CellValue, MockProver},
+        halo2curves::{bn256::Fr, FieldExt},
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+
+    use super::{Columns, Cost, CostReport};
+    use crate::constants::BLOCK_SIZE;
+    use crate::table16::{BlockWord, Table16Chip, Table16Config};
+    use crate::{RIPEMD160Instructions, Ripemd, RipemdVariant};
+
+    // Hashes zero blocks after a region of `filler` rows in the main columns, or
+    // `Some(messages)` messages of that many blocks with `digest_batch`
+    #[derive(Clone, Copy)]
+    struct FilledCircuit {
+        variant: RipemdVariant,
+        blocks: usize,
+        filler: usize,
+        batch: Option<usize>,
+    }
+
+    impl<F: FieldExt> Circuit<F> for FilledCircuit {
+        type Config = Table16Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            Table16Chip::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let chip = Table16Chip::construct_variant(config.clone(), self.variant);
+            Table16Chip::load(config, &mut layouter)?;
+
+            chip.witness_words(
+                &mut layouter.namespace(|| "filler"),
+                &vec![BlockWord(Value::known(0)); self.filler],
+            )?;
+
+            let blocks = vec![[BlockWord(Value::known(0)); BLOCK_SIZE]; self.blocks];
+            match self.batch {
+                Some(messages) => {
+                    chip.digest_batch(&mut layouter, &vec![blocks; messages])?;
+                }
+                None => {
+                    Ripemd::digest(chip, layouter.namespace(|| "ripemd"), &blocks)?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_ripemd160_block() {
+        let report = CostReport::new::<Fr>(RipemdVariant::Ripemd160, 1);
+
+        assert_eq!(report.regions.len(), 14);
+        assert_eq!(report.column_rows(Columns::Main), 1853);
+        assert_eq!(report.column_rows(Columns::Right), 1740);
+        assert_eq!(
+            report.total(),
+            Cost {
+                rows: 3593,
+                lookups: 3276,
+                copies: 2343,
+                constants: 5,
+            }
+        );
+        assert_eq!(report.min_k(), 17);
+    }
+
+    #[test]
+    fn test_message_len() {
+        for (len, blocks) in [(0, 1), (55, 1), (56, 2), (119, 2), (120, 3), (2560, 41)] {
+            let report = CostReport::for_message_len::<Fr>(RipemdVariant::Ripemd160, len);
+            assert_eq!(report.blocks, blocks);
+        }
+    }
+
+    #[test]
+    fn test_batch() {
+        let report = CostReport::batch::<Fr>(&[1, 1, 1]);
+        assert_eq!(report.column_rows(Columns::Main), 5388);
+        assert_eq!(report.column_rows(Columns::Right), 5361);
+
+        let sequential = CostReport::new::<Fr>(RipemdVariant::Ripemd160, 1);
+        assert!(report.column_rows(Columns::Main) < 3 * sequential.column_rows(Columns::Main));
+        assert_eq!(report.total().constants, 5);
+    }
+
+    // The hash fits exactly after a filler region if and only if the report has the exact
+    // height of the columns and the exact number of blinding rows
+    #[test]
+    fn test_rows_against_mock_prover() {
+        let k = 17;
+        for (variant, blocks, batch) in [
+            (RipemdVariant::Ripemd160, 2, None),
+            (RipemdVariant::Ripemd320, 1, None),
+            (RipemdVariant::Ripemd160, 1, Some(3)),
+        ] {
+            let report = match batch {
+                Some(messages) => CostReport::batch::<Fr>(&vec![blocks; messages]),
+                None => CostReport::new::<Fr>(variant, blocks),
+            };
+            // The filler is only in the main columns, so a right line that is higher than
+            // the main columns would not start after it
+            let rows = report.column_rows(Columns::Main);
+            assert!(report.column_rows(Columns::Right) <= rows);
+
+            let filler = report.usable_rows(k) - rows;
+            let circuit = FilledCircuit {
+                variant,
+                blocks,
+                filler,
+                batch,
+            };
+            assert!(MockProver::<Fr>::run(k, &circuit, vec![]).is_ok());
+
+            let circuit = FilledCircuit {
+                filler: filler + 1,
+                ..circuit
+            };
+            assert!(matches!(
+                MockProver::<Fr>::run(k, &circuit, vec![]),
+                Err(Error::NotEnoughRowsAvailable { .. })
+            ));
+        }
+    }
+
+    // Returns the number of copy constraints that joined the cells in the cycles of the
+    // permutation, which is the size of each cycle but one
+    fn permutation_copies(mapping: &[Vec<(usize, usize)>]) -> usize {
+        let mut visited: Vec<_> = mapping
+            .iter()
+            .map(|column| vec![false; column.len()])
+            .collect();
+        let mut copies = 0;
+        for column in 0..mapping.len() {
+            for row in 0..mapping[column].len() {
+                let mut cell = (column, row);
+                let mut size = 0;
+                while !visited[cell.0][cell.1] {
+                    visited[cell.0][cell.1] = true;
+                    size += 1;
+                    cell = mapping[cell.0][cell.1];
+                }
+                copies += size.saturating_sub(1);
+            }
+        }
+        copies
+    }
+
+    // The spread lookups have no selector, so each assigned row of their input columns is
+    // looked up
+    #[test]
+    fn test_lookups_and_copies_against_mock_prover() {
+        let k = 17;
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let config = Table16Chip::configure(&mut meta);
+        let lookup_columns = [
+            config.lookup.input.dense,
+            config.compression_right.lookup.dense,
+        ];
+
+        for variant in [RipemdVariant::Ripemd160, RipemdVariant::Ripemd320] {
+            let circuit = FilledCircuit {
+                variant,
+                blocks: 1,
+                filler: 0,
+                batch: None,
+            };
+            let prover = MockProver::<Fr>::run(k, &circuit, vec![]).unwrap();
+            let lookups: usize = lookup_columns
+                .iter()
+                .map(|column| {
+                    prover.advice()[column.index()]
+                        .iter()
+                        .filter(|cell| matches!(cell, CellValue::Assigned(_)))
+                        .count()
+                })
+                .sum();
+
+            let report = CostReport::new::<Fr>(variant, 1);
+            assert_eq!(report.total().lookups, lookups, "{:?}", variant);
+            assert_eq!(
+                report.total().copies,
+                permutation_copies(prover.permutation().mapping()),
+                "{:?}",
+                variant
+            );
+        }
+    }
+}
@@ -2,7 +2,10 @@
 Modified version of code from https://github.com/privacy-scaling-explorations/halo2/blob/8c945507ceca5f4ed6e52da3672ea0308bcac812/halo2_gadgets/src/sha256/table16/gates.rs
 */
 
-use halo2_proofs::{halo2curves::FieldExt, plonk::Expression};
+use halo2_proofs::{
+    halo2curves::FieldExt,
+    plonk::{Constraints, Expression},
+};
 
 pub struct Gate<F: FieldExt>(pub Expression<F>);
 
@@ -111,4 +114,135 @@ impl<F: FieldExt> Gate<F> {
         let check = lo + hi * F::from(1 << 16) - word;
         Some(("s_decompose_word", s_decompose_word * check))
     }
+
+    /// s_swap_bytes for big-endian words
+    /// bytes are the little-endian bytes of word and all of them have tag 0 (8 bits)
+    pub fn s_swap_bytes(
+        s_swap_bytes: Expression<F>,
+        tags: Vec<Expression<F>>,
+        bytes: Vec<Expression<F>>,
+        word: Expression<F>,
+        swapped_lo: Expression<F>,
+        swapped_hi: Expression<F>,
+    ) -> Constraints<
+        F,
+        (&'static str, Expression<F>),
+        impl Iterator<Item = (&'static str, Expression<F>)>,
+    > {
+        assert_eq!(tags.len(), 4);
+        assert_eq!(bytes.len(), 4);
+
+        let word_check = bytes[0].clone()
+            + bytes[1].clone() * F::from(1 << 8)
+            + bytes[2].clone() * F::from(1 << 16)
+            + bytes[3].clone() * F::from(1 << 24)
+            - word;
+        let swapped_lo_check = bytes[3].clone() + bytes[2].clone() * F::from(1 << 8) - swapped_lo;
+        let swapped_hi_check = bytes[1].clone() + bytes[0].clone() * F::from(1 << 8) - swapped_hi;
+
+        Constraints::with_selector(
+            s_swap_bytes,
+            tags.into_iter()
+                .map(|tag| ("byte_tag", tag))
+                .chain(Some(("word_check", word_check)))
+                .chain(Some(("swapped_lo_check", swapped_lo_check)))
+                .chain(Some(("swapped_hi_check", swapped_hi_check))),
+        )
+    }
+
+    /// s_word_bytes for the RLC of the bytes of a word
+    /// bytes are the little-endian bytes of word and all of them have tag 0 (8 bits)
+    pub fn s_word_bytes(
+        s_word_bytes: Expression<F>,
+        tags: Vec<Expression<F>>,
+        bytes: Vec<Expression<F>>,
+        word: Expression<F>,
+    ) -> Constraints<
+        F,
+        (&'static str, Expression<F>),
+        impl Iterator<Item = (&'static str, Expression<F>)>,
+    > {
+        assert_eq!(tags.len(), 4);
+        assert_eq!(bytes.len(), 4);
+
+        let word_check = bytes[0].clone()
+            + bytes[1].clone() * F::from(1 << 8)
+            + bytes[2].clone() * F::from(1 << 16)
+            + bytes[3].clone() * F::from(1 << 24)
+            - word;
+
+        Constraints::with_selector(
+            s_word_bytes,
+            tags.into_iter()
+                .map(|tag| ("byte_tag", tag))
+                .chain(Some(("word_check", word_check))),
+        )
+    }
+
+    /// s_rlc for each byte after the first one of an RLC
+    /// rlc_prev is the RLC of the bytes before byte
+    pub fn s_rlc(
+        s_rlc: Expression<F>,
+        rlc_prev: Expression<F>,
+        challenge: Expression<F>,
+        byte: Expression<F>,
+        rlc: Expression<F>,
+    ) -> Option<(&'static str, Expression<F>)> {
+        let check = rlc_prev * challenge + byte - rlc;
+        Some(("s_rlc", s_rlc * check))
+    }
+
+    /// s_rlc_first for the first byte of an RLC
+    pub fn s_rlc_first(
+        s_rlc_first: Expression<F>,
+        byte: Expression<F>,
+        rlc: Expression<F>,
+    ) -> Option<(&'static str, Expression<F>)> {
+        Some(("s_rlc_first", s_rlc_first * (byte - rlc)))
+    }
+
+    /// s_key_pads for an HMAC key word and its ipad and opad words
+    /// spread holds, for each 16-bit half k of the word, spread_k followed by
+    /// spread(k XOR pad) and spread(k AND pad) for ipad and then opad
+    pub fn s_key_pads(
+        s_key_pads: Expression<F>,
+        word: Expression<F>,
+        k_lo: Expression<F>,
+        k_hi: Expression<F>,
+        spread: Vec<Expression<F>>,
+        spread_ipad: u64,
+        spread_opad: u64,
+    ) -> Constraints<
+        F,
+        (&'static str, Expression<F>),
+        impl Iterator<Item = (&'static str, Expression<F>)>,
+    > {
+        assert_eq!(spread.len(), 10);
+
+        let word_check = k_lo + k_hi * F::from(1 << 16) - word;
+
+        // spread_k + spread_pad = spread_xor + 2 * spread_and
+        let pad_checks: Vec<_> = spread
+            .chunks(5)
+            .flat_map(|half| {
+                [
+                    ("ipad_check", spread_ipad, 1),
+                    ("opad_check", spread_opad, 3),
+                ]
+                .map(|(name, spread_pad, idx)| {
+                    let check = half[0].clone() + Expression::Constant(F::from(spread_pad))
+                        - half[idx].clone()
+                        - half[idx + 1].clone() * F::from(2);
+                    (name, check)
+                })
+            })
+            .collect();
+
+        Constraints::with_selector(
+            s_key_pads,
+            std::iter::empty()
+                .chain(Some(("word_check", word_check)))
+                .chain(pad_checks),
+        )
+    }
 }
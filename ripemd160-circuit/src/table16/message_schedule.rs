@@ -1,18 +1,31 @@
 use std::marker::PhantomData;
 
+use bitwise_gadgets::util::{i2lebsp, lebs2ip, spread_bits};
 use halo2_proofs::{
     circuit::{Layouter, Region, Value},
     halo2curves::FieldExt,
-    plonk::{Advice, Column, ConstraintSystem, Error, Selector},
+    plonk::{Advice, Column, ConstraintSystem, Selector},
     poly::Rotation,
 };
 
-use crate::constants::BLOCK_SIZE;
+use crate::{
+    constants::{BLOCK_SIZE, DIGEST_SIZE, SHA256_DIGEST_SIZE},
+    error::assign_region,
+    Error,
+};
 
-use super::{gates::Gate, spread_table::SpreadInputs, AssignedBits, BlockWord, Table16Assignment};
+use super::{
+    gates::Gate, AssignedBits, BlockWord, SpreadInputs, SpreadVar, SpreadWord, Table16Assignment,
+};
 
 // Rows needed for each decompose gate
-pub const DECOMPOSE_WORD_ROWS: usize = 2;
+pub const DECOMPOSE_WORD_ROWS: usize = 3;
+// Rows needed for each key pads gate, 5 for each 16-bit half of the key word
+pub const KEY_PADS_ROWS: usize = 10;
+
+// The HMAC ipad and opad bytes, repeated over a 16-bit half word
+const IPAD_HALF: u16 = 0x3636;
+const OPAD_HALF: u16 = 0x5c5c;
 
 #[derive(Debug, Clone)]
 pub(super) struct MessageWord<F: FieldExt>(AssignedBits<32, F>);
@@ -32,6 +45,10 @@ pub(super) struct MessageScheduleConfig<F: FieldExt> {
 
     /// Decomposition gate for X[0..16]
     s_decompose_word: Selector,
+    /// Byte swap gate for big-endian input words
+    s_swap_bytes: Selector,
+    /// HMAC ipad and opad gate for key words
+    s_key_pads: Selector,
 
     _marker: PhantomData<F>,
 }
@@ -50,7 +67,13 @@ impl<F: FieldExt> MessageScheduleConfig<F> {
         advice: Column<Advice>,
         s_decompose_word: Selector,
     ) -> Self {
+        let s_swap_bytes = meta.selector();
+        let s_key_pads = meta.selector();
+
         // Rename these here for ease of matching the gates to the spec
+        let a_0 = lookup.tag;
+        let a_1 = lookup.dense;
+        let a_2 = lookup.spread;
         let a_3 = advice;
 
         // s_decompose_word for all words
@@ -63,10 +86,68 @@ impl<F: FieldExt> MessageScheduleConfig<F> {
             Gate::s_decompose_word(s_decompose_word, lo, hi, word)
         });
 
+        // s_swap_bytes converts a big-endian word into the little-endian halves of X[i]
+        // s_swap_bytes | a_0   | a_1 |    a_2     |    a_3     |
+        //   1          | tag_0 | b_0 | spread_b_0 | word       |
+        //              | tag_1 | b_1 | spread_b_1 | swapped_lo |
+        //              | tag_2 | b_2 | spread_b_2 | swapped_hi |
+        //              | tag_3 | b_3 | spread_b_3 |            |
+        //
+        meta.create_gate("s_swap_bytes", |meta| {
+            let s_swap_bytes = meta.query_selector(s_swap_bytes);
+            let tags: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_0, Rotation(idx)))
+                .collect();
+            let bytes: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_1, Rotation(idx)))
+                .collect();
+            let word = meta.query_advice(a_3, Rotation(0));
+            let swapped_lo = meta.query_advice(a_3, Rotation(1));
+            let swapped_hi = meta.query_advice(a_3, Rotation(2));
+
+            Gate::s_swap_bytes(s_swap_bytes, tags, bytes, word, swapped_lo, swapped_hi)
+        });
+
+        // s_key_pads computes K XOR ipad and K XOR opad for an HMAC key word K, using
+        // spread(k) + spread(pad) = spread(k XOR pad) + 2 * spread(k AND pad)
+        // s_key_pads | a_0 |     a_1     |         a_2        | a_3  |
+        //   1        |     | k_lo        | spread_k_lo        | word |
+        //            |     | ipad_lo_xor | spread_ipad_lo_xor |      |
+        //            |     | ipad_lo_and | spread_ipad_lo_and |      |
+        //            |     | opad_lo_xor | spread_opad_lo_xor |      |
+        //            |     | opad_lo_and | spread_opad_lo_and |      |
+        //            |     | k_hi        | spread_k_hi        |      |
+        //            |     | ipad_hi_xor | spread_ipad_hi_xor |      |
+        //            |     | ipad_hi_and | spread_ipad_hi_and |      |
+        //            |     | opad_hi_xor | spread_opad_hi_xor |      |
+        //            |     | opad_hi_and | spread_opad_hi_and |      |
+        //
+        meta.create_gate("s_key_pads", |meta| {
+            let s_key_pads = meta.query_selector(s_key_pads);
+            let word = meta.query_advice(a_3, Rotation(0));
+            let k_lo = meta.query_advice(a_1, Rotation(0));
+            let k_hi = meta.query_advice(a_1, Rotation(KEY_PADS_ROWS as i32 / 2));
+            let spread: Vec<_> = (0..KEY_PADS_ROWS)
+                .map(|idx| meta.query_advice(a_2, Rotation(idx as i32)))
+                .collect();
+
+            Gate::s_key_pads(
+                s_key_pads,
+                word,
+                k_lo,
+                k_hi,
+                spread,
+                spread_u16(IPAD_HALF),
+                spread_u16(OPAD_HALF),
+            )
+        });
+
         MessageScheduleConfig {
             lookup,
             advice,
             s_decompose_word,
+            s_swap_bytes,
+            s_key_pads,
             _marker: PhantomData,
         }
     }
@@ -86,7 +167,8 @@ impl<F: FieldExt> MessageScheduleConfig<F> {
         let mut w_halves =
             Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
 
-        layouter.assign_region(
+        assign_region(
+            layouter,
             || "process message block",
             |mut region| {
                 w = Vec::<MessageWord<F>>::with_capacity(BLOCK_SIZE);
@@ -107,12 +189,331 @@ impl<F: FieldExt> MessageScheduleConfig<F> {
 
         Ok((w.try_into().unwrap(), w_halves.try_into().unwrap()))
     }
+
+    /// Builds the padded message block for a 32-byte message given as big-endian words,
+    /// such as a SHA-256 digest. The words are copied into the block and the padding is
+    /// constrained to be constant.
+    pub(super) fn process_be_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: [AssignedBits<32, F>; SHA256_DIGEST_SIZE],
+    ) -> Result<[(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE], Error> {
+        let mut w_halves =
+            Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+
+        assign_region(
+            layouter,
+            || "process 32-byte message block",
+            |mut region| {
+                w_halves =
+                    Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+                let mut row: usize = 0;
+
+                // X[0..8] are the byte-swapped input words
+                for word in input.iter() {
+                    w_halves.push(self.assign_swap_bytes(&mut region, word, row)?);
+                    row += 4; // swap_bytes requires 4 rows
+                }
+
+                // X[8..16] hold the padding of a 32-byte message
+                let block = pad_block(SHA256_DIGEST_SIZE, 4 * SHA256_DIGEST_SIZE);
+                let pad_words = &block[SHA256_DIGEST_SIZE..];
+                w_halves.extend(self.assign_pad_words(&mut region, pad_words, row)?);
+
+                Ok(())
+            },
+        )?;
+
+        Ok(w_halves.try_into().unwrap())
+    }
+
+    /// Builds the last padded message block of a `msg_len`-byte message ending with the
+    /// given little-endian digest words, such as the outer message of an HMAC. The words
+    /// are copied into the block and the padding is constrained to be constant.
+    pub(super) fn process_digest_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        input: [AssignedBits<32, F>; DIGEST_SIZE],
+        msg_len: usize,
+    ) -> Result<[(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE], Error> {
+        let mut w_halves =
+            Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+
+        assign_region(
+            layouter,
+            || "process digest message block",
+            |mut region| {
+                w_halves =
+                    Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+
+                // X[0..5] are the input words
+                for (word_idx, input_word) in input.iter().enumerate() {
+                    let (word, halves) = self.assign_msgblk_word_and_halves(
+                        &mut region,
+                        input_word.value_u32(),
+                        word_idx,
+                    )?;
+                    region.constrain_equal(word.cell(), input_word.cell())?;
+                    w_halves.push(halves);
+                }
+
+                // X[5..16] hold the padding
+                let block = pad_block(DIGEST_SIZE, msg_len);
+                let pad_words = &block[DIGEST_SIZE..];
+                w_halves.extend(self.assign_pad_words(
+                    &mut region,
+                    pad_words,
+                    get_word_row(DIGEST_SIZE),
+                )?);
+
+                Ok(())
+            },
+        )?;
+
+        Ok(w_halves.try_into().unwrap())
+    }
+
+    /// Assigns the given words without range checking them.
+    pub(super) fn witness_words(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[BlockWord],
+    ) -> Result<Vec<AssignedBits<32, F>>, Error> {
+        assign_region(
+            layouter,
+            || "witness words",
+            |mut region| {
+                words
+                    .iter()
+                    .enumerate()
+                    .map(|(row, word)| {
+                        Ok(AssignedBits::<32, F>::assign(
+                            &mut region,
+                            || format!("word {}", row),
+                            self.advice,
+                            row,
+                            word.0,
+                        )?)
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    /// Returns the message blocks K XOR ipad and K XOR opad of an HMAC for the key K,
+    /// given as at most 16 little-endian words. The key is padded with zero words.
+    pub(super) fn process_key_pads(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        key: &[AssignedBits<32, F>],
+    ) -> Result<
+        (
+            [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
+            [(AssignedBits<16, F>, AssignedBits<16, F>); BLOCK_SIZE],
+        ),
+        Error,
+    > {
+        if key.len() > BLOCK_SIZE {
+            return Err(Error::KeyLength { words: key.len() });
+        }
+
+        let mut ipad = Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+        let mut opad = Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+
+        assign_region(
+            layouter,
+            || "process hmac key pads",
+            |mut region| {
+                ipad = Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+                opad = Vec::<(AssignedBits<16, F>, AssignedBits<16, F>)>::with_capacity(BLOCK_SIZE);
+
+                for word_idx in 0..BLOCK_SIZE {
+                    let row = word_idx * KEY_PADS_ROWS;
+                    let word = match key.get(word_idx) {
+                        Some(word) => {
+                            word.copy_advice(
+                                || format!("K_{}", word_idx),
+                                &mut region,
+                                self.advice,
+                                row,
+                            )?;
+                            word.value_u32()
+                        }
+                        None => {
+                            region.assign_advice_from_constant(
+                                || format!("K_{}", word_idx),
+                                self.advice,
+                                row,
+                                F::zero(),
+                            )?;
+                            Value::known(0)
+                        }
+                    };
+
+                    let (ipad_halves, opad_halves) =
+                        self.assign_key_pads(&mut region, word, row)?;
+                    ipad.push(ipad_halves);
+                    opad.push(opad_halves);
+                }
+
+                Ok(())
+            },
+        )?;
+
+        Ok((ipad.try_into().unwrap(), opad.try_into().unwrap()))
+    }
+
+    // Assigns the given constant padding words from `row` onwards
+    fn assign_pad_words(
+        &self,
+        region: &mut Region<'_, F>,
+        pad_words: &[u32],
+        mut row: usize,
+    ) -> Result<Vec<(AssignedBits<16, F>, AssignedBits<16, F>)>, Error> {
+        let mut w_halves = Vec::with_capacity(pad_words.len());
+        for pad_word in pad_words.iter() {
+            self.s_decompose_word.enable(region, row)?;
+            let (word, (lo, hi)) = self.assign_word_and_halves(
+                || format!("X_{}", BLOCK_SIZE - pad_words.len() + w_halves.len()),
+                region,
+                &self.lookup,
+                self.advice,
+                Value::known(*pad_word),
+                row,
+            )?;
+            region.constrain_constant(word.cell(), F::from(*pad_word as u64))?;
+            w_halves.push((lo.dense, hi.dense));
+            row += DECOMPOSE_WORD_ROWS;
+        }
+
+        Ok(w_halves)
+    }
+
+    // See the s_key_pads gate for the layout
+    fn assign_key_pads(
+        &self,
+        region: &mut Region<'_, F>,
+        word: Value<u32>,
+        row: usize,
+    ) -> Result<
+        (
+            (AssignedBits<16, F>, AssignedBits<16, F>),
+            (AssignedBits<16, F>, AssignedBits<16, F>),
+        ),
+        Error,
+    > {
+        self.s_key_pads.enable(region, row)?;
+
+        let spread_word =
+            |value: Value<u16>| value.map(|v| SpreadWord::<16, 32>::new(i2lebsp(v.into())));
+
+        let mut ipad = Vec::with_capacity(2);
+        let mut opad = Vec::with_capacity(2);
+        for half_idx in 0..2 {
+            let half_row = row + half_idx * KEY_PADS_ROWS / 2;
+            let k = word.map(|w| (w >> (16 * half_idx)) as u16);
+            SpreadVar::with_lookup(region, &self.lookup, half_row, spread_word(k))?;
+
+            for (pad_idx, (pad, halves)) in [(IPAD_HALF, &mut ipad), (OPAD_HALF, &mut opad)]
+                .into_iter()
+                .enumerate()
+            {
+                let pad_row = half_row + 1 + 2 * pad_idx;
+                let xor = SpreadVar::with_lookup(
+                    region,
+                    &self.lookup,
+                    pad_row,
+                    spread_word(k.map(|k| k ^ pad)),
+                )?;
+                SpreadVar::with_lookup(
+                    region,
+                    &self.lookup,
+                    pad_row + 1,
+                    spread_word(k.map(|k| k & pad)),
+                )?;
+                halves.push(xor.dense);
+            }
+        }
+
+        let [ipad_lo, ipad_hi]: [AssignedBits<16, F>; 2] = ipad.try_into().unwrap();
+        let [opad_lo, opad_hi]: [AssignedBits<16, F>; 2] = opad.try_into().unwrap();
+        Ok(((ipad_lo, ipad_hi), (opad_lo, opad_hi)))
+    }
+
+    // s_swap_bytes | a_0   | a_1 |    a_2     |    a_3     |
+    //   1          | tag_0 | b_0 | spread_b_0 | word       |
+    //              | tag_1 | b_1 | spread_b_1 | swapped_lo |
+    //              | tag_2 | b_2 | spread_b_2 | swapped_hi |
+    //              | tag_3 | b_3 | spread_b_3 |            |
+    fn assign_swap_bytes(
+        &self,
+        region: &mut Region<'_, F>,
+        word: &AssignedBits<32, F>,
+        row: usize,
+    ) -> Result<(AssignedBits<16, F>, AssignedBits<16, F>), Error> {
+        let a_3 = self.advice;
+
+        self.s_swap_bytes.enable(region, row)?;
+
+        word.copy_advice(|| "word", region, a_3, row)?;
+
+        // Lookup the little-endian bytes of the word
+        let word = word.value_u32();
+        for idx in 0..4 {
+            let byte: Value<[bool; 8]> = word.map(|w| i2lebsp(((w >> (8 * idx)) & 0xff).into()));
+            SpreadVar::with_lookup(
+                region,
+                &self.lookup,
+                row + idx,
+                byte.map(SpreadWord::<8, 16>::new),
+            )?;
+        }
+
+        let swapped = word.map(|w| w.swap_bytes());
+        let swapped_lo = AssignedBits::<16, F>::assign(
+            region,
+            || "swapped_lo",
+            a_3,
+            row + 1,
+            swapped.map(|w| w as u16),
+        )?;
+        let swapped_hi = AssignedBits::<16, F>::assign(
+            region,
+            || "swapped_hi",
+            a_3,
+            row + 2,
+            swapped.map(|w| (w >> 16) as u16),
+        )?;
+
+        Ok((swapped_lo, swapped_hi))
+    }
+}
+
+/// Returns the padded last block of a `msg_len`-byte message whose last `msg_words`
+/// words are in this block, with zeroes in place of the message words.
+fn pad_block(msg_words: usize, msg_len: usize) -> [u32; BLOCK_SIZE] {
+    assert_eq!(msg_len % 4, 0);
+    assert!(msg_words < BLOCK_SIZE - 2);
+
+    let mut block = [0; BLOCK_SIZE];
+    // The 0x80 byte right after the message
+    block[msg_words] = 0x80;
+    // The message length in bits as a little-endian 64-bit integer
+    let msg_len_in_bits = (msg_len as u64) << 3;
+    block[BLOCK_SIZE - 2] = msg_len_in_bits as u32;
+    block[BLOCK_SIZE - 1] = (msg_len_in_bits >> 32) as u32;
+    block
+}
+
+/// Returns the spread form of a 16-bit value
+fn spread_u16(value: u16) -> u64 {
+    lebs2ip(&spread_bits::<16, 32>(i2lebsp::<16>(value.into())))
 }
 
 /// Returns row number of a word
 pub fn get_word_row(word_idx: usize) -> usize {
     assert!(word_idx <= BLOCK_SIZE);
-    word_idx * BLOCK_SIZE
+    word_idx * DECOMPOSE_WORD_ROWS
 }
 
 impl<F: FieldExt> MessageScheduleConfig<F> {
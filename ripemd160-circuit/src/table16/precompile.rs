@@ -0,0 +1,324 @@
+//! The RIPEMD-160 precompile, through which the EVM circuit looks up the digest of the
+//! input of each call.
+//!
+//! Each row of [`Ripemd160Table`] holds a call as the RLCs of its input and of its digest,
+//! which are copied from the RLCs assigned by [`Table16Chip::digest_batch_with_rlc`]. The
+//! chip lays out the bytes of each input for its length, so the rows and the lengths of
+//! the inputs are fixed columns, set when the keys are generated.
+//!
+//! The public inputs of a call are the words of the padded blocks of its input followed
+//! by the five words of its digest, as little-endian 32-bit integers.
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Cell, Layouter, Value},
+    plonk::{self, Advice, Any, Challenge, Column, ConstraintSystem, Fixed, SecondPhase},
+};
+use precompile_circuit::PrecompileCircuit;
+
+use super::{
+    cost::{Columns, CostReport},
+    rlc::WORD_BYTES_ROWS,
+    util::{convert_byte_slice_to_blockword_slice, convert_byte_slice_to_u32_slice},
+    BlockWord, RlcDigest, SpreadTable, Table16Chip, Table16Config,
+};
+use crate::{
+    constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES, DIGEST_SIZE, DIGEST_SIZE_BYTES},
+    error::assign_region,
+    native::{hash, pad_message_bytes},
+    Error,
+};
+
+/// The columns through which other circuits look up the calls to the RIPEMD-160
+/// precompile, along with the spread table and the challenge used by the chip
+#[derive(Clone, Debug)]
+pub struct Ripemd160Table {
+    /// Whether the row holds a call
+    pub q_enable: Column<Fixed>,
+    /// The length of the input in bytes
+    pub input_len: Column<Fixed>,
+    /// The RLC of the bytes of the input
+    pub input_rlc: Column<Advice>,
+    /// The RLC of the 20 bytes of the digest
+    pub output_rlc: Column<Advice>,
+    /// The spread table looked up by the chip, which may be shared with other chips. The
+    /// circuit that configures it loads it with [`SpreadTable::load`].
+    pub spread_table: SpreadTable,
+    /// The challenge of the RLCs, which should be the one of the EVM circuit
+    pub challenge: Challenge,
+}
+
+impl Ripemd160Table {
+    /// Configures the columns of the table. The challenge must be usable after the first
+    /// phase.
+    pub fn construct<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        spread_table: SpreadTable,
+        challenge: Challenge,
+    ) -> Self {
+        let input_rlc = meta.advice_column_in(SecondPhase);
+        let output_rlc = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(input_rlc);
+        meta.enable_equality(output_rlc);
+
+        Self {
+            q_enable: meta.fixed_column(),
+            input_len: meta.fixed_column(),
+            input_rlc,
+            output_rlc,
+            spread_table,
+            challenge,
+        }
+    }
+
+    pub fn columns(&self) -> Vec<Column<Any>> {
+        vec![
+            self.q_enable.into(),
+            self.input_len.into(),
+            self.input_rlc.into(),
+            self.output_rlc.into(),
+        ]
+    }
+
+    pub fn annotations(&self) -> Vec<String> {
+        ["q_enable", "input_len", "input_rlc", "output_rlc"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    /// Assigns a row for each call, given the length of its input and its RLCs
+    fn assign<F: FieldExt>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        calls: &[(usize, RlcDigest<F>)],
+    ) -> Result<(), Error> {
+        assign_region(
+            layouter,
+            || "ripemd160 table",
+            |mut region| {
+                for (row, (len, digest)) in calls.iter().enumerate() {
+                    region.assign_fixed(
+                        || "q_enable",
+                        self.q_enable,
+                        row,
+                        || Value::known(F::one()),
+                    )?;
+                    region.assign_fixed(
+                        || "input_len",
+                        self.input_len,
+                        row,
+                        || Value::known(F::from(*len as u64)),
+                    )?;
+                    digest.input_rlc.copy_advice(
+                        || "input_rlc",
+                        &mut region,
+                        self.input_rlc,
+                        row,
+                    )?;
+                    digest.digest_rlc.copy_advice(
+                        || "output_rlc",
+                        &mut region,
+                        self.output_rlc,
+                        row,
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Rows taken by the RLC of `len` bytes
+fn rlc_rows(len: usize) -> usize {
+    // The RLC of no bytes is a single constant
+    if len == 0 {
+        1
+    } else {
+        (len + WORD_BYTES_ROWS - 1) / WORD_BYTES_ROWS * WORD_BYTES_ROWS
+    }
+}
+
+impl<F: FieldExt> PrecompileCircuit<F> for Table16Config<F> {
+    type Table = Ripemd160Table;
+    /// The message
+    type Witness = Vec<u8>;
+    /// The digest
+    type Output = [u8; DIGEST_SIZE_BYTES];
+
+    fn configure(meta: &mut ConstraintSystem<F>, table: Self::Table) -> Self {
+        let mut config = Table16Chip::configure_with_spread_table(
+            meta,
+            &table.spread_table,
+            Some(table.challenge),
+        );
+        config.table = Some(table);
+        config
+    }
+
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        witnesses: &[Self::Witness],
+    ) -> Result<Vec<Cell>, plonk::Error> {
+        let table = self.table.as_ref().ok_or(Error::TableNotConfigured)?;
+        let chip = Table16Chip::construct(self.clone());
+
+        let messages: Vec<(Vec<[BlockWord; BLOCK_SIZE]>, usize)> = witnesses
+            .iter()
+            .map(|input| {
+                let blocks = pad_message_bytes(input.clone())
+                    .into_iter()
+                    .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                    .collect::<Result<_, _>>()?;
+                Ok((blocks, input.len()))
+            })
+            .collect::<Result<_, Error>>()?;
+        let digests = chip.digest_batch_with_rlc(layouter, &messages)?;
+        let cells = digests
+            .iter()
+            .flat_map(|digest| digest.words.iter().chain(&digest.digest))
+            .map(|word| word.cell())
+            .collect();
+
+        let calls: Vec<_> = messages.iter().map(|(_, len)| *len).zip(digests).collect();
+        table.assign(layouter, &calls)?;
+        Ok(cells)
+    }
+
+    fn public_inputs(witness: &Self::Witness) -> Vec<F> {
+        let digest: [u32; DIGEST_SIZE] =
+            convert_byte_slice_to_u32_slice(hash(witness.clone())).expect("the digest is 5 words");
+        pad_message_bytes(witness.clone())
+            .into_iter()
+            .flat_map(|block| {
+                convert_byte_slice_to_u32_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>(block)
+                    .expect("a block is 16 words")
+            })
+            .chain(digest)
+            .map(|word| F::from(word as u64))
+            .collect()
+    }
+
+    fn min_rows(witnesses: &[Self::Witness]) -> usize {
+        let blocks: Vec<_> = witnesses
+            .iter()
+            .map(|input| (input.len() + 8) / BLOCK_SIZE_BYTES + 1)
+            .collect();
+        let report = CostReport::batch::<F>(&blocks);
+
+        // The RLCs of the inputs and digests follow the batch, which takes as many rows in
+        // both sets of columns
+        let batch_rows = report
+            .column_rows(Columns::Main)
+            .max(report.column_rows(Columns::Right));
+        let rlcs: usize = witnesses
+            .iter()
+            .map(|input| rlc_rows(input.len()) + rlc_rows(DIGEST_SIZE_BYTES))
+            .sum();
+        let empty_inputs = witnesses.iter().filter(|input| input.is_empty()).count();
+
+        [
+            report.rows(),
+            batch_rows + rlcs,
+            report.total().constants + empty_inputs,
+            witnesses.len(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+
+    fn compute(witness: &Self::Witness) -> Self::Output {
+        hash(witness.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem, Error, FirstPhase},
+    };
+    use precompile_circuit::PrecompileCircuit;
+
+    use super::Ripemd160Table;
+    use crate::{
+        constants::{BLOCK_SIZE, DIGEST_SIZE},
+        dev::INPUTS_OUTPUTS,
+        table16::{SpreadTable, Table16Config, SPREAD_TABLE_ROWS},
+    };
+
+    /// Hashes the inputs through [`PrecompileCircuit`]
+    struct PrecompileTestCircuit {
+        inputs: Vec<Vec<u8>>,
+    }
+
+    impl Circuit<Fr> for PrecompileTestCircuit {
+        type Config = Table16Config<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            PrecompileTestCircuit {
+                inputs: self.inputs.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            let spread_table = SpreadTable::configure(meta);
+            let table = Ripemd160Table::construct(meta, spread_table, challenge);
+            <Table16Config<Fr> as PrecompileCircuit<Fr>>::configure(meta, table)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let table = config.table.as_ref().unwrap();
+            table.spread_table.load(&mut layouter)?;
+            config.assign(&mut layouter, &self.inputs)?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_compute() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        for (input, output) in inputs.iter().zip(outputs) {
+            assert_eq!(Table16Config::<Fr>::compute(input), output.0);
+        }
+    }
+
+    #[test]
+    fn test_public_inputs() {
+        // "abc" takes a block, whose first word holds the bytes of the input and the 0x80 of
+        // the padding
+        let public_inputs = Table16Config::<Fr>::public_inputs(&b"abc".to_vec());
+        assert_eq!(public_inputs.len(), BLOCK_SIZE + DIGEST_SIZE);
+        assert_eq!(public_inputs[0], Fr::from(0x80636261));
+
+        let digest = Table16Config::<Fr>::compute(&b"abc".to_vec());
+        let word = u32::from_le_bytes(digest[..4].try_into().unwrap());
+        assert_eq!(public_inputs[BLOCK_SIZE], Fr::from(word as u64));
+    }
+
+    #[test]
+    fn test_min_rows() {
+        // A few messages fit next to the spread table
+        let (inputs, _) = INPUTS_OUTPUTS.clone();
+        assert_eq!(Table16Config::<Fr>::min_rows(&inputs), SPREAD_TABLE_ROWS);
+
+        let inputs = vec![vec![0; 55]; 40];
+        assert!(Table16Config::<Fr>::min_rows(&inputs) > SPREAD_TABLE_ROWS);
+    }
+
+    #[test]
+    fn test_precompile() {
+        let (inputs, _) = INPUTS_OUTPUTS.clone();
+        let prover = MockProver::run(17, &PrecompileTestCircuit { inputs }, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
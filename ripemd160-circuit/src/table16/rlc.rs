@@ -0,0 +1,436 @@
+//! Random linear combinations (RLC) of the bytes of messages and digests, so that a
+//! lookup table of the EVM circuit can hold a variable-length input as one field element.
+//!
+//! The RLC of the bytes `b_0, ..., b_{n-1}` with the challenge `r` is
+//! `b_0 * r^{n-1} + b_1 * r^{n-2} + ... + b_{n-1}`, which is how the EVM circuit combines
+//! call data. It is assigned in a second-phase advice column, since `r` is only known
+//! after the first-phase columns have been committed.
+
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{AssignedCell, Layouter, Value},
+    plonk::{Advice, Challenge, Column, ConstraintSystem, SecondPhase, Selector},
+    poly::Rotation,
+};
+
+use super::{gates::Gate, i2lebsp, AssignedBits, SpreadInputs, SpreadVar, SpreadWord};
+use crate::{error::assign_region, Error};
+
+pub use bitwise_gadgets::rlc::{bytes_rlc, WORD_BYTES_ROWS};
+
+#[derive(Clone, Debug)]
+pub(super) struct RlcConfig<F: FieldExt> {
+    lookup: SpreadInputs,
+    advice: Column<Advice>,
+    rlc: Column<Advice>,
+    challenge: Challenge,
+    s_word_bytes: Selector,
+    s_rlc_first: Selector,
+    s_rlc: Selector,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> RlcConfig<F> {
+    /// Configures the RLC region, using `challenge` as the randomness. The challenge must
+    /// be usable after the first phase.
+    pub(super) fn configure(
+        meta: &mut ConstraintSystem<F>,
+        lookup: SpreadInputs,
+        advice: Column<Advice>,
+        challenge: Challenge,
+    ) -> Self {
+        let rlc = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(rlc);
+
+        let s_word_bytes = meta.selector();
+        let s_rlc_first = meta.selector();
+        let s_rlc = meta.selector();
+
+        // Rename these here for ease of matching the gates to the spec
+        let a_0 = lookup.tag;
+        let a_1 = lookup.dense;
+        let a_3 = advice;
+
+        // s_word_bytes decomposes a word into its little-endian bytes, and s_rlc_first and
+        // s_rlc add each byte to the RLC of the bytes before it
+        // s_word_bytes | a_0   | a_1 |    a_2     | a_3  | rlc   |
+        //   1          | tag_0 | b_0 | spread_b_0 | word | rlc_0 |
+        //              | tag_1 | b_1 | spread_b_1 |      | rlc_1 |
+        //              | tag_2 | b_2 | spread_b_2 |      | rlc_2 |
+        //              | tag_3 | b_3 | spread_b_3 |      | rlc_3 |
+        //
+        meta.create_gate("s_word_bytes", |meta| {
+            let s_word_bytes = meta.query_selector(s_word_bytes);
+            let tags: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_0, Rotation(idx)))
+                .collect();
+            let bytes: Vec<_> = (0..4)
+                .map(|idx| meta.query_advice(a_1, Rotation(idx)))
+                .collect();
+            let word = meta.query_advice(a_3, Rotation::cur());
+
+            Gate::s_word_bytes(s_word_bytes, tags, bytes, word)
+        });
+
+        meta.create_gate("s_rlc_first", |meta| {
+            let s_rlc_first = meta.query_selector(s_rlc_first);
+            let byte = meta.query_advice(a_1, Rotation::cur());
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+
+            Gate::s_rlc_first(s_rlc_first, byte, rlc)
+        });
+
+        meta.create_gate("s_rlc", |meta| {
+            let s_rlc = meta.query_selector(s_rlc);
+            let rlc_prev = meta.query_advice(rlc, Rotation::prev());
+            let challenge = meta.query_challenge(challenge);
+            let byte = meta.query_advice(a_1, Rotation::cur());
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+
+            Gate::s_rlc(s_rlc, rlc_prev, challenge, byte, rlc)
+        });
+
+        RlcConfig {
+            lookup,
+            advice,
+            rlc,
+            challenge,
+            s_word_bytes,
+            s_rlc_first,
+            s_rlc,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assigns the RLC of the first `len` bytes of `words`, taking the bytes of each word
+    /// in little-endian order. The words are copy constrained into the region.
+    ///
+    /// If `padded`, the words are the padded blocks of a message of `len` bytes, and the
+    /// bytes after the message are constrained to its padding, so that the RLC is the one
+    /// of the hashed message.
+    pub(super) fn assign_rlc(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        words: &[AssignedBits<32, F>],
+        len: usize,
+        padded: bool,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        assert!(len <= words.len() * WORD_BYTES_ROWS);
+        let challenge = layouter.get_challenge(self.challenge);
+        let padded_len = words.len() * WORD_BYTES_ROWS;
+
+        assign_region(
+            layouter,
+            || "rlc",
+            |mut region| {
+                let mut rlc = Value::known(F::zero());
+                let mut rlc_cell = None;
+                let num_words = if padded {
+                    words.len()
+                } else {
+                    (len + WORD_BYTES_ROWS - 1) / WORD_BYTES_ROWS
+                };
+                for (word_idx, word) in words[..num_words].iter().enumerate() {
+                    let row = word_idx * WORD_BYTES_ROWS;
+                    self.s_word_bytes.enable(&mut region, row)?;
+                    word.copy_advice(
+                        || format!("word_{}", word_idx),
+                        &mut region,
+                        self.advice,
+                        row,
+                    )?;
+
+                    // Lookup the little-endian bytes of the word
+                    let word = word.value_u32();
+                    for idx in 0..WORD_BYTES_ROWS {
+                        let byte = word.map(|w| (w >> (8 * idx)) & 0xff);
+                        let byte_var = SpreadVar::with_lookup(
+                            &mut region,
+                            &self.lookup,
+                            row + idx,
+                            byte.map(|b| SpreadWord::<8, 16>::new(i2lebsp(b.into()))),
+                        )?;
+
+                        // Bytes past the end of the message are its padding, or only
+                        // complete the word
+                        let byte_idx = row + idx;
+                        if byte_idx >= len {
+                            if padded {
+                                let padding = padding_byte(len, padded_len, byte_idx);
+                                region.constrain_constant(
+                                    byte_var.dense.cell(),
+                                    F::from(padding as u64),
+                                )?;
+                            }
+                            continue;
+                        }
+                        if byte_idx == 0 {
+                            self.s_rlc_first.enable(&mut region, byte_idx)?;
+                        } else {
+                            self.s_rlc.enable(&mut region, byte_idx)?;
+                        }
+                        rlc = rlc * challenge + byte.map(|b| F::from(b as u64));
+                        rlc_cell = Some(region.assign_advice(
+                            || format!("rlc_{}", byte_idx),
+                            self.rlc,
+                            byte_idx,
+                            || rlc,
+                        )?);
+                    }
+                }
+
+                // The RLC of no bytes is zero
+                match rlc_cell {
+                    Some(rlc_cell) => Ok(rlc_cell),
+                    None => {
+                        Ok(region.assign_advice_from_constant(|| "rlc", self.rlc, 0, F::zero())?)
+                    }
+                }
+            },
+        )
+    }
+}
+
+/// Returns the byte at `idx` of the padding of a `len`-byte message to `padded_len`
+/// bytes: a 0x80 byte, zero bytes and the bit length of the message as 8 little-endian
+/// bytes
+fn padding_byte(len: usize, padded_len: usize, idx: usize) -> u8 {
+    let length_start = padded_len - 8;
+    if idx >= length_start {
+        ((len as u64 * 8) >> (8 * (idx - length_start))) as u8
+    } else if idx == len {
+        0x80
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+        plonk::{Challenge, Circuit, ConstraintSystem, Error as PlonkError, FirstPhase},
+    };
+
+    use super::{bytes_rlc, padding_byte};
+    use crate::{
+        constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES},
+        dev::INPUTS_OUTPUTS,
+        native::pad_message_bytes,
+        table16::{
+            util::convert_byte_slice_to_blockword_slice, BlockWord, Table16Chip, Table16Config,
+        },
+        Error,
+    };
+
+    struct RlcTestCircuit {
+        inputs: Vec<Vec<u8>>,
+        outputs: Vec<[u8; 20]>,
+    }
+
+    impl Circuit<Fr> for RlcTestCircuit {
+        type Config = (Table16Config<Fr>, Challenge);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            RlcTestCircuit {
+                inputs: self.inputs.clone(),
+                outputs: self.outputs.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            (Table16Chip::configure_with_rlc(meta, challenge), challenge)
+        }
+
+        fn synthesize(
+            &self,
+            (config, challenge): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), PlonkError> {
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let messages: Vec<(Vec<[BlockWord; BLOCK_SIZE]>, usize)> = self
+                .inputs
+                .iter()
+                .map(|input| {
+                    let blocks = pad_message_bytes(input.clone())
+                        .into_iter()
+                        .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                        .collect::<Result<_, _>>()?;
+                    Ok((blocks, input.len()))
+                })
+                .collect::<Result<_, Error>>()?;
+
+            // A length that does not pad to the given blocks is rejected
+            let (blocks, len) = messages[0].clone();
+            assert!(matches!(
+                chip.digest_batch_with_rlc(&mut layouter, &[(blocks, len + BLOCK_SIZE_BYTES)]),
+                Err(Error::MessageLength { .. })
+            ));
+
+            let rlcs = chip.digest_batch_with_rlc(&mut layouter, &messages)?;
+            let challenge = layouter.get_challenge(challenge);
+            for ((rlc, input), output) in rlcs.iter().zip(&self.inputs).zip(&self.outputs) {
+                rlc.input_rlc
+                    .value()
+                    .zip(challenge)
+                    .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(input, *r));
+                rlc.digest_rlc
+                    .value()
+                    .zip(challenge)
+                    .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(output, *r));
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bytes_rlc() {
+        let r = Fr::from(256);
+        assert_eq!(bytes_rlc(&[], r), Fr::zero());
+        assert_eq!(bytes_rlc(&[0x12, 0x34, 0x56], r), Fr::from(0x12_3456));
+    }
+
+    #[test]
+    fn test_rlc() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        let circuit = RlcTestCircuit {
+            inputs,
+            outputs: outputs.iter().map(|output| output.0).collect(),
+        };
+
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    /// Hashes a message with the word `word_idx` of its padded blocks replaced by `word`,
+    /// keeping its length
+    struct ForgedPaddingCircuit {
+        input: Vec<u8>,
+        word_idx: usize,
+        word: u32,
+    }
+
+    impl Circuit<Fr> for ForgedPaddingCircuit {
+        type Config = Table16Config<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            ForgedPaddingCircuit {
+                input: self.input.clone(),
+                word_idx: self.word_idx,
+                word: self.word,
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            Table16Chip::configure_with_rlc(meta, challenge)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), PlonkError> {
+            let chip = Table16Chip::construct(config.clone());
+            Table16Chip::load(config, &mut layouter)?;
+
+            let mut blocks: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.input.clone())
+                .into_iter()
+                .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+                .collect::<Result<_, _>>()?;
+            blocks[self.word_idx / BLOCK_SIZE][self.word_idx % BLOCK_SIZE] =
+                BlockWord(Value::known(self.word));
+            chip.digest_batch_with_rlc(&mut layouter, &[(blocks, self.input.len())])?;
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_padding_byte() {
+        let padded: Vec<u8> = (0..BLOCK_SIZE_BYTES)
+            .map(|idx| padding_byte(3, BLOCK_SIZE_BYTES, idx))
+            .collect();
+        assert_eq!(&padded[3..], &pad_message_bytes(b"abc".to_vec())[0][3..]);
+    }
+
+    #[test]
+    fn test_forged_padding() {
+        let input = b"abc".to_vec();
+        for (word_idx, word) in [
+            // The 0x80 byte after the message
+            (0, u32::from_le_bytes(*b"abc\0")),
+            // A zero word
+            (5, 0x8000_0000),
+            // The bit length
+            (14, 0),
+        ] {
+            let circuit = ForgedPaddingCircuit {
+                input: input.clone(),
+                word_idx,
+                word,
+            };
+            let failures = MockProver::run(17, &circuit, vec![])
+                .unwrap()
+                .verify()
+                .unwrap_err();
+            assert!(failures
+                .iter()
+                .any(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+        }
+
+        // The honest padding
+        let circuit = ForgedPaddingCircuit {
+            input,
+            word_idx: 14,
+            word: 24,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_rlc_not_configured() {
+        struct NoRlcCircuit;
+
+        impl Circuit<Fr> for NoRlcCircuit {
+            type Config = Table16Config<Fr>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                NoRlcCircuit
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+                Table16Chip::configure(meta)
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fr>,
+            ) -> Result<(), PlonkError> {
+                let chip = Table16Chip::construct(config.clone());
+                Table16Chip::load(config, &mut layouter)?;
+                assert!(matches!(
+                    chip.digest_batch_with_rlc(&mut layouter, &[]),
+                    Err(Error::RlcNotConfigured)
+                ));
+                Ok(())
+            }
+        }
+
+        let prover = MockProver::run(17, &NoRlcCircuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
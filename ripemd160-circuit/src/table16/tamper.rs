@@ -0,0 +1,200 @@
+//! Soundness tests of the gates of [`Table16Chip`], which override cells of an honest
+//! witness through [`bitwise_gadgets::tamper`] and check that the mock prover rejects it.
+
+use bitwise_gadgets::tamper::harness::{
+    failed_constraint, failed_gate, failed_lookup, failed_permutation, verify_tampered, Tamper,
+};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    halo2curves::FieldExt,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+
+use crate::constants::{BLOCK_SIZE, BLOCK_SIZE_BYTES};
+use crate::native::pad_message_bytes;
+use crate::table16::{
+    util::convert_byte_slice_to_blockword_slice, BlockWord, Table16Chip, Table16Config,
+};
+use crate::RIPEMD160;
+
+const K: u32 = 17;
+
+/// Hashes a message without checking the digest, which tampered cells may change
+struct HashCircuit(Vec<u8>);
+
+impl<F: FieldExt> Circuit<F> for HashCircuit {
+    type Config = Table16Config<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        HashCircuit(vec![])
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        Table16Chip::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = Table16Chip::construct(config.clone());
+        Table16Chip::load(config, &mut layouter)?;
+
+        let data: Vec<[BlockWord; BLOCK_SIZE]> = pad_message_bytes(self.0.clone())
+            .into_iter()
+            .map(convert_byte_slice_to_blockword_slice::<BLOCK_SIZE_BYTES, BLOCK_SIZE>)
+            .collect::<Result<_, _>>()?;
+        RIPEMD160::digest(chip, layouter.namespace(|| "digest"), &data)?;
+
+        Ok(())
+    }
+}
+
+fn circuit() -> HashCircuit {
+    HashCircuit(b"abc".to_vec())
+}
+
+#[test]
+fn test_honest_witness() {
+    assert!(verify_tampered(K, &circuit(), vec![]).is_empty());
+}
+
+// (gate, carry cell, range check, sum check, largest carry of an honest sum)
+const CARRIES: [(&str, &str, &str, &str, u64); 4] = [
+    // The bits of (B & C) and (!B & D) never overlap
+    ("s_f2f4", "f2f4_carry", "range_check_carry", "sum_f2f4", 0),
+    // A + F + X + K
+    (
+        "s_step",
+        "carry_sum",
+        "range_check_carry_sum",
+        "sum_afxk",
+        3,
+    ),
+    // rotate_left(A + F + X + K, s) + E
+    ("s_step", "carry_t", "range_check_carry_t", "t", 1),
+    // h + left + right
+    (
+        "s_sum_combine_ilr",
+        "sum_combine_ilr_carry",
+        "range_check_carry",
+        "sum_re",
+        2,
+    ),
+];
+
+// A carry that an honest sum can reach must only be rejected by the sum check, and the
+// next value must be rejected by the range check, so that range checks match the
+// number of words added.
+#[test]
+fn test_carry_ranges() {
+    for (gate, carry, range_check, sum_check, max) in CARRIES {
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::set(carry, 0, max)]);
+        assert!(
+            !failed_constraint(&failures, gate, range_check),
+            "{} rejects the honest carry {}",
+            range_check,
+            max
+        );
+
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::set(carry, 0, max + 1)]);
+        assert!(
+            failed_constraint(&failures, gate, range_check),
+            "{} accepts the carry {}",
+            range_check,
+            max + 1
+        );
+        assert!(failed_constraint(&failures, gate, sum_check));
+    }
+}
+
+#[test]
+fn test_tampered_carries() {
+    for (gate, carry, _, sum_check, _) in CARRIES {
+        for occurrence in [0, 4] {
+            let failures = verify_tampered(K, &circuit(), vec![Tamper::xor(carry, occurrence, 1)]);
+            assert!(failed_constraint(&failures, gate, sum_check), "{}", carry);
+        }
+    }
+}
+
+#[test]
+fn test_tampered_spread_values() {
+    for spread in ["spread_sum_lo", "spread_t_hi", "spread_rol10_t_lo"] {
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::xor(spread, 3, 1 << 2)]);
+        assert!(failed_lookup(&failures), "{}", spread);
+    }
+}
+
+#[test]
+fn test_tampered_rotate_limbs() {
+    for limb in ["sum_a", "sum_b", "r_a", "r_b"] {
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::xor(limb, 5, 1)]);
+        assert!(failed_gate(&failures, "s_step"), "{}", limb);
+    }
+    for limb in ["rol_a", "rol_b"] {
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::xor(limb, 5, 1)]);
+        assert!(failed_gate(&failures, "s_rotate_left"), "{}", limb);
+    }
+}
+
+#[test]
+fn test_tampered_iv() {
+    for word in ["assign iv[0]", "assign iv[4]"] {
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::xor(word, 0, 1 << 31)]);
+        assert!(failed_permutation(&failures), "{}", word);
+    }
+}
+
+// The round constant K in the coeffs column of a step, as if the fixed column had been
+// mis-configured, while the witness adds the right constant
+#[test]
+fn test_tampered_round_constant() {
+    for occurrence in [0, 17, 42, 100, 159] {
+        let failures = verify_tampered(K, &circuit(), vec![Tamper::xor("k", occurrence, 1)]);
+        assert!(failed_constraint(&failures, "s_step", "sum_afxk"));
+    }
+}
+
+// The sum A + F + X + K moved by one with its spread kept consistent, so that only the
+// sum check can reject it
+#[test]
+fn test_tampered_sum_with_consistent_spread() {
+    for occurrence in [0, 17, 42, 100, 159] {
+        let failures = verify_tampered(
+            K,
+            &circuit(),
+            vec![
+                Tamper::xor("sum_lo", occurrence, 1),
+                Tamper::xor("spread_sum_lo", occurrence, 1),
+            ],
+        );
+        assert!(!failed_lookup(&failures));
+        assert!(failed_constraint(&failures, "s_step", "sum_afxk"));
+    }
+}
+
+// The spread word of rotate_left(C, 10) in the first rounds of a line, which must be
+// the rotated word
+#[test]
+fn test_tampered_rol10_spread_word() {
+    for (half, spread) in [
+        ("rol10_c_lo", "spread_rol10_c_lo"),
+        ("rol10_c_hi", "spread_rol10_c_hi"),
+    ] {
+        for occurrence in [0, 3] {
+            let failures = verify_tampered(
+                K,
+                &circuit(),
+                vec![
+                    Tamper::xor(half, occurrence, 1),
+                    Tamper::xor(spread, occurrence, 1),
+                ],
+            );
+            assert!(!failed_lookup(&failures), "{}", half);
+            assert!(failed_permutation(&failures), "{}", half);
+        }
+    }
+}
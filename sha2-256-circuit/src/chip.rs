@@ -0,0 +1,312 @@
+//! The SHA-256 compression function, built on the XOR, AND, NOT, rotations, shifts and
+//! modular addition of [`BitwiseChip`].
+//!
+//! Each block is compressed by extending its 16 words to the 64 words of the message
+//! schedule, and by running the 64 rounds over the state. Every operation takes its own
+//! region of the bitwise chip, so the rows taken by a block are fixed.
+
+use bitwise_gadgets::{
+    BitwiseChip, Error, Word32, ADD_ROWS, ROTATE_ROWS, SHIFT_ROWS, WORD_ROWS, XOR_AND_ROWS,
+};
+use halo2_proofs::{arithmetic::FieldExt, circuit::Layouter};
+
+/// Number of words in a block
+pub const BLOCK_SIZE: usize = 16;
+/// Number of bytes in a block
+pub const BLOCK_SIZE_BYTES: usize = 4 * BLOCK_SIZE;
+/// Number of words in a digest
+pub const DIGEST_SIZE: usize = 8;
+/// Number of bytes in a digest
+pub const DIGEST_SIZE_BYTES: usize = 4 * DIGEST_SIZE;
+/// Number of rounds of the compression function
+pub const ROUNDS: usize = 64;
+
+/// The initial state, which is the first 32 bits of the fractional parts of the square
+/// roots of the first 8 primes
+pub const IV: [u32; DIGEST_SIZE] = [
+    0x6a09_e667,
+    0xbb67_ae85,
+    0x3c6e_f372,
+    0xa54f_f53a,
+    0x510e_527f,
+    0x9b05_688c,
+    0x1f83_d9ab,
+    0x5be0_cd19,
+];
+
+/// The round constants, which are the first 32 bits of the fractional parts of the cube
+/// roots of the first 64 primes
+pub const ROUND_CONSTANTS: [u32; ROUNDS] = [
+    0x428a_2f98,
+    0x7137_4491,
+    0xb5c0_fbcf,
+    0xe9b5_dba5,
+    0x3956_c25b,
+    0x59f1_11f1,
+    0x923f_82a4,
+    0xab1c_5ed5,
+    0xd807_aa98,
+    0x1283_5b01,
+    0x2431_85be,
+    0x550c_7dc3,
+    0x72be_5d74,
+    0x80de_b1fe,
+    0x9bdc_06a7,
+    0xc19b_f174,
+    0xe49b_69c1,
+    0xefbe_4786,
+    0x0fc1_9dc6,
+    0x240c_a1cc,
+    0x2de9_2c6f,
+    0x4a74_84aa,
+    0x5cb0_a9dc,
+    0x76f9_88da,
+    0x983e_5152,
+    0xa831_c66d,
+    0xb003_27c8,
+    0xbf59_7fc7,
+    0xc6e0_0bf3,
+    0xd5a7_9147,
+    0x06ca_6351,
+    0x1429_2967,
+    0x27b7_0a85,
+    0x2e1b_2138,
+    0x4d2c_6dfc,
+    0x5338_0d13,
+    0x650a_7354,
+    0x766a_0abb,
+    0x81c2_c92e,
+    0x9272_2c85,
+    0xa2bf_e8a1,
+    0xa81a_664b,
+    0xc24b_8b70,
+    0xc76c_51a3,
+    0xd192_e819,
+    0xd699_0624,
+    0xf40e_3585,
+    0x106a_a070,
+    0x19a4_c116,
+    0x1e37_6c08,
+    0x2748_774c,
+    0x34b0_bcb5,
+    0x391c_0cb3,
+    0x4ed8_aa4a,
+    0x5b9c_ca4f,
+    0x682e_6ff3,
+    0x748f_82ee,
+    0x78a5_636f,
+    0x84c8_7814,
+    0x8cc7_0208,
+    0x90be_fffa,
+    0xa450_6ceb,
+    0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+/// Rows taken by sigma_0 and sigma_1 of the message schedule
+const SIGMA_ROWS: usize = 2 * ROTATE_ROWS + SHIFT_ROWS + 2 * XOR_AND_ROWS;
+/// Rows taken by Sigma_0 and Sigma_1 of the rounds
+const BIG_SIGMA_ROWS: usize = 3 * ROTATE_ROWS + 2 * XOR_AND_ROWS;
+/// Rows taken by Ch, as (E & F) + (!E & G)
+const CH_ROWS: usize = 2 * XOR_AND_ROWS + WORD_ROWS + ADD_ROWS;
+/// Rows taken by Maj, as (A & B) + (C & (A ^ B))
+const MAJ_ROWS: usize = 2 * XOR_AND_ROWS + ADD_ROWS;
+/// Rows taken by a word of the message schedule after the words of the block
+const SCHEDULE_WORD_ROWS: usize = 2 * SIGMA_ROWS + 3 * ADD_ROWS;
+/// Rows taken by a round: T1 is the sum of five words, T2 of two, and E and A add T1 to D
+/// and to T2
+const ROUND_ROWS: usize = 2 * BIG_SIGMA_ROWS + CH_ROWS + MAJ_ROWS + 7 * ADD_ROWS;
+
+/// Rows taken by the compression of a block
+pub const COMPRESSION_ROWS: usize =
+    (ROUNDS - BLOCK_SIZE) * SCHEDULE_WORD_ROWS + ROUNDS * ROUND_ROWS + DIGEST_SIZE * ADD_ROWS;
+/// Rows taken by the initial state and the round constants of a digest
+pub const CONSTANTS_ROWS: usize = (DIGEST_SIZE + ROUNDS) * WORD_ROWS;
+
+/// Returns the bytes following a message of `len` bytes in its padded blocks: a 0x80
+/// byte, zero bytes and the bit length of the message as 8 big-endian bytes
+pub fn padding(len: usize) -> Vec<u8> {
+    let zeros = (BLOCK_SIZE_BYTES * 2 - 9 - len % BLOCK_SIZE_BYTES) % BLOCK_SIZE_BYTES;
+    let mut padding = vec![0x80];
+    padding.extend(vec![0; zeros]);
+    padding.extend((len as u64 * 8).to_be_bytes());
+    padding
+}
+
+/// Pads a message into blocks of big-endian words
+pub fn pad_message(message: &[u8]) -> Vec<[u32; BLOCK_SIZE]> {
+    let mut bytes = message.to_vec();
+    bytes.extend(padding(message.len()));
+    bytes
+        .chunks(BLOCK_SIZE_BYTES)
+        .map(|block| {
+            let mut words = [0; BLOCK_SIZE];
+            for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+                *word = u32::from_be_bytes(bytes.try_into().unwrap());
+            }
+            words
+        })
+        .collect()
+}
+
+/// A chip computing SHA-256 digests with a [`BitwiseChip`]
+#[derive(Clone, Debug)]
+pub struct Sha2Chip<F: FieldExt> {
+    bitwise: BitwiseChip<F>,
+}
+
+impl<F: FieldExt> Sha2Chip<F> {
+    pub fn construct(bitwise: BitwiseChip<F>) -> Self {
+        Self { bitwise }
+    }
+
+    /// Returns the digest of the padded blocks of a message
+    pub fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        blocks: &[[Word32<F>; BLOCK_SIZE]],
+    ) -> Result<[Word32<F>; DIGEST_SIZE], Error> {
+        let mut state = IV
+            .iter()
+            .map(|word| self.bitwise.assign_constant(layouter, *word))
+            .collect::<Result<Vec<_>, _>>()?;
+        let round_constants = ROUND_CONSTANTS
+            .iter()
+            .map(|word| self.bitwise.assign_constant(layouter, *word))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for block in blocks {
+            state = self.compress(layouter, &state, block, &round_constants)?;
+        }
+        Ok(state.try_into().unwrap())
+    }
+
+    /// Returns the state after compressing a block
+    fn compress(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        state: &[Word32<F>],
+        block: &[Word32<F>; BLOCK_SIZE],
+        round_constants: &[Word32<F>],
+    ) -> Result<Vec<Word32<F>>, Error> {
+        let bitwise = &self.bitwise;
+
+        // The message schedule
+        let mut w = block.to_vec();
+        for t in BLOCK_SIZE..ROUNDS {
+            let s_0 = self.sigma(layouter, &w[t - 15], [7, 18], 3)?;
+            let s_1 = self.sigma(layouter, &w[t - 2], [17, 19], 10)?;
+            let word = bitwise.sum(layouter, &s_1, &[w[t - 7].clone(), s_0, w[t - 16].clone()])?;
+            w.push(word);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h]: [Word32<F>; DIGEST_SIZE] =
+            state.to_vec().try_into().unwrap();
+        for t in 0..ROUNDS {
+            let s_1 = self.big_sigma(layouter, &e, [6, 11, 25])?;
+            let ch = self.ch(layouter, &e, &f, &g)?;
+            let t_1 = bitwise.sum(
+                layouter,
+                &h,
+                &[s_1, ch, round_constants[t].clone(), w[t].clone()],
+            )?;
+            let s_0 = self.big_sigma(layouter, &a, [2, 13, 22])?;
+            let maj = self.maj(layouter, &a, &b, &c)?;
+            let t_2 = bitwise.add(layouter, &s_0, &maj)?;
+
+            h = g;
+            g = f;
+            f = e;
+            e = bitwise.add(layouter, &d, &t_1)?;
+            d = c;
+            c = b;
+            b = a;
+            a = bitwise.add(layouter, &t_1, &t_2)?;
+        }
+
+        [a, b, c, d, e, f, g, h]
+            .iter()
+            .zip(state)
+            .map(|(word, prev)| bitwise.add(layouter, prev, word))
+            .collect()
+    }
+
+    /// sigma_0 and sigma_1 of the message schedule, which XOR two rotations of X and a
+    /// shift of X
+    fn sigma(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        rotations: [u32; 2],
+        shift: u32,
+    ) -> Result<Word32<F>, Error> {
+        let bitwise = &self.bitwise;
+        let r_0 = bitwise.rotate_right(layouter, x, rotations[0])?;
+        let r_1 = bitwise.rotate_right(layouter, x, rotations[1])?;
+        let s = bitwise.shift_right(layouter, x, shift)?;
+        let xor = bitwise.xor(layouter, &r_0, &r_1)?;
+        bitwise.xor(layouter, &xor, &s)
+    }
+
+    /// Sigma_0 and Sigma_1 of the rounds, which XOR three rotations of X
+    fn big_sigma(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        x: &Word32<F>,
+        rotations: [u32; 3],
+    ) -> Result<Word32<F>, Error> {
+        let bitwise = &self.bitwise;
+        let r_0 = bitwise.rotate_right(layouter, x, rotations[0])?;
+        let r_1 = bitwise.rotate_right(layouter, x, rotations[1])?;
+        let r_2 = bitwise.rotate_right(layouter, x, rotations[2])?;
+        let xor = bitwise.xor(layouter, &r_0, &r_1)?;
+        bitwise.xor(layouter, &xor, &r_2)
+    }
+
+    /// (E & F) ^ (!E & G), which have no bits in common, so their XOR is their sum
+    fn ch(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        e: &Word32<F>,
+        f: &Word32<F>,
+        g: &Word32<F>,
+    ) -> Result<Word32<F>, Error> {
+        let bitwise = &self.bitwise;
+        let e_and_f = bitwise.and(layouter, e, f)?;
+        let not_e = bitwise.not(layouter, e)?;
+        let not_e_and_g = bitwise.and(layouter, &not_e, g)?;
+        bitwise.add(layouter, &e_and_f, &not_e_and_g)
+    }
+
+    /// (A & B) ^ (A & C) ^ (B & C), as (A & B) + (C & (A ^ B)), which have no bits in
+    /// common
+    fn maj(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        a: &Word32<F>,
+        b: &Word32<F>,
+        c: &Word32<F>,
+    ) -> Result<Word32<F>, Error> {
+        let bitwise = &self.bitwise;
+        let (a_xor_b, a_and_b) = bitwise.xor_and(layouter, a, b)?;
+        let c_and_a_xor_b = bitwise.and(layouter, c, &a_xor_b)?;
+        bitwise.add(layouter, &a_and_b, &c_and_a_xor_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pad_message, padding, BLOCK_SIZE_BYTES};
+
+    #[test]
+    fn test_padding() {
+        for len in [0, 3, 55, 56, 63, 64, 119] {
+            let padded = len + padding(len).len();
+            assert_eq!(padded % BLOCK_SIZE_BYTES, 0);
+            assert!(padded - len >= 9 && padded - len <= BLOCK_SIZE_BYTES + 8);
+        }
+        assert_eq!(pad_message(b"abc")[0][0], 0x6162_6380);
+        assert_eq!(pad_message(b"abc")[0][15], 24);
+        assert_eq!(pad_message(&[0; 56]).len(), 2);
+    }
+}
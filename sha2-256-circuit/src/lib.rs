@@ -1,130 +1,279 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
-#![allow(unreachable_code)]
-
-//! A circuit is a layout of columns over multiple rows, capable of building or
-//! defining their own custom constraints. In the [`zkEVM`] architecture, many
-//! such circuits (individually termed as sub-circuits) are placed within a
-//! super-circuit. When a circuit encounters an expensive operation, it can
-//! outsource the verification effort to another circuit through the usage of
-//! lookup arguments.
+//! The circuit of the [`SHA2-256`] precompile, through which the EVM circuit looks up the
+//! digest of the input of each call.
 //!
-//! For instance, the [`EVM-circuit`] would like to verify the output of a call
-//! to the precompiled contract [`SHA2-256`], which in itself is an expensive
-//! operation to verify. So in order to separate out the verification logic and
-//! build a more developer-friendly approach, the EVM circuit would use the
-//! SHA2-256 circuit's table via lookups to communicate simply the input-output
-//! relationship, outsourcing the effort of verifying the relationship itself
-//! to the SHA2-256 circuit.
+//! [`Sha2Chip`] compresses the padded blocks of each input with the gadgets of
+//! [`bitwise_gadgets`], over a spread table that may be shared with the other circuits of
+//! the super-circuit. Each row of [`Sha2Table`] holds a call as the RLCs of its input and
+//! of its digest, which are computed from the words of the padded blocks and of the digest,
+//! the bytes of the blocks after the input being constrained to its padding. The chip lays
+//! out the blocks of each input for its length, so the rows and the lengths of the inputs
+//! are fixed columns, set when the keys are generated.
 //!
-//! In the sha2-256-circuit crate, we export the SHA2-256 circuit's config `Sha2Config`,
-//! and the table within it (that other circuits can use as a lookup argument)
-//! `Sha2Table`. The config type defines the layout of the circuit, the various
-//! named columns in the circuit's layout, and the `configure` method is meant
-//! to define the relationship between those columns over its neighbouring rows.
+//! The public inputs of a call are the words of the padded blocks of its input followed
+//! by the eight words of its digest, as big-endian 32-bit integers.
 //!
-//! For instance, for the `id` field to be an incremental field, one may specify
-//! the following relationship:
-//! ```
-//! # impl<F: FieldExt> Sha2Config<F> {
-//!     pub fn configure(meta: &mut ConstraintSystem<F>, table: Sha2Table) -> Self {
-//!         meta.create_gate("validity check over all rows", |meta| {
-//!             let mut cb = BaseConstraintBuilder::default();
-//!             cb.require_equal(
-//!                 "id field is incremental, i.e. id::cur + 1 == id::next",
-//!                 meta.query_advice(table.id, Rotation::cur()) + 1.expr(),
-//!                 meta.query_advice(table.id, Rotation::next()),
-//!             );
-//!             cb.gate(1.expr()) // enable this gate over all rows.
-//!         });
-//!
-//!         Self {
-//!             table,
-//!             _marker: PhantomData,
-//!         }
-//!     }
-//! # }
-//! ```
-//!
-//! We also describe how the EVM circuit would lookup to the SHA2 circuit via lookup
-//! arguments [`here`]. Currently, the table is a dummy column named `id`.
-//!
-//! The following tasks are expected to be done:
-//! - Define the layout of the SHA2-256 circuit through columns in `Sha2Config`.
-//! - Define the lookup argument exposed by SHA2-256 circuit via `Sha2Table`.
-//! - Define verification logic over rows of the circuit by constraining the relationship
-//!   between the columns.
-//! - Assign witness data to the circuit via the `load` method.
-//! - Test the verification logic in the circuit.
-//!
-//! [`zkEVM`]: https://privacy-scaling-explorations.github.io/zkevm-docs/introduction.html
-//! [`EVM-circuit`]: https://github.com/scroll-tech/zkevm-circuits/blob/scroll-stable/zkevm-circuits/src/evm_circuit.rs
 //! [`SHA2-256`]: https://en.wikipedia.org/wiki/SHA-2#Pseudocode
-//! [`here`]: https://github.com/scroll-tech/zkevm-circuits/pull/398
-
-use std::marker::PhantomData;
 
+use bitwise_gadgets::{
+    rlc::rlc_rows, AssignedBits, BitwiseChip, BitwiseConfig, ByteOrder, Error, RlcConfig,
+    SpreadTable, Word32, SPREAD_TABLE_ROWS, WORD_ROWS,
+};
 use halo2_proofs::{
     arithmetic::FieldExt,
-    circuit::Layouter,
-    plonk::{Advice, Any, Column, ConstraintSystem, Error},
+    circuit::{AssignedCell, Cell, Layouter, Value},
+    plonk::{self, Advice, Any, Challenge, Column, ConstraintSystem, Fixed, SecondPhase},
 };
+use precompile_circuit::PrecompileCircuit;
+use sha2::{Digest, Sha256};
+
+mod chip;
 
+pub use chip::{
+    pad_message, padding, Sha2Chip, BLOCK_SIZE, BLOCK_SIZE_BYTES, COMPRESSION_ROWS, CONSTANTS_ROWS,
+    DIGEST_SIZE, DIGEST_SIZE_BYTES,
+};
+
+/// The columns through which other circuits look up the calls to the SHA2-256
+/// precompile, along with the spread table and the challenge used by the chip
 #[derive(Clone, Debug)]
 pub struct Sha2Table {
-    id: Column<Advice>,
+    /// Whether the row holds a call
+    pub q_enable: Column<Fixed>,
+    /// The length of the input in bytes
+    pub input_len: Column<Fixed>,
+    /// The RLC of the bytes of the input
+    pub input_rlc: Column<Advice>,
+    /// The RLC of the 32 bytes of the digest
+    pub output_rlc: Column<Advice>,
+    /// The spread table looked up by the chip, which may be shared with other chips. The
+    /// circuit that configures it loads it with [`SpreadTable::load`].
+    pub spread_table: SpreadTable,
+    /// The challenge of the RLCs, which should be the one of the EVM circuit
+    pub challenge: Challenge,
 }
 
 impl Sha2Table {
-    pub fn construct<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+    /// Configures the columns of the table. The challenge must be usable after the first
+    /// phase.
+    pub fn construct<F: FieldExt>(
+        meta: &mut ConstraintSystem<F>,
+        spread_table: SpreadTable,
+        challenge: Challenge,
+    ) -> Self {
+        let input_rlc = meta.advice_column_in(SecondPhase);
+        let output_rlc = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(input_rlc);
+        meta.enable_equality(output_rlc);
+
         Self {
-            id: meta.advice_column(),
+            q_enable: meta.fixed_column(),
+            input_len: meta.fixed_column(),
+            input_rlc,
+            output_rlc,
+            spread_table,
+            challenge,
         }
     }
 
     pub fn columns(&self) -> Vec<Column<Any>> {
-        vec![self.id.into()]
+        vec![
+            self.q_enable.into(),
+            self.input_len.into(),
+            self.input_rlc.into(),
+            self.output_rlc.into(),
+        ]
     }
 
     pub fn annotations(&self) -> Vec<String> {
-        vec![String::from("id")]
+        ["q_enable", "input_len", "input_rlc", "output_rlc"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    /// Assigns a row for each call, given the length of its input
+    fn assign<F: FieldExt>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        calls: &[(usize, &Sha2Call<F>)],
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "sha256 table",
+            |mut region| {
+                for (row, (len, call)) in calls.iter().enumerate() {
+                    region.assign_fixed(
+                        || "q_enable",
+                        self.q_enable,
+                        row,
+                        || Value::known(F::one()),
+                    )?;
+                    region.assign_fixed(
+                        || "input_len",
+                        self.input_len,
+                        row,
+                        || Value::known(F::from(*len as u64)),
+                    )?;
+                    call.input_rlc
+                        .copy_advice(|| "input_rlc", &mut region, self.input_rlc, row)?;
+                    call.output_rlc.copy_advice(
+                        || "output_rlc",
+                        &mut region,
+                        self.output_rlc,
+                        row,
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
     }
 }
 
+/// The words of a call, along with the RLCs of its input and of its digest
 #[derive(Clone, Debug)]
-pub struct Sha2Config<F> {
-    table: Sha2Table,
-    _marker: PhantomData<F>,
+pub struct Sha2Call<F: FieldExt> {
+    /// The words of the padded blocks of the input
+    pub words: Vec<Word32<F>>,
+    /// The words of the digest
+    pub digest: [Word32<F>; DIGEST_SIZE],
+    /// The RLC of the bytes of the input
+    pub input_rlc: AssignedCell<F, F>,
+    /// The RLC of the 32 bytes of the digest
+    pub output_rlc: AssignedCell<F, F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha2Config<F: FieldExt> {
+    pub table: Sha2Table,
+    bitwise: BitwiseConfig<F>,
+    rlc: RlcConfig<F>,
 }
 
 impl<F: FieldExt> Sha2Config<F> {
     pub fn configure(meta: &mut ConstraintSystem<F>, table: Sha2Table) -> Self {
+        let bitwise = BitwiseChip::configure_with_spread_table(meta, &table.spread_table);
+        let rlc = RlcConfig::configure(meta, &bitwise, table.challenge);
         Self {
             table,
-            _marker: PhantomData,
+            bitwise,
+            rlc,
         }
     }
-}
 
-#[derive(Clone, Debug)]
-pub struct Sha2Witness<F> {
-    pub inputs: Vec<Vec<u8>>,
-    pub _marker: PhantomData<F>,
+    /// Hashes each input, assigning a row of the table for each of them. The spread
+    /// table is loaded by the circuit that configures it.
+    pub fn assign_calls(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        inputs: &[Vec<u8>],
+    ) -> Result<Vec<Sha2Call<F>>, Error> {
+        let bitwise = BitwiseChip::construct(self.bitwise.clone());
+        let chip = Sha2Chip::construct(bitwise.clone());
+
+        let mut calls = vec![];
+        for input in inputs {
+            let words = pad_message(input)
+                .into_iter()
+                .flatten()
+                .map(|word| bitwise.assign_word(layouter, Value::known(word)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let blocks: Vec<[Word32<F>; BLOCK_SIZE]> = words
+                .chunks(BLOCK_SIZE)
+                .map(|block| block.to_vec().try_into().unwrap())
+                .collect();
+            let digest = chip.digest(layouter, &blocks)?;
+
+            let input_rlc = self.rlc.assign_rlc(
+                layouter,
+                &big_endian(&words),
+                input.len(),
+                &padding(input.len()),
+            )?;
+            let output_rlc =
+                self.rlc
+                    .assign_rlc(layouter, &big_endian(&digest), DIGEST_SIZE_BYTES, &[])?;
+            calls.push(Sha2Call {
+                words,
+                digest,
+                input_rlc,
+                output_rlc,
+            });
+        }
+
+        let rows: Vec<_> = inputs.iter().map(Vec::len).zip(&calls).collect();
+        self.table.assign(layouter, &rows)?;
+        Ok(calls)
+    }
 }
 
-#[derive(Clone, Debug)]
-pub struct Sha2Chip<F> {
-    config: Sha2Config<F>,
-    data: Sha2Witness<F>,
+/// The words along with the order of their bytes in the RLCs
+fn big_endian<F: FieldExt>(words: &[Word32<F>]) -> Vec<(AssignedBits<32, F>, ByteOrder)> {
+    words
+        .iter()
+        .map(|word| (word.word.clone(), ByteOrder::BigEndian))
+        .collect()
 }
 
-impl<F: FieldExt> Sha2Chip<F> {
-    pub fn construct(config: Sha2Config<F>, data: Sha2Witness<F>) -> Self {
-        Self { data, config }
+impl<F: FieldExt> PrecompileCircuit<F> for Sha2Config<F> {
+    type Table = Sha2Table;
+    /// The message
+    type Witness = Vec<u8>;
+    /// The digest
+    type Output = [u8; DIGEST_SIZE_BYTES];
+
+    fn configure(meta: &mut ConstraintSystem<F>, table: Self::Table) -> Self {
+        Sha2Config::configure(meta, table)
     }
 
-    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        Ok(())
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        witnesses: &[Self::Witness],
+    ) -> Result<Vec<Cell>, plonk::Error> {
+        let calls = self.assign_calls(layouter, witnesses)?;
+        Ok(calls
+            .iter()
+            .flat_map(|call| call.words.iter().chain(&call.digest))
+            .map(|word| word.word.cell())
+            .collect())
+    }
+
+    fn public_inputs(witness: &Self::Witness) -> Vec<F> {
+        let digest = Self::compute(witness);
+        pad_message(witness)
+            .into_iter()
+            .flatten()
+            .chain(
+                digest
+                    .chunks(4)
+                    .map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap())),
+            )
+            .map(|word| F::from(word as u64))
+            .collect()
+    }
+
+    fn min_rows(witnesses: &[Self::Witness]) -> usize {
+        // The calls take the columns of the bitwise chip one after the other, while the
+        // table has columns of its own
+        let calls: usize = witnesses
+            .iter()
+            .map(|input| {
+                let blocks = pad_message(input).len();
+                blocks * BLOCK_SIZE * WORD_ROWS
+                    + CONSTANTS_ROWS
+                    + blocks * COMPRESSION_ROWS
+                    + rlc_rows(blocks * BLOCK_SIZE)
+                    + rlc_rows(DIGEST_SIZE)
+            })
+            .sum();
+
+        [calls, SPREAD_TABLE_ROWS, witnesses.len()]
+            .into_iter()
+            .max()
+            .unwrap()
+    }
+
+    fn compute(witness: &Self::Witness) -> Self::Output {
+        Sha256::digest(witness).into()
     }
 }
 
@@ -132,9 +281,13 @@ impl<F: FieldExt> Sha2Chip<F> {
 pub mod dev {
     use super::*;
 
+    use bitwise_gadgets::bytes_rlc;
     use ethers_core::types::H256;
-    use halo2_proofs::{circuit::SimpleFloorPlanner, plonk::Circuit};
-    use std::str::FromStr;
+    use halo2_proofs::{
+        circuit::SimpleFloorPlanner,
+        plonk::{Circuit, FirstPhase},
+    };
+    use std::{marker::PhantomData, str::FromStr};
 
     lazy_static::lazy_static! {
         pub static ref INPUTS_OUTPUTS: (Vec<Vec<u8>>, Vec<H256>) = {
@@ -167,6 +320,7 @@ pub mod dev {
         };
     }
 
+    /// Hashes the inputs, checking the RLCs of the digests against the outputs
     #[derive(Default)]
     pub struct Sha2TestCircuit<F> {
         pub inputs: Vec<Vec<u8>>,
@@ -178,12 +332,19 @@ pub mod dev {
         type Config = Sha2Config<F>;
         type FloorPlanner = SimpleFloorPlanner;
 
+        /// The layout depends on the length of each input, so the inputs are kept
         fn without_witnesses(&self) -> Self {
-            Self::default()
+            Self {
+                inputs: self.inputs.clone(),
+                outputs: self.outputs.clone(),
+                _marker: PhantomData,
+            }
         }
 
         fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let sha2_table = Sha2Table::construct(meta);
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            let spread_table = SpreadTable::configure(meta);
+            let sha2_table = Sha2Table::construct(meta, spread_table, challenge);
             Sha2Config::configure(meta, sha2_table)
         }
 
@@ -191,25 +352,81 @@ pub mod dev {
             &self,
             config: Self::Config,
             mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
-            let chip = Sha2Chip::construct(
-                config,
-                Sha2Witness {
-                    inputs: self.inputs.clone(),
-                    _marker: PhantomData,
-                },
-            );
-            chip.load(&mut layouter)
+        ) -> Result<(), plonk::Error> {
+            config.table.spread_table.load(&mut layouter)?;
+            let calls = config.assign_calls(&mut layouter, &self.inputs)?;
+
+            // The RLCs are the ones of the input and of the digest of each call
+            let challenge = layouter.get_challenge(config.table.challenge);
+            for ((call, input), output) in calls.iter().zip(&self.inputs).zip(&self.outputs) {
+                call.input_rlc
+                    .value()
+                    .zip(challenge)
+                    .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(input, *r));
+                call.output_rlc
+                    .value()
+                    .zip(challenge)
+                    .assert_if_known(|(rlc, r)| **rlc == bytes_rlc(&output.0, *r));
+            }
+            Ok(())
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use bitwise_gadgets::{SpreadTable, SPREAD_TABLE_ROWS};
+    use halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, Column, ConstraintSystem, Error, FirstPhase, Instance},
+    };
+    use precompile_circuit::PrecompileCircuit;
     use std::marker::PhantomData;
 
-    use crate::dev::{Sha2TestCircuit, INPUTS_OUTPUTS};
+    use crate::{
+        dev::{Sha2TestCircuit, INPUTS_OUTPUTS},
+        Sha2Config, Sha2Table, BLOCK_SIZE, DIGEST_SIZE,
+    };
+
+    /// Hashes the inputs through [`PrecompileCircuit`], exposing their public inputs
+    struct PrecompileTestCircuit {
+        inputs: Vec<Vec<u8>>,
+    }
+
+    impl Circuit<Fr> for PrecompileTestCircuit {
+        type Config = (Sha2Config<Fr>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            PrecompileTestCircuit {
+                inputs: self.inputs.clone(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let instance = meta.instance_column();
+            meta.enable_equality(instance);
+            let challenge = meta.challenge_usable_after(FirstPhase);
+            let spread_table = SpreadTable::configure(meta);
+            let table = Sha2Table::construct(meta, spread_table, challenge);
+            (PrecompileCircuit::configure(meta, table), instance)
+        }
+
+        fn synthesize(
+            &self,
+            (config, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.table.spread_table.load(&mut layouter)?;
+            let cells = config.assign(&mut layouter, &self.inputs)?;
+            for (row, cell) in cells.into_iter().enumerate() {
+                layouter.constrain_instance(cell, instance, row)?;
+            }
+            Ok(())
+        }
+    }
 
     #[test]
     fn test_sha2_circuit() {
@@ -221,8 +438,59 @@ mod tests {
             _marker: PhantomData,
         };
 
-        let k = 8;
+        let k = 17;
         let prover = MockProver::run(k, &circuit, vec![]).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_compute() {
+        let (inputs, outputs) = INPUTS_OUTPUTS.clone();
+        for (input, output) in inputs.iter().zip(outputs) {
+            assert_eq!(Sha2Config::<Fr>::compute(input), output.0);
+        }
+    }
+
+    #[test]
+    fn test_public_inputs() {
+        // "abc" takes a block, whose first word holds the bytes of the input and the 0x80 of
+        // the padding
+        let public_inputs = Sha2Config::<Fr>::public_inputs(&b"abc".to_vec());
+        assert_eq!(public_inputs.len(), BLOCK_SIZE + DIGEST_SIZE);
+        assert_eq!(public_inputs[0], Fr::from(0x61626380));
+        assert_eq!(public_inputs[BLOCK_SIZE], Fr::from(0xba7816bf));
+    }
+
+    #[test]
+    fn test_min_rows() {
+        // A few messages fit next to the spread table
+        let (inputs, _) = INPUTS_OUTPUTS.clone();
+        assert_eq!(Sha2Config::<Fr>::min_rows(&inputs), SPREAD_TABLE_ROWS);
+
+        let inputs = vec![vec![0; 55]; 7];
+        assert!(Sha2Config::<Fr>::min_rows(&inputs) > SPREAD_TABLE_ROWS);
+    }
+
+    #[test]
+    fn test_wrong_public_inputs() {
+        let circuit = PrecompileTestCircuit {
+            inputs: vec![b"abc".to_vec()],
+        };
+        let public_inputs = Sha2Config::<Fr>::public_inputs(&circuit.inputs[0]);
+        let prover = MockProver::run(17, &circuit, vec![public_inputs.clone()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // A wrong word of the digest, or of the padded input
+        for idx in [BLOCK_SIZE + DIGEST_SIZE - 1, 0, BLOCK_SIZE - 1] {
+            let mut public_inputs = public_inputs.clone();
+            public_inputs[idx] += Fr::one();
+            let failures = MockProver::run(17, &circuit, vec![public_inputs])
+                .unwrap()
+                .verify()
+                .unwrap_err();
+            assert!(failures
+                .iter()
+                .any(|failure| matches!(failure, VerifyFailure::Permutation { .. })));
+        }
+    }
 }
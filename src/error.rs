@@ -0,0 +1,32 @@
+//! The error type of the super-circuit.
+
+use std::fmt;
+
+/// An error returned when building a [`crate::SuperCircuit`] from its calls
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A call was made to an address that is not one of the supported precompiles
+    UnknownAddress { address: u64 },
+    /// A call was made with an input that the precompile rejects, such as an input of
+    /// BLAKE2F that is not 213 bytes long
+    InvalidInput { address: u64 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownAddress { address } => {
+                write!(f, "no precompile at address {:#x}", address)
+            }
+            Error::InvalidInput { address } => {
+                write!(
+                    f,
+                    "invalid input to the precompile at address {:#x}",
+                    address
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,5 +1,15 @@
+mod error;
+mod super_circuit;
+
+pub use error::Error;
+pub use super_circuit::{Precompile, PrecompileCall, SuperCircuit, SuperCircuitConfig};
+
+pub use bitwise_gadgets;
+
 pub use blake2f_circuit;
 
+pub use precompile_circuit;
+
 pub use ripemd160_circuit;
 
 pub use sha2_256_circuit;
@@ -0,0 +1,383 @@
+//! The super-circuit, which holds the circuits of the SHA2-256, RIPEMD-160 and BLAKE2F
+//! precompiles and verifies their calls in one proof.
+//!
+//! Each call is tagged by the address of its precompile and holds its input as the EVM
+//! passes it. [`SuperCircuit::new`] sorts the calls by precompile, and each precompile
+//! circuit assigns all of its calls through [`PrecompileCircuit::assign`]. The sub-circuits
+//! share the challenge of the RLCs and the spread table, which is loaded once.
+//!
+//! The inputs and outputs of the calls are the public inputs of the super-circuit, in one
+//! instance column, which [`SuperCircuit::public_inputs`] computes for the verifier. They
+//! are grouped by precompile, in the order of [`Precompile::ALL`], since each circuit
+//! assigns its calls together.
+
+use std::marker::PhantomData;
+
+use blake2f_circuit::{Blake2fConfig, Blake2fTable, Blake2fWitness};
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, Column, ConstraintSystem, Error as PlonkError, FirstPhase, Instance},
+};
+use precompile_circuit::PrecompileCircuit;
+use ripemd160_circuit::{Ripemd160Table, SpreadTable, Table16Config};
+use sha2_256_circuit::{Sha2Config, Sha2Table};
+
+use crate::Error;
+
+/// A precompiled contract verified by the super-circuit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Precompile {
+    Sha256,
+    Ripemd160,
+    Blake2f,
+}
+
+impl Precompile {
+    /// The precompiles with a circuit in the super-circuit
+    pub const ALL: [Precompile; 3] = [
+        Precompile::Sha256,
+        Precompile::Ripemd160,
+        Precompile::Blake2f,
+    ];
+
+    /// Returns the address of the precompile
+    pub fn address(&self) -> u64 {
+        match self {
+            Precompile::Sha256 => 0x02,
+            Precompile::Ripemd160 => 0x03,
+            Precompile::Blake2f => 0x09,
+        }
+    }
+
+    /// Returns the precompile at `address`, if the super-circuit has a circuit for it
+    pub fn from_address(address: u64) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|precompile| precompile.address() == address)
+    }
+}
+
+/// A call to a precompile, tagged by its address
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrecompileCall {
+    pub address: u64,
+    pub input: Vec<u8>,
+}
+
+impl PrecompileCall {
+    pub fn new(precompile: Precompile, input: Vec<u8>) -> Self {
+        Self {
+            address: precompile.address(),
+            input,
+        }
+    }
+}
+
+/// The configs of the precompile circuits
+#[derive(Clone, Debug)]
+pub struct SuperCircuitConfig<F: FieldExt> {
+    pub sha256: Sha2Config<F>,
+    pub ripemd160: Table16Config<F>,
+    pub blake2f: Blake2fConfig<F>,
+    /// The spread table shared by the sub-circuits, which the super-circuit loads
+    pub spread_table: SpreadTable,
+    /// The public inputs of the calls
+    pub instance: Column<Instance>,
+}
+
+/// A circuit verifying a list of calls to the precompiles
+#[derive(Clone, Debug)]
+pub struct SuperCircuit<F> {
+    calls: Vec<PrecompileCall>,
+    sha256: Vec<Vec<u8>>,
+    ripemd160: Vec<Vec<u8>>,
+    blake2f: Vec<Blake2fWitness>,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> SuperCircuit<F> {
+    /// Sorts the calls by precompile, failing on a call to an unknown address or with an
+    /// input that the precompile rejects
+    pub fn new(calls: Vec<PrecompileCall>) -> Result<Self, Error> {
+        let mut sha256 = vec![];
+        let mut ripemd160 = vec![];
+        let mut blake2f = vec![];
+        for call in calls.iter() {
+            match Precompile::from_address(call.address) {
+                Some(Precompile::Sha256) => sha256.push(call.input.clone()),
+                Some(Precompile::Ripemd160) => ripemd160.push(call.input.clone()),
+                Some(Precompile::Blake2f) => {
+                    let witness =
+                        Blake2fWitness::from_input(&call.input).ok_or(Error::InvalidInput {
+                            address: call.address,
+                        })?;
+                    blake2f.push(witness);
+                }
+                None => {
+                    return Err(Error::UnknownAddress {
+                        address: call.address,
+                    })
+                }
+            }
+        }
+        Ok(Self {
+            calls,
+            sha256,
+            ripemd160,
+            blake2f,
+            _marker: PhantomData,
+        })
+    }
+
+    pub fn calls(&self) -> &[PrecompileCall] {
+        &self.calls
+    }
+
+    /// Computes the output of each call natively, in the order of the calls
+    pub fn outputs(&self) -> Vec<Vec<u8>> {
+        self.calls
+            .iter()
+            .map(|call| match Precompile::from_address(call.address) {
+                Some(Precompile::Sha256) => Sha2Config::<F>::compute(&call.input).to_vec(),
+                Some(Precompile::Ripemd160) => Table16Config::<F>::compute(&call.input).to_vec(),
+                Some(Precompile::Blake2f) => {
+                    let witness = Blake2fWitness::from_input(&call.input)
+                        .expect("the input is checked by SuperCircuit::new");
+                    Blake2fConfig::<F>::compute(&witness).to_vec()
+                }
+                None => unreachable!("the address is checked by SuperCircuit::new"),
+            })
+            .collect()
+    }
+
+    /// Returns the public inputs of the calls, grouped by precompile in the order of
+    /// [`Precompile::ALL`], and in the order of the calls within each precompile
+    pub fn public_inputs(&self) -> Vec<F> {
+        let sha256 = self.sha256.iter().flat_map(Sha2Config::<F>::public_inputs);
+        let ripemd160 = self
+            .ripemd160
+            .iter()
+            .flat_map(Table16Config::<F>::public_inputs);
+        let blake2f = self
+            .blake2f
+            .iter()
+            .flat_map(Blake2fConfig::<F>::public_inputs);
+        sha256.chain(ripemd160).chain(blake2f).collect()
+    }
+
+    /// Returns the number of rows needed to assign the calls, not counting the blinding
+    /// rows
+    pub fn min_rows(&self) -> usize {
+        // The sub-circuits have columns of their own, and the public inputs take a row each
+        // of the instance column
+        [
+            Sha2Config::<F>::min_rows(&self.sha256),
+            Table16Config::<F>::min_rows(&self.ripemd160),
+            Blake2fConfig::<F>::min_rows(&self.blake2f),
+            self.public_inputs().len(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+    }
+
+    /// Returns the smallest `k` such that the calls fit in a circuit of `2^k` rows
+    pub fn min_k(&self) -> u32 {
+        let mut meta = ConstraintSystem::default();
+        Self::configure(&mut meta);
+        let rows = self.min_rows() + meta.blinding_factors() + 1;
+        (1..).find(|k| 1_usize << k >= rows).unwrap()
+    }
+}
+
+impl<F: FieldExt> Circuit<F> for SuperCircuit<F> {
+    type Config = SuperCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    /// The layout depends on the length of each input, so the calls are kept
+    fn without_witnesses(&self) -> Self {
+        self.clone()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        let spread_table = SpreadTable::configure(meta);
+
+        let sha256_table = Sha2Table::construct(meta, spread_table.clone(), challenge);
+        let ripemd160_table = Ripemd160Table::construct(meta, spread_table.clone(), challenge);
+        let blake2f_table = Blake2fTable::construct(meta, spread_table.clone(), challenge);
+
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        SuperCircuitConfig {
+            sha256: PrecompileCircuit::configure(meta, sha256_table),
+            ripemd160: PrecompileCircuit::configure(meta, ripemd160_table),
+            blake2f: PrecompileCircuit::configure(meta, blake2f_table),
+            spread_table,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), PlonkError> {
+        config.spread_table.load(&mut layouter)?;
+        let mut cells = config
+            .sha256
+            .assign(&mut layouter.namespace(|| "sha256"), &self.sha256)?;
+        cells.extend(
+            config
+                .ripemd160
+                .assign(&mut layouter.namespace(|| "ripemd160"), &self.ripemd160)?,
+        );
+        cells.extend(
+            config
+                .blake2f
+                .assign(&mut layouter.namespace(|| "blake2f"), &self.blake2f)?,
+        );
+        for (row, cell) in cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.instance, row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitwise_gadgets::tamper::harness::{
+        failed_constraint, failed_permutation, verify_tampered_with_instances, Tamper,
+    };
+    use halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+
+    use super::{Precompile, PrecompileCall, SuperCircuit};
+    use crate::{blake2f_circuit, ripemd160_circuit, sha2_256_circuit, Error};
+
+    /// Returns calls to RIPEMD-160 with the test vectors, and their outputs
+    fn ripemd160_calls() -> (Vec<PrecompileCall>, Vec<Vec<u8>>) {
+        let (inputs, outputs) = ripemd160_circuit::dev::INPUTS_OUTPUTS.clone();
+        let calls = inputs
+            .into_iter()
+            .map(|input| PrecompileCall::new(Precompile::Ripemd160, input))
+            .collect();
+        (
+            calls,
+            outputs.iter().map(|output| output.0.to_vec()).collect(),
+        )
+    }
+
+    /// Returns calls to the three precompiles, interleaved, and their outputs
+    fn mixed_calls() -> (Vec<PrecompileCall>, Vec<Vec<u8>>) {
+        let (sha256_inputs, sha256_outputs) = sha2_256_circuit::dev::INPUTS_OUTPUTS.clone();
+        let (blake2f_inputs, blake2f_outputs) = blake2f_circuit::dev::INPUTS_OUTPUTS.clone();
+        let (mut calls, mut outputs) = ripemd160_calls();
+
+        calls.insert(
+            1,
+            PrecompileCall::new(Precompile::Blake2f, blake2f_inputs[0].to_input()),
+        );
+        outputs.insert(1, blake2f_outputs[0].0.to_vec());
+        for idx in [0, 1] {
+            calls.insert(
+                2 * idx,
+                PrecompileCall::new(Precompile::Sha256, sha256_inputs[idx].clone()),
+            );
+            outputs.insert(2 * idx, sha256_outputs[idx].0.to_vec());
+        }
+        (calls, outputs)
+    }
+
+    #[test]
+    fn test_address() {
+        for precompile in Precompile::ALL {
+            assert_eq!(
+                Precompile::from_address(precompile.address()),
+                Some(precompile)
+            );
+        }
+        assert_eq!(Precompile::from_address(0x01), None);
+    }
+
+    #[test]
+    fn test_outputs() {
+        let (calls, outputs) = ripemd160_calls();
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+        assert_eq!(circuit.outputs(), outputs);
+
+        let (calls, outputs) = mixed_calls();
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+        assert_eq!(circuit.outputs(), outputs);
+    }
+
+    #[test]
+    fn test_invalid_calls() {
+        let ecrecover = PrecompileCall {
+            address: 0x01,
+            input: vec![0; 128],
+        };
+        assert_eq!(
+            SuperCircuit::<Fr>::new(vec![ecrecover]).unwrap_err(),
+            Error::UnknownAddress { address: 0x01 }
+        );
+
+        // BLAKE2F only takes inputs of 213 bytes
+        let (mut calls, _) = mixed_calls();
+        calls.push(PrecompileCall::new(Precompile::Blake2f, vec![0; 212]));
+        assert_eq!(
+            SuperCircuit::<Fr>::new(calls).unwrap_err(),
+            Error::InvalidInput { address: 0x09 }
+        );
+    }
+
+    #[test]
+    fn test_super_circuit() {
+        let (calls, _) = ripemd160_calls();
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+        let prover =
+            MockProver::run(circuit.min_k(), &circuit, vec![circuit.public_inputs()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_mixed_calls() {
+        let (calls, _) = mixed_calls();
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+        let prover =
+            MockProver::run(circuit.min_k(), &circuit, vec![circuit.public_inputs()]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_public_inputs() {
+        let (calls, _) = mixed_calls();
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+
+        // The last public input is the high half of the last word of the output of the
+        // BLAKE2F call, and the first one the first word of the first SHA2-256 call
+        let len = circuit.public_inputs().len();
+        for idx in [len - 1, 0] {
+            let mut public_inputs = circuit.public_inputs();
+            public_inputs[idx] += Fr::from(1);
+            let prover = MockProver::run(circuit.min_k(), &circuit, vec![public_inputs]).unwrap();
+            assert!(failed_permutation(&prover.verify().unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn test_tampered_ripemd160_call() {
+        let (calls, _) = ripemd160_calls();
+        let circuit = SuperCircuit::<Fr>::new(calls).unwrap();
+
+        // A wrong carry of the first step of the first call changes its digest
+        let failures = verify_tampered_with_instances(
+            circuit.min_k(),
+            &circuit,
+            vec![circuit.public_inputs()],
+            vec![Tamper::xor("carry_t", 0, 1)],
+        );
+        assert!(failed_constraint(&failures, "s_step", "t"));
+    }
+}